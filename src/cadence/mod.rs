@@ -0,0 +1,232 @@
+use crate::models::Release;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The releases detected less than this number of hours apart count as a single release, e.g. a batch of chapters.
+const SAME_RELEASE_HOURS: i64 = 24;
+/// The number of intervals between releases needed to estimate the cadence.
+const MIN_INTERVALS: usize = 2;
+/// The share of intervals that must be close to the usual one for the cadence to be regular.
+const REGULAR_SHARE: f32 = 2.0 / 3.0;
+
+/// How often a manga gets a new chapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    /// The releases don't follow any of the other cadences.
+    Irregular,
+}
+
+impl fmt::Display for Cadence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cadence::Weekly => write!(f, "weekly"),
+            Cadence::Biweekly => write!(f, "biweekly"),
+            Cadence::Monthly => write!(f, "monthly"),
+            Cadence::Irregular => write!(f, "irregular"),
+        }
+    }
+}
+
+/// The release cadence of a manga, estimated from its recorded releases.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReleaseEstimate {
+    pub cadence: Cadence,
+    /// The usual number of days between two releases, i.e. the median interval.
+    pub interval_days: f32,
+    pub last_release: DateTime<Utc>,
+    /// When the next chapter should come out, None if the cadence is irregular. It is in the past if the release is late.
+    pub next_release: Option<DateTime<Utc>>,
+}
+
+impl ReleaseEstimate {
+    fn interval(&self) -> Duration {
+        Duration::minutes((self.interval_days * 24.0 * 60.0) as i64)
+    }
+
+    /// Describes the cadence and the next release, e.g. `weekly, next release expected on 2024-05-01`.
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        match self.next_release {
+            Some(next) if next < now => format!(
+                "{}, next release late since {}",
+                self.cadence,
+                next.format("%Y-%m-%d")
+            ),
+            Some(next) => format!(
+                "{}, next release expected on {}",
+                self.cadence,
+                next.format("%Y-%m-%d")
+            ),
+            None => format!(
+                "{}, about every {:.0} day(s)",
+                self.cadence, self.interval_days
+            ),
+        }
+    }
+}
+
+/// Estimates the release cadence of a manga from the dates its chapters have been detected.
+/// # Arguments:
+/// * `dates`: the dates of the releases of the manga, in any order.
+/// # Returns:
+/// The estimate, None if there aren't enough releases yet.
+pub fn estimate_cadence(dates: &[DateTime<Utc>]) -> Option<ReleaseEstimate> {
+    let mut dates = dates.to_vec();
+    dates.sort();
+    let mut releases: Vec<DateTime<Utc>> = Vec::new();
+    for date in dates {
+        match releases.last() {
+            Some(last) if date - *last < Duration::hours(SAME_RELEASE_HOURS) => {}
+            _ => releases.push(date),
+        }
+    }
+    let mut intervals: Vec<f32> = releases
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_minutes() as f32 / (24.0 * 60.0))
+        .collect();
+    if intervals.len() < MIN_INTERVALS {
+        return None;
+    }
+    intervals.sort_by(f32::total_cmp);
+    let median = intervals[intervals.len() / 2];
+    let close = intervals
+        .iter()
+        .filter(|interval| (**interval - median).abs() <= median * 0.4)
+        .count();
+    let cadence = if (close as f32) < intervals.len() as f32 * REGULAR_SHARE {
+        Cadence::Irregular
+    } else if (5.0..=9.0).contains(&median) {
+        Cadence::Weekly
+    } else if (12.0..=17.0).contains(&median) {
+        Cadence::Biweekly
+    } else if (25.0..=38.0).contains(&median) {
+        Cadence::Monthly
+    } else {
+        Cadence::Irregular
+    };
+    let mut estimate = ReleaseEstimate {
+        cadence,
+        interval_days: median,
+        last_release: *releases.last()?,
+        next_release: None,
+    };
+    if cadence != Cadence::Irregular {
+        estimate.next_release = Some(estimate.last_release + estimate.interval());
+    }
+    Some(estimate)
+}
+
+/// Estimates the release cadence of every manga with enough recorded releases.
+/// # Returns:
+/// The estimates, by URL of the manga.
+pub fn estimate_all(releases: &[Release]) -> HashMap<String, ReleaseEstimate> {
+    let mut dates: HashMap<&str, Vec<DateTime<Utc>>> = HashMap::new();
+    for release in releases {
+        dates
+            .entry(release.manga_url.as_str())
+            .or_default()
+            .push(release.detected_at);
+    }
+    dates
+        .into_iter()
+        .filter_map(|(url, dates)| Some((url.to_owned(), estimate_cadence(&dates)?)))
+        .collect()
+}
+
+/// Computes when a manga should be checked next, from its release cadence.
+/// Without a regular cadence, or once the next release is close, it is checked at every interval.
+/// Otherwise, it is checked every eighth of its cadence, to catch the early releases, until the next one gets close.
+/// # Arguments:
+/// * `estimate`: the release cadence of the manga, if known.
+/// * `checked_at`: when the manga has been checked.
+/// * `interval`: the delay between two checks of the mangas without a regular cadence.
+/// # Returns:
+/// The time of the next check.
+pub fn next_check(
+    estimate: Option<&ReleaseEstimate>,
+    checked_at: DateTime<Utc>,
+    interval: Duration,
+) -> DateTime<Utc> {
+    let Some((estimate, next_release)) =
+        estimate.and_then(|estimate| Some((estimate, estimate.next_release?)))
+    else {
+        return checked_at + interval;
+    };
+    // The next release is expected within a quarter of the cadence.
+    let close = next_release - estimate.interval() / 4;
+    if checked_at >= close {
+        checked_at + interval
+    } else {
+        (checked_at + interval.max(estimate.interval() / 8)).min(close)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn days(days: &[i64]) -> Vec<DateTime<Utc>> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        days.iter()
+            .map(|day| start + Duration::days(*day))
+            .collect()
+    }
+
+    #[test]
+    fn cadence_is_estimated_from_the_releases() {
+        let weekly = estimate_cadence(&days(&[0, 7, 14, 22, 28])).unwrap();
+        assert_eq!(weekly.cadence, Cadence::Weekly);
+        assert_eq!(weekly.interval_days, 7.0);
+        assert_eq!(weekly.next_release, Some(days(&[35])[0]));
+
+        // The chapters released together count once.
+        let biweekly = estimate_cadence(&days(&[28, 0, 14, 14, 42])).unwrap();
+        assert_eq!(biweekly.cadence, Cadence::Biweekly);
+        assert_eq!(biweekly.last_release, days(&[42])[0]);
+
+        let monthly = estimate_cadence(&days(&[0, 30, 61, 90])).unwrap();
+        assert_eq!(monthly.cadence, Cadence::Monthly);
+
+        let irregular = estimate_cadence(&days(&[0, 3, 30, 33, 80])).unwrap();
+        assert_eq!(irregular.cadence, Cadence::Irregular);
+        assert_eq!(irregular.next_release, None);
+
+        assert_eq!(estimate_cadence(&days(&[0, 7])), None);
+    }
+
+    #[test]
+    fn regular_mangas_are_checked_less_often() {
+        let interval = Duration::hours(2);
+        let monthly = estimate_cadence(&days(&[0, 30, 60])).unwrap();
+        let start = days(&[60])[0];
+        // A monthly manga just released is checked every few days.
+        assert_eq!(
+            next_check(Some(&monthly), start, interval),
+            start + Duration::minutes(30 * 24 * 60 / 8)
+        );
+        // Until its next release is close.
+        let checked_at = days(&[81])[0];
+        assert_eq!(
+            next_check(Some(&monthly), checked_at, interval),
+            days(&[90])[0] - Duration::minutes(30 * 24 * 60 / 4)
+        );
+        let checked_at = days(&[85])[0];
+        assert_eq!(
+            next_check(Some(&monthly), checked_at, interval),
+            checked_at + interval
+        );
+
+        let irregular = estimate_cadence(&days(&[0, 3, 30, 33, 80])).unwrap();
+        assert_eq!(
+            next_check(Some(&irregular), start, interval),
+            start + interval
+        );
+        assert_eq!(next_check(None, start, interval), start + interval);
+    }
+}
@@ -1,36 +1,298 @@
-use crate::file_ops::is_url_present;
-use crate::file_ops::write_file::append_to_file;
-use std::path::PathBuf;
-use crate::scraper::find_last_chapter;
+use crate::file_ops::read_csv;
+use crate::file_ops::write_file::update_csv;
+use crate::models::{CSVLine, MangaChapter};
+use crate::output::{AddOutcome, AdditionReport, CommandReport, LibraryChange, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS};
+use crate::scraper::{concurrency, create_client, find_last_chapter};
+use crate::urls::{extract_urls, manga_page_url, same_manga};
+use crate::utils::ScraperError;
+use futures::stream::{self, StreamExt};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use reqwest::Client;
+use std::fs;
+use std::future::Future;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
 /// Adds a new manga to the CSV file.
 /// If the manga is already present, an error message is shown.
 /// If the CSV file is not present,  another error message is shown.
-/// The function searches for the last chapter at the time, and adds it to the CSV with the URL.
+/// The function searches for the last chapter at the time, and adds it to the CSV with the URL of the page of the manga, even if the URL is the one of a chapter (See [`crate::urls::manga_page_url`]).
 /// # Arguments:
 /// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
 /// * `manga_url`: The Manganelo URL to the manga page.
-pub async fn add_new_manga(path: Option<PathBuf>,  manga_url: &str, verbose: bool) {
-    match is_url_present(path.clone(),manga_url) {
-        Ok(is_present) => {
-            if !is_present {
-                match find_last_chapter(manga_url, None, &verbose).await {
-                    Ok(last_chapter) => {
-                        match append_to_file(path, manga_url, last_chapter.num, last_chapter.manga_title.as_str()) {
-                            Ok(_) => println!("The manga has been added."),
-                            Err(e) => eprintln!("Error during the add : {}", e)
-                        }
-                    },
-                    Err(e) => eprintln!("Error during the add : {}", e)
-                }
-
-            } else {
-                println!("The manga is already present!");
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub async fn add_new_manga(path: Option<PathBuf>,  manga_url: &str, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("add");
+    match create_client() {
+        Ok(client) => add_to_library(path, manga_url, &client, verbose, &mut report).await,
+        Err(e) => report.error(None, format!("Error while creating the client: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Adds a new manga to the CSV file, and fills the report with the change or the error.
+/// The URL goes through the same checks as the ones of a text (See [`add_from_text`]), but an unsupported URL is an error.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file.
+/// * `manga_url`: The Manganelo URL to the manga page.
+/// * `client`: the client to fetch the page with.
+/// * `verbose`: if true, more messages will be shown.
+/// * `report`: the report to fill.
+pub async fn add_to_library(path: Option<PathBuf>, manga_url: &str, client: &Client, verbose: bool, report: &mut CommandReport) {
+    let fetch = move |page: String| async move { find_last_chapter(&page, Some(client), &verbose).await };
+    if let Err(e) = add_urls(&path, vec![manga_url.to_owned()], fetch, verbose, report).await {
+        let hint = if e.kind() == io::ErrorKind::NotFound { ". Try running manga-updater init or specify the path with -p." } else { "" };
+        report.error(None, format!("An error occurred! {}{}", e, hint));
+        return;
+    }
+    match report.additions.last().map(|addition| addition.outcome) {
+        Some(AddOutcome::Added) => report.message = Some("The manga has been added.".to_owned()),
+        Some(AddOutcome::Duplicate) => report.message = Some("The manga is already present!".to_owned()),
+        Some(AddOutcome::Unsupported) => report.error(Some(manga_url), "The URL isn't the page of a manga from a supported website (Manganato, Manganelo)."),
+        // The reason is already in the errors.
+        Some(AddOutcome::Failed) | None => {}
+    }
+}
+
+/// Adds every manga whose URL is found in a text, e.g. pasted bookmarks, read from a file or from stdin.
+/// Only the URLs of the supported websites are added, each as the page of its manga (See [`crate::urls::manga_page_url`]), and the ones already in the library are skipped.
+/// The pages are fetched concurrently with a shared client, and the library is written once, so that `undo` reverts the whole batch.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `from`: the file to read the text from, or None to read stdin.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if some mangas couldn't be added, [`EXIT_FATAL`] if no URL was found or the library couldn't be read or written.
+pub async fn add_from_text(path: Option<PathBuf>, from: Option<&Path>, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("add");
+    let text = match from {
+        Some(file) => fs::read_to_string(file),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map(|_| text)
+        }
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let urls = extract_urls(&text);
+    if urls.is_empty() {
+        report.error(None, "No URL found in the text.");
+        report.print(format);
+        return EXIT_FATAL;
+    }
+    let client = match create_client() {
+        Ok(client) => client,
+        Err(e) => {
+            report.error(None, format!("Error while creating the client: {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let client = &client;
+    let fetch = move |page: String| async move { find_last_chapter(&page, Some(client), &verbose).await };
+    if let Err(e) = add_urls(&path, urls, fetch, verbose, &mut report).await {
+        report.error(None, format!("An error occurred! {}", e));
+        report.print(format);
+        return EXIT_FATAL;
+    }
+
+    if format.is_table() {
+        print_additions(&report.additions);
+    }
+    let count = |outcome: AddOutcome| report.additions.iter().filter(|addition| addition.outcome == outcome).count();
+    report.message = Some(format!(
+        "{} added, {} duplicate(s), {} unsupported, {} failed.",
+        count(AddOutcome::Added),
+        count(AddOutcome::Duplicate),
+        count(AddOutcome::Unsupported),
+        count(AddOutcome::Failed)
+    ));
+    report.print(format);
+    if report.errors.is_empty() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Adds the mangas of the URLs to the library, and tells what happened to each URL in the additions of the report.
+/// The URLs of the unsupported websites are left out, and the ones of the mangas already in the library, or given before, are duplicates.
+/// The other pages are fetched concurrently, and the mangas found are written at once.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file.
+/// * `urls`: the URLs to add, in the order they were given.
+/// * `fetch`: finds the last chapter of the manga of a page, e.g. [`find_last_chapter`] with a shared client.
+/// * `verbose`: if true, more messages will be shown.
+/// * `report`: the report to fill. The pages that couldn't be fetched are added to its errors.
+/// # Returns:
+/// An error if the library couldn't be read or written.
+async fn add_urls<F, Fut>(path: &Option<PathBuf>, urls: Vec<String>, fetch: F, verbose: bool, report: &mut CommandReport) -> Result<(), io::Error>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<MangaChapter, ScraperError>>,
+{
+    let mut lines = read_csv(path, &verbose)?;
+    // The mangas to fetch, along with the index of their URL in the additions.
+    let mut to_fetch: Vec<(usize, String)> = Vec::new();
+    for url in urls {
+        let outcome = match manga_page_url(&url) {
+            None => AddOutcome::Unsupported,
+            Some(page) if lines.iter().any(|line| same_manga(&line.url, &page))
+                || to_fetch.iter().any(|(_, other)| same_manga(other, &page)) => AddOutcome::Duplicate,
+            Some(page) => {
+                to_fetch.push((report.additions.len(), page));
+                AddOutcome::Added
+            }
+        };
+        report.additions.push(AdditionReport { url, outcome, title: None, reason: None });
+    }
+    if verbose {
+        println!("Fetching the pages of {} manga(s)...", to_fetch.len());
+    }
+    let results: Vec<_> = stream::iter(to_fetch)
+        .map(|(index, page)| {
+            let found = fetch(page.clone());
+            async move { (index, page, found.await) }
+        })
+        .buffered(concurrency())
+        .collect()
+        .await;
+    let before = lines.clone();
+    for (index, page, result) in results {
+        let addition = &mut report.additions[index];
+        match result {
+            Ok(chapter) => {
+                addition.title = Some(chapter.manga_title.clone());
+                lines.push(CSVLine {
+                    url: page,
+                    last_chapter_num: chapter.num,
+                    title: chapter.manga_title,
+                    status: Default::default(),
+                    score: None,
+                });
+            }
+            Err(e) => {
+                addition.outcome = AddOutcome::Failed;
+                addition.reason = Some(e.reason.clone());
+                report.error(Some(&page), format!("Error during the add of {}: {}", page, e.reason));
             }
         }
-        Err(_) => {
-            eprintln!("Error: the file is not present. Try running manga-updater init or specify the path with -p.")
+    }
+    let changes = LibraryChange::diff(&before, &lines);
+    if !changes.is_empty() {
+        update_csv(path, lines)?;
+        report.changes.extend(changes);
+    }
+    Ok(())
+}
+
+/// Prints what happened to each URL, in the order of the text.
+fn print_additions(additions: &[AdditionReport]) {
+    for addition in additions {
+        let outcome = format!("[{}]", addition.outcome);
+        let outcome = match addition.outcome {
+            AddOutcome::Added => outcome.if_supports_color(Stdout, |t| t.green()).to_string(),
+            AddOutcome::Duplicate => outcome.if_supports_color(Stdout, |t| t.yellow()).to_string(),
+            AddOutcome::Unsupported => outcome.if_supports_color(Stdout, |t| t.dimmed()).to_string(),
+            AddOutcome::Failed => outcome.if_supports_color(Stdout, |t| t.red()).to_string(),
+        };
+        match &addition.title {
+            Some(title) => println!("{} {} ({})", outcome, title, addition.url),
+            None => println!("{} {}", outcome, addition.url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_ops::write_file::create_file;
+    use crate::test_utils::line;
+    use serial_test::serial;
+
+    /// Finds the manga of the pages ending with a number, and fails on the other ones, without any request.
+    async fn fetch(page: String) -> Result<MangaChapter, ScraperError> {
+        match page.chars().last().and_then(|c| c.to_digit(10)) {
+            Some(num) => Ok(MangaChapter {
+                manga_title: format!("Manga {}", num),
+                url: format!("{}/chapter-{}", page, num),
+                chapter_title: format!("Chapter {}", num),
+                num: num as f32,
+                cover: None,
+            }),
+            None => Err(ScraperError { reason: "The page can't be reached.".to_owned() }),
         }
     }
 
-}
\ No newline at end of file
+    #[tokio::test]
+    #[serial]
+    async fn urls_of_a_text_are_added() -> Result<(), io::Error> {
+        let path = Some(PathBuf::from("test_add.csv"));
+        create_file(&path)?;
+        let current = CSVLine {
+            title: "Current".to_owned(),
+            ..line("https://manganato.com/manga-ab12", 4.0)
+        };
+        update_csv(&path, vec![current.clone()])?;
+        let text = "To read:\n\
+            - https://chapmanganato.to/manga-ab1/chapter-3 (not ab12!)\n\
+            - http://www.manganato.com/manga-ab12/\n\
+            - https://readmanganato.com/manga-ab1\n\
+            - https://example.com/series/one\n\
+            - https://manganato.com/manga-cd";
+        let mut report = CommandReport::new("add");
+        add_urls(&path, extract_urls(text), fetch, false, &mut report).await?;
+
+        let outcomes: Vec<AddOutcome> = report.additions.iter().map(|addition| addition.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![AddOutcome::Added, AddOutcome::Duplicate, AddOutcome::Duplicate, AddOutcome::Unsupported, AddOutcome::Failed]
+        );
+        assert_eq!(report.additions[0].title.as_deref(), Some("Manga 1"));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.changes.len(), 1);
+        // The chapter URL is stored as the page of its manga.
+        let added = CSVLine {
+            title: "Manga 1".to_owned(),
+            ..line("https://chapmanganato.to/manga-ab1", 1.0)
+        };
+        assert_eq!(read_csv(&path, &false)?, vec![current, added]);
+
+        // Nothing is written when nothing is added.
+        let mut report = CommandReport::new("add");
+        add_urls(&path, vec!["https://manganato.com/manga-ab1".to_owned()], fetch, false, &mut report).await?;
+        assert_eq!(report.additions[0].outcome, AddOutcome::Duplicate);
+        assert!(report.changes.is_empty());
+
+        fs::remove_file("test_add.csv")?;
+        fs::remove_file("test_add.csv.bak")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn error_of_the_library_is_reported() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_add_error_test");
+        fs::create_dir_all(&dir)?;
+        let client = create_client().unwrap();
+        let mut report = CommandReport::new("add");
+        // A folder can't be read as the library.
+        add_to_library(Some(dir.clone()), "https://manganato.com/manga-ab1", &client, false, &mut report).await;
+        assert_eq!(report.errors.len(), 1);
+        assert!(!report.errors[0].reason.contains("init"));
+
+        let mut report = CommandReport::new("add");
+        add_to_library(Some(dir.join("missing.csv")), "https://manganato.com/manga-ab1", &client, false, &mut report).await;
+        assert!(report.errors[0].reason.contains("init"));
+        fs::remove_dir_all(dir)
+    }
+}
@@ -0,0 +1,184 @@
+use crate::file_ops::read_csv;
+use crate::output::{EXIT_FATAL, EXIT_SUCCESS};
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::clap::{App, Shell};
+
+/// The name of the executable, as called by the completion scripts.
+const BIN_NAME: &str = "manga_updater";
+
+/// The commands whose argument is an entry of the library, given by its position.
+/// The options choosing the library, `-p/--path`, `--profile` and `-c/--config`, are forwarded to `complete-entries`.
+const ENTRY_COMMANDS: &str = "download info open package remove unread update";
+
+/// Completes the entries in bash. Bash can't describe the candidates, so only the positions are offered.
+const BASH_ENTRIES: &str = r#"
+_manga_updater_with_entries() {
+    local i path_args=() profile_args=() config_args=()
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            -p|--path) path_args=(--path "${COMP_WORDS[i+1]}") ;;
+            --profile) profile_args=(--profile "${COMP_WORDS[i+1]}") ;;
+            -c|--config) config_args=(--config "${COMP_WORDS[i+1]}") ;;
+        esac
+    done
+    case " {commands} " in
+        *" ${COMP_WORDS[COMP_CWORD-1]} "*)
+            local entries
+            entries=$(manga_updater "${path_args[@]}" "${profile_args[@]}" "${config_args[@]}" complete-entries 2>/dev/null | cut -f1)
+            COMPREPLY=($(compgen -W "$entries" -- "${COMP_WORDS[COMP_CWORD]}"))
+            return 0
+            ;;
+    esac
+    _manga_updater "$@"
+}
+
+complete -F _manga_updater_with_entries -o bashdefault -o default manga_updater
+"#;
+
+/// Completes the entries in zsh, with their titles as descriptions.
+const ZSH_ENTRIES: &str = r#"
+_manga_updater_with_entries() {
+    local i
+    local -a path_args profile_args config_args entries commands=({commands})
+    for ((i = 2; i < CURRENT; i++)); do
+        case "${words[i]}" in
+            -p|--path) path_args=(--path "${words[i+1]}") ;;
+            --profile) profile_args=(--profile "${words[i+1]}") ;;
+            -c|--config) config_args=(--config "${words[i+1]}") ;;
+        esac
+    done
+    if (( ${commands[(Ie)${words[CURRENT-1]}]} )); then
+        entries=("${(@f)$(manga_updater "${path_args[@]}" "${profile_args[@]}" "${config_args[@]}" complete-entries 2>/dev/null)}")
+        entries=("${entries[@]/$'\t'/:}")
+        _describe -t entries 'entry' entries
+        return
+    fi
+    _manga_updater "$@"
+}
+
+_manga_updater_with_entries "$@"
+"#;
+
+/// Completes the entries in fish, with their titles as descriptions.
+const FISH_ENTRIES: &str = r#"
+function __manga_updater_entries
+    set -l tokens (commandline -opc)
+    set -l path_args
+    set -l profile_args
+    set -l config_args
+    for i in (seq (count $tokens))
+        test $i -lt (count $tokens); or continue
+        if contains -- $tokens[$i] -p --path
+            set path_args --path $tokens[(math $i + 1)]
+        else if contains -- $tokens[$i] --profile
+            set profile_args --profile $tokens[(math $i + 1)]
+        else if contains -- $tokens[$i] -c --config
+            set config_args --config $tokens[(math $i + 1)]
+        end
+    end
+    manga_updater $path_args $profile_args $config_args complete-entries 2>/dev/null
+end
+
+complete -c manga_updater -n "__fish_seen_subcommand_from {commands}" -f -a "(__manga_updater_entries)"
+"#;
+
+/// Prints the completion script of the shell.
+/// # Arguments:
+/// * `app`: the definition of the command line, from which the static completions are generated.
+/// * `shell`: the shell to complete, bash, zsh or fish.
+/// # Returns:
+/// The exit code of the command.
+pub fn print_completions(app: App, shell: Shell) -> i32 {
+    let script = completion_script(app, shell);
+    match io::stdout().write_all(script.as_bytes()) {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("An error occurred : {}", e);
+            EXIT_FATAL
+        }
+    }
+}
+
+/// Generates the completion script of the shell.
+/// The static completions of the commands and options are generated by clap.
+/// The entries of the library are completed dynamically, by calling the hidden `complete-entries` command.
+/// # Arguments:
+/// * `app`: the definition of the command line.
+/// * `shell`: the shell to complete.
+/// # Returns:
+/// The script to source, or to install in the completion folder of the shell.
+pub fn completion_script(mut app: App, shell: Shell) -> String {
+    let mut generated = Vec::new();
+    app.gen_completions_to(BIN_NAME, shell, &mut generated);
+    let mut script = String::from_utf8_lossy(&generated).into_owned();
+    match shell {
+        Shell::Bash => script.push_str(&BASH_ENTRIES.replace("{commands}", ENTRY_COMMANDS)),
+        Shell::Zsh => {
+            // The generated script ends by calling its function, which must now go through the entries first.
+            let call = format!("_{} \"$@\"", BIN_NAME);
+            if let Some(index) = script.rfind(&call) {
+                script.truncate(index);
+            }
+            script.push_str(&ZSH_ENTRIES.replace("{commands}", ENTRY_COMMANDS));
+        }
+        Shell::Fish => script.push_str(&FISH_ENTRIES.replace("{commands}", ENTRY_COMMANDS)),
+        _ => {}
+    }
+    script
+}
+
+/// Prints the position and the title of each entry, separated by a tab.
+/// Used by the completion scripts, so the errors are only printed to stderr.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// # Returns:
+/// The exit code of the command.
+pub fn print_entries(path: Option<PathBuf>) -> i32 {
+    match read_csv(&path, &false) {
+        Ok(lines) => {
+            for (index, line) in lines.iter().enumerate() {
+                println!("{}\t{}", index + 1, line.title);
+            }
+            EXIT_SUCCESS
+        }
+        Err(e) => {
+            eprintln!("An error occurred : {}", e);
+            EXIT_FATAL
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::clap::SubCommand;
+
+    fn app() -> App<'static, 'static> {
+        App::new(BIN_NAME)
+            .subcommand(SubCommand::with_name("list"))
+            .subcommand(SubCommand::with_name("open"))
+    }
+
+    #[test]
+    fn scripts_complete_the_entries() {
+        let bash = completion_script(app(), Shell::Bash);
+        assert!(bash.contains("_manga_updater()"));
+        assert!(bash.contains("case \" download info open package remove unread update \" in"));
+        assert!(bash.contains("--profile) profile_args=(--profile \"${COMP_WORDS[i+1]}\") ;;"));
+        assert!(bash.contains("-c|--config) config_args=(--config \"${COMP_WORDS[i+1]}\") ;;"));
+        assert!(bash.ends_with("complete -F _manga_updater_with_entries -o bashdefault -o default manga_updater\n"));
+
+        let zsh = completion_script(app(), Shell::Zsh);
+        assert!(zsh.starts_with("#compdef manga_updater"));
+        assert!(zsh.contains("commands=(download info open package remove unread update)"));
+        assert!(zsh.contains("manga_updater \"${path_args[@]}\" \"${profile_args[@]}\" \"${config_args[@]}\" complete-entries"));
+        assert!(zsh.ends_with("_manga_updater_with_entries \"$@\"\n"));
+        assert!(!zsh.contains("\n_manga_updater \"$@\"\n"));
+
+        let fish = completion_script(app(), Shell::Fish);
+        assert!(fish.contains("manga_updater $path_args $profile_args $config_args complete-entries"));
+        assert!(fish.contains("__fish_seen_subcommand_from download info open package remove unread update"));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::email::{build_digest, send_digest, DigestPeriod, EmailConfig};
+use crate::file_ops::releases::read_releases;
+use crate::output::{CommandReport, OutputFormat};
+use chrono::Utc;
+use std::path::PathBuf;
+
+/// Sends an email listing the chapters released during the period.
+/// The chapters are taken from the releases file, filled by the list and watch commands.
+/// No email is sent if there are no new chapters.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `email`: the SMTP settings. If None, an error is reported.
+/// * `period`: the period to cover.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub async fn send_email_digest(
+    path: Option<PathBuf>,
+    email: Option<EmailConfig>,
+    period: DigestPeriod,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("email-digest");
+    match email {
+        Some(config) => {
+            if let Err(e) = send_period_digest(&path, &config, period, &mut report).await {
+                report.error(None, e);
+            }
+        }
+        None => report.error(
+            None,
+            "The email settings are missing. Please add an [email] section to the config file.",
+        ),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Builds and sends the digest of the period, and tells the outcome in the report message.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file.
+/// * `config`: the SMTP settings.
+/// * `period`: the period to cover.
+/// * `report`: the report to fill.
+/// # Returns:
+/// An error message if the releases couldn't be read or the email couldn't be sent.
+pub async fn send_period_digest(
+    path: &Option<PathBuf>,
+    config: &EmailConfig,
+    period: DigestPeriod,
+    report: &mut CommandReport,
+) -> Result<(), String> {
+    let releases = read_releases(path).map_err(|e| e.to_string())?;
+    match build_digest(&releases, period, Utc::now()) {
+        Some(digest) => {
+            send_digest(config, &digest).await?;
+            report.message = Some(format!(
+                "The {} digest of {} chapters has been sent to {}.",
+                period,
+                digest.chapters,
+                config.to.join(", ")
+            ));
+        }
+        None => {
+            report.message = Some(format!(
+                "No new chapters for the {} digest, no email has been sent.",
+                period
+            ))
+        }
+    }
+    Ok(())
+}
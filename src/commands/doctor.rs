@@ -0,0 +1,282 @@
+use crate::doctor::{
+    is_stale_backup, merge_duplicates, read_rows, Issue, IssueKind, STALE_BACKUP_DAYS,
+};
+use crate::file_ops::extract_path_or_default;
+use crate::file_ops::save::backup_path;
+use crate::file_ops::write_file::update_csv;
+use crate::models::CSVLine;
+use crate::output::{
+    CommandReport, LibraryChange, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::scraper::{check_manga_page, concurrency, create_client, PageError};
+use futures::stream::{self, StreamExt};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Checks the library for problems, and tells how to fix them:
+/// - the malformed lines, which the other commands can't read,
+/// - the mangas in the library more than once, under different forms of their URL,
+/// - the pages that can't be reached, or that don't match the selectors anymore,
+/// - the last chapters read above the last chapter online,
+/// - a backup much older than the library, which `undo` would restore.
+///
+/// With `fix`, the fixes that lose nothing are applied: the duplicates are merged into their first line keeping the highest chapter read,
+/// the unknown statuses and scores are reset, and the stale backup is removed.
+/// The library isn't rewritten while some of its lines can't be read, as they would be lost.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `fix`: if true, the safe fixes are applied.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if some problems are left, or if a check or a fix failed.
+pub async fn run_doctor(
+    path: Option<PathBuf>,
+    fix: bool,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("doctor");
+    let library = extract_path_or_default(&path);
+    let (rows, mut issues) = match fs::File::open(&library).and_then(read_rows) {
+        Ok(rows) => rows,
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let lines: Vec<(usize, CSVLine)> = rows
+        .iter()
+        .filter_map(|row| Some((row.position, row.line.clone()?)))
+        .collect();
+    let readable = lines.len() == rows.len();
+    let (merged, duplicates) = merge_duplicates(&lines);
+    // The duplicates are the same mangas as their first lines, so their pages aren't checked again.
+    let kept: Vec<(usize, CSVLine)> = lines
+        .iter()
+        .filter(|(position, _)| {
+            !duplicates
+                .iter()
+                .any(|issue| issue.position == Some(*position))
+        })
+        .cloned()
+        .collect();
+    issues.extend(duplicates);
+    if verbose {
+        println!("Checking the pages of {} manga(s)...", kept.len());
+    }
+    match check_pages(&kept).await {
+        Ok(problems) => issues.extend(problems),
+        Err(e) => report.error(None, format!("The pages couldn't be checked: {}", e)),
+    }
+    let backup = backup_path(&library);
+    issues.extend(check_backup(&library, &backup));
+    issues.sort_by_key(|issue| issue.position.unwrap_or(usize::MAX));
+
+    if fix {
+        apply_fixes(
+            &path,
+            &backup,
+            readable,
+            &lines,
+            merged,
+            &mut issues,
+            &mut report,
+        );
+    }
+    if format.is_table() {
+        print_issues(&issues);
+    }
+    let left = issues.iter().filter(|issue| !issue.fixed).count();
+    report.message = Some(if issues.is_empty() {
+        "No problems found.".to_owned()
+    } else {
+        let fixable = issues
+            .iter()
+            .filter(|issue| issue.fixable && !issue.fixed)
+            .count();
+        let hint = if fixable > 0 {
+            format!(" {} can be fixed with --fix.", fixable)
+        } else {
+            String::new()
+        };
+        format!(
+            "{} problem(s) found, {} fixed.{}",
+            issues.len(),
+            issues.len() - left,
+            hint
+        )
+    });
+    report.issues = issues;
+    report.print(format);
+    if report.errors.is_empty() && left == 0 {
+        EXIT_SUCCESS
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Fetches the page of every manga, and reports the ones that can't be reached or scraped, and the chapters read ahead of the website.
+async fn check_pages(lines: &[(usize, CSVLine)]) -> Result<Vec<Issue>, reqwest::Error> {
+    let client = create_client()?;
+    let results: Vec<_> = stream::iter(lines)
+        .map(|(position, line)| {
+            let client = &client;
+            async move { (*position, line, check_manga_page(&line.url, client).await) }
+        })
+        .buffered(concurrency())
+        .collect()
+        .await;
+    Ok(results
+        .into_iter()
+        .filter_map(|(position, line, result)| match result {
+            Ok(chapter) if chapter.num < line.last_chapter_num => Some(Issue::new(
+                IssueKind::AheadOfSite,
+                Some(position),
+                Some(&line.url),
+                format!(
+                    "The last chapter read of {}, #{}, is above the last chapter online, #{}.",
+                    line.title, line.last_chapter_num, chapter.num
+                ),
+                format!(
+                    "If the chapters have been removed from the website, set it back with `update {}`.",
+                    line.url
+                ),
+                false,
+            )),
+            Ok(_) => None,
+            Err(PageError::Unreachable(reason)) => Some(Issue::new(
+                IssueKind::Unreachable,
+                Some(position),
+                Some(&line.url),
+                format!("The page of {} can't be reached: {}.", line.title, reason),
+                format!(
+                    "Open the URL in a browser: if the manga has moved, `remove {}` and add its new URL.",
+                    line.url
+                ),
+                false,
+            )),
+            Err(PageError::Layout(e)) => Some(Issue::new(
+                IssueKind::LayoutChanged,
+                Some(position),
+                Some(&line.url),
+                format!(
+                    "The page of {} doesn't match the selectors: {}",
+                    line.title, e.reason
+                ),
+                "Check that the URL is the page of the manga. If every manga is affected, the website has changed its layout and the program must be updated.",
+                false,
+            )),
+        })
+        .collect())
+}
+
+/// Reports the backup if it is stale (See [`is_stale_backup`]).
+fn check_backup(library: &Path, backup: &Path) -> Option<Issue> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    let (Ok(library_time), Ok(backup_time)) = (modified(library), modified(backup)) else {
+        return None;
+    };
+    is_stale_backup(library_time, backup_time).then(|| {
+        Issue::new(
+            IssueKind::StaleBackup,
+            None,
+            None,
+            format!(
+                "The backup {} is more than {} days older than the library: `undo` would revert every change since then.",
+                backup.display(),
+                STALE_BACKUP_DAYS
+            ),
+            "Remove the backup, a new one is made at the next change.",
+            true,
+        )
+    })
+}
+
+/// Applies the safe fixes, and marks the issues as fixed.
+/// The library is rewritten with the merged lines if a line must be fixed, which also replaces the backup.
+fn apply_fixes(
+    path: &Option<PathBuf>,
+    backup: &Path,
+    readable: bool,
+    lines: &[(usize, CSVLine)],
+    merged: Vec<CSVLine>,
+    issues: &mut [Issue],
+    report: &mut CommandReport,
+) {
+    let in_library = |issue: &Issue| {
+        issue.fixable && matches!(issue.kind, IssueKind::MalformedRow | IssueKind::Duplicate)
+    };
+    let mut rewritten = false;
+    if issues.iter().any(in_library) {
+        if !readable {
+            report.error(
+                None,
+                "The library hasn't been rewritten, as the lines that can't be read would be lost: fix them by hand first.",
+            );
+        } else {
+            let before: Vec<CSVLine> = lines.iter().map(|(_, line)| line.clone()).collect();
+            let changes = LibraryChange::diff(&before, &merged);
+            match update_csv(path, merged) {
+                Ok(()) => {
+                    report.changes = changes;
+                    rewritten = true;
+                }
+                Err(e) => report.error(None, format!("Error while writing the library: {}", e)),
+            }
+        }
+    }
+    for issue in issues.iter_mut().filter(|issue| issue.fixable) {
+        issue.fixed = match issue.kind {
+            IssueKind::StaleBackup => rewritten || remove_backup(backup, report).is_ok(),
+            _ => rewritten,
+        };
+    }
+}
+
+/// Removes the backup, reporting the error if any.
+fn remove_backup(backup: &Path, report: &mut CommandReport) -> Result<(), io::Error> {
+    fs::remove_file(backup).inspect_err(|e| {
+        report.error(
+            None,
+            format!("Error while removing {}: {}", backup.display(), e),
+        )
+    })
+}
+
+/// Prints the issues, each one followed by its fix.
+fn print_issues(issues: &[Issue]) {
+    for issue in issues {
+        let place = match issue.position {
+            Some(position) => format!("Line {}: ", position),
+            None => String::new(),
+        };
+        println!(
+            "[{}] {}{}",
+            issue.kind.if_supports_color(Stdout, |t| t.red()),
+            place,
+            issue.problem
+        );
+        if issue.fixed {
+            println!(
+                "  {} {}",
+                "Fixed:".if_supports_color(Stdout, |t| t.green()),
+                issue.suggestion
+            );
+        } else {
+            let label = if issue.fixable {
+                "Fix (--fix):"
+            } else {
+                "Fix:"
+            };
+            println!(
+                "  {} {}",
+                label.if_supports_color(Stdout, |t| t.yellow()),
+                issue.suggestion
+            );
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::downloads::{
+    chapter_path, existing_page, extract_downloads_path, part_dir, save_page, write_cbz, ComicInfo,
+};
+use crate::file_ops::read_csv;
+use crate::models::{CSVLine, ChapterLink};
+use crate::output::{
+    CommandReport, DownloadReport, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::scraper::{
+    concurrency, create_client, download_image, find_chapter_images, find_chapters,
+};
+use crate::urls::same_manga;
+use crate::utils::ScraperError;
+use futures::stream::{self, StreamExt};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use reqwest::Client;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Downloads chapters of a manga as CBZ archives, to read them offline.
+/// Without bounds, the chapters after the last one read are downloaded.
+/// The chapters already downloaded are skipped, and the pages of an interrupted download are kept, so running the command again resumes it.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `target`: the URL or the line number of the manga.
+/// * `from`: the first chapter to download, if any.
+/// * `to`: the last chapter to download, if any.
+/// * `download_dir`: the folder of the downloads given in the config, if any (See [`crate::downloads::extract_downloads_path`]).
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if some chapters couldn't be downloaded.
+pub async fn download_chapters(
+    path: Option<PathBuf>,
+    target: &str,
+    from: Option<f32>,
+    to: Option<f32>,
+    download_dir: Option<PathBuf>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("download");
+    let line = match read_csv(&path, &verbose) {
+        Ok(lines) => match find_line(lines, target) {
+            Some(line) => line,
+            None => {
+                report.error(None, "The manga you asked for is not in the library (the list command may be helpful).");
+                report.print(format);
+                return report.exit_code();
+            }
+        },
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let client = match create_client() {
+        Ok(client) => client,
+        Err(e) => {
+            report.error(None, format!("Error while creating the client: {}", e));
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let chapters = match find_chapters(&line.url, &client).await {
+        Ok(chapters) => select_chapters(chapters, &line, from, to),
+        Err(e) => {
+            report.error(Some(&line.url), e.reason);
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let dir = extract_downloads_path(&path, &download_dir);
+    for chapter in &chapters {
+        match download_chapter(&client, &dir, &line, chapter, verbose).await {
+            Ok(download) => {
+                if format.is_table() {
+                    let status = if download.already_downloaded {
+                        "Already downloaded"
+                    } else {
+                        "Downloaded"
+                    };
+                    println!(
+                        "{} {}: {}",
+                        status.if_supports_color(Stdout, |t| t.green()),
+                        chapter.title,
+                        download.file
+                    );
+                }
+                report.downloads.push(download);
+            }
+            Err(e) => report.error(Some(&chapter.url), e.reason),
+        }
+    }
+    let downloaded = report
+        .downloads
+        .iter()
+        .filter(|download| !download.already_downloaded)
+        .count();
+    report.message = Some(if chapters.is_empty() {
+        format!("No chapters to download for {}.", line.title)
+    } else {
+        format!(
+            "{} chapter(s) downloaded, {} already downloaded, {} failed.",
+            downloaded,
+            report.downloads.len() - downloaded,
+            report.errors.len()
+        )
+    });
+    report.print(format);
+    if report.errors.is_empty() {
+        EXIT_SUCCESS
+    } else if report.downloads.is_empty() {
+        EXIT_FATAL
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Finds a manga of the library by its line number, starting from 1, or by its URL.
+pub(super) fn find_line(lines: Vec<CSVLine>, target: &str) -> Option<CSVLine> {
+    match target.parse::<usize>() {
+        Ok(position) => lines.into_iter().nth(position.checked_sub(1)?),
+        Err(_) => lines.into_iter().find(|line| same_manga(&line.url, target)),
+    }
+}
+
+/// Keeps the chapters to download: the ones between the bounds, or the unread ones if there are none.
+fn select_chapters(
+    chapters: Vec<ChapterLink>,
+    line: &CSVLine,
+    from: Option<f32>,
+    to: Option<f32>,
+) -> Vec<ChapterLink> {
+    chapters
+        .into_iter()
+        .filter(|chapter| match (from, to) {
+            (None, None) => chapter.num > line.last_chapter_num,
+            (from, to) => {
+                from.is_none_or(|from| chapter.num >= from) && to.is_none_or(|to| chapter.num <= to)
+            }
+        })
+        .collect()
+}
+
+/// Downloads the pages of a chapter, then packs them in an archive.
+/// The pages already downloaded by an interrupted download are reused.
+/// # Errors:
+/// A ScraperError if the chapter or some of its pages couldn't be downloaded, or if the archive couldn't be written.
+async fn download_chapter(
+    client: &Client,
+    dir: &Path,
+    line: &CSVLine,
+    chapter: &ChapterLink,
+    verbose: bool,
+) -> Result<DownloadReport, ScraperError> {
+    let archive = chapter_path(dir, &line.title, chapter.num);
+    let report = |pages: usize, already_downloaded: bool| DownloadReport {
+        title: line.title.clone(),
+        chapter_num: chapter.num,
+        chapter_url: chapter.url.clone(),
+        file: archive.display().to_string(),
+        pages,
+        already_downloaded,
+    };
+    if archive.exists() {
+        return Ok(report(0, true));
+    }
+    let images = find_chapter_images(&chapter.url, client).await?;
+    let pages_dir = part_dir(&archive);
+    fs::create_dir_all(&pages_dir).map_err(|e| write_error(&pages_dir, e))?;
+    if verbose {
+        println!(
+            "Downloading the {} pages of {}...",
+            images.len(),
+            chapter.title
+        );
+    }
+    let results: Vec<Result<PathBuf, ScraperError>> = stream::iter(images.iter().enumerate())
+        .map(|(index, url)| download_page(client, &pages_dir, index, url, &chapter.url))
+        .buffered(concurrency())
+        .collect()
+        .await;
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    let mut pages: Vec<PathBuf> = Vec::new();
+    for result in results {
+        match result {
+            Ok(page) => pages.push(page),
+            Err(e) => {
+                return Err(ScraperError {
+                    reason: format!(
+                    "{} page(s) of {} couldn't be downloaded, run the command again to resume: {}",
+                    failed, chapter.title, e.reason
+                ),
+                })
+            }
+        }
+    }
+    let info = ComicInfo {
+        series: &line.title,
+        number: chapter.num,
+        title: &chapter.title,
+        page_count: pages.len(),
+        web: &chapter.url,
+    };
+    write_cbz(&archive, &pages, &info).map_err(|e| write_error(&archive, e))?;
+    fs::remove_dir_all(&pages_dir).map_err(|e| write_error(&pages_dir, e))?;
+    Ok(report(pages.len(), false))
+}
+
+/// Downloads a page of a chapter, unless an interrupted download already did.
+async fn download_page(
+    client: &Client,
+    pages_dir: &Path,
+    index: usize,
+    url: &str,
+    chapter_url: &str,
+) -> Result<PathBuf, ScraperError> {
+    if let Some(page) = existing_page(pages_dir, index) {
+        return Ok(page);
+    }
+    let (bytes, content_type) = download_image(url, chapter_url, client).await?;
+    save_page(pages_dir, index, &bytes, &content_type).map_err(|e| write_error(pages_dir, e))
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> ScraperError {
+    ScraperError {
+        reason: format!("Error while writing {}: {}", path.display(), e),
+    }
+}
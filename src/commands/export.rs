@@ -1,18 +1,165 @@
-use crate::file_ops::write_file::export_file;
+use crate::commands::list::fetch_chapters;
+use crate::exporters::html::{embed_covers, write_html};
+use crate::exporters::markdown::write_markdown;
+use crate::exporters::opml::write_opml;
+use crate::exporters::{filter_lines, write_json, ExportFormat};
+use crate::file_ops::write_file::{export_file, write_lines};
+use crate::file_ops::{extract_path_or_default, read_csv};
+use crate::importers::{anilist, mal};
+use crate::models::{CSVLine, ReadingStatus};
+use crate::output::{CommandReport, EntryReport, OutputFormat, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS};
+use crate::scraper::create_client;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
 use std::path::PathBuf;
 
-/// Exports the CSV file to a folder given in parameter.
+/// Exports the CSV file to a folder given in parameter, or writes the library in another format.
+/// Without a format, an output file or a status, the CSV file is copied as is, as in the previous versions.
 /// # Arguments:
 /// * `original_path`: The path to the source file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
-/// * `to`: The folder in which the CSV file will be copied. If not present, an error message will be displayed.
-pub fn export_data(original_path: Option<PathBuf>, to: Option<PathBuf>) {
-    match to {
-        Some(mut path) => match export_file(original_path, &mut path) {
-            Ok(result) => println!("File has been exported to {}", result.display()),
-            Err(e) => eprintln!("An error occured: {}", e),
-        },
-        None => {
-            eprintln!("Error: no path provided. Usage: export [path]. The file will be created.")
+/// * `to`: The folder in which the CSV file will be copied. For the other formats, it is the file to write, as `output`.
+/// * `output`: The file to write. Defaults to a file next to the CSV file, named after the format (See [`ExportFormat::extension`]).
+/// * `export_format`: the format of the exported file.
+/// * `statuses`: only the mangas having one of these statuses are exported. If empty, all of them are.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command. Some mangas can't be fetched for the HTML page, in which case it is written without their latest chapters and covers, and the command exits with [`EXIT_PARTIAL_FAILURE`].
+pub async fn export_data(
+    original_path: Option<PathBuf>,
+    to: Option<PathBuf>,
+    output: Option<PathBuf>,
+    export_format: ExportFormat,
+    statuses: Vec<ReadingStatus>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("export");
+    if export_format == ExportFormat::Csv && output.is_none() && statuses.is_empty() {
+        match to {
+            Some(mut path) => match export_file(original_path, &mut path) {
+                Ok(result) => {
+                    report.message = Some(format!("File has been exported to {}", result.display()))
+                }
+                Err(e) => report.error(None, format!("An error occured: {}", e)),
+            },
+            None => report.error(
+                None,
+                "Error: no path provided. Usage: export [path]. The file will be created.",
+            ),
         }
+        report.print(format);
+        return report.exit_code();
     }
+    match export_lines(
+        original_path,
+        to,
+        output,
+        export_format,
+        &statuses,
+        &mut report,
+        verbose,
+    )
+    .await
+    {
+        Ok(path) => {
+            report.message = Some(format!("File has been exported to {}", path.display()));
+            report.print(format);
+            // The mangas that couldn't be fetched are still in the page.
+            if report.errors.is_empty() {
+                EXIT_SUCCESS
+            } else {
+                EXIT_PARTIAL_FAILURE
+            }
+        }
+        Err(e) => {
+            report.error(None, format!("An error occured: {}", e));
+            report.print(format);
+            report.exit_code()
+        }
+    }
+}
+
+/// Reads and filters the lines, then writes them to the file.
+/// # Returns:
+/// The path of the written file.
+async fn export_lines(
+    original_path: Option<PathBuf>,
+    to: Option<PathBuf>,
+    output: Option<PathBuf>,
+    export_format: ExportFormat,
+    statuses: &[ReadingStatus],
+    report: &mut CommandReport,
+    verbose: bool,
+) -> Result<PathBuf, io::Error> {
+    let lines = filter_lines(read_csv(&original_path, &verbose)?, statuses);
+    let path = match (export_format, output, to) {
+        (_, Some(output), _) => output,
+        (ExportFormat::Csv, None, Some(mut folder)) => {
+            folder.push("mangas.csv");
+            folder
+        }
+        (ExportFormat::Csv, None, None) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "no path provided. Use -o [file] to choose the file to write.",
+            ))
+        }
+        (_, None, Some(file)) => file,
+        (_, None, None) => {
+            extract_path_or_default(&original_path).with_extension(export_format.extension())
+        }
+    };
+    write_export(&path, lines, export_format, report, verbose).await?;
+    Ok(path)
+}
+
+/// Writes the lines to the file, in the format.
+/// For the HTML page, the pages of the mangas are fetched for their latest chapters and their covers; the ones that can't be fetched are added to the errors of the report.
+async fn write_export(
+    path: &PathBuf,
+    lines: Vec<(usize, CSVLine)>,
+    export_format: ExportFormat,
+    report: &mut CommandReport,
+    verbose: bool,
+) -> Result<(), io::Error> {
+    let entries = || -> Vec<EntryReport> {
+        lines
+            .iter()
+            .map(|(position, line)| EntryReport::from_line(*position, line))
+            .collect()
+    };
+    let csv_lines = || -> Vec<CSVLine> { lines.iter().map(|(_, line)| line.clone()).collect() };
+    let contents = match export_format {
+        ExportFormat::Csv => return write_lines(&Some(path.clone()), csv_lines()),
+        ExportFormat::Json => write_json(&entries())?,
+        ExportFormat::Markdown => write_markdown(&entries()),
+        ExportFormat::Opml => write_opml(&entries()),
+        ExportFormat::Mal => mal::write_list(&csv_lines()),
+        ExportFormat::Anilist => anilist::write_list(&csv_lines())?,
+        ExportFormat::Html => {
+            let client = create_client().map_err(Error::other)?;
+            if verbose {
+                println!("Fetching the pages for the covers...");
+            }
+            let csv_lines = csv_lines();
+            let mut entries: Vec<EntryReport> = Vec::new();
+            let chapters = fetch_chapters(&csv_lines, &client, &verbose).await;
+            for ((position, line), chapter) in lines.iter().zip(chapters) {
+                match chapter {
+                    Ok(chapter) => {
+                        entries.push(EntryReport::from_line_chapter(*position, &chapter))
+                    }
+                    Err(e) => {
+                        report.error(Some(&line.url), e.reason);
+                        entries.push(EntryReport::from_line(*position, line));
+                    }
+                }
+            }
+            embed_covers(&mut entries, &client, verbose).await;
+            write_html(&entries)
+        }
+    };
+    fs::write(path, contents)
 }
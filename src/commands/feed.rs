@@ -0,0 +1,80 @@
+use crate::feed::{build_feed, FeedFormat, FEED_SIZE};
+use crate::file_ops::extract_path_or_default;
+use crate::file_ops::releases::read_releases;
+use crate::output::{CommandReport, OutputFormat};
+use crate::server::serve_feed;
+use crate::utils::wait_for_shutdown;
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+/// Writes the feed of the latest chapters, taken from the releases file filled by the list and watch commands.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `output`: the file to write. If None, the feed is written next to the CSV file, e.g. `mangas.atom.xml`.
+/// * `feed_format`: the format of the feed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn write_feed(
+    path: Option<PathBuf>,
+    output: Option<PathBuf>,
+    feed_format: FeedFormat,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("feed");
+    let output = output
+        .unwrap_or_else(|| extract_path_or_default(&path).with_extension(feed_format.extension()));
+    match read_releases(&path) {
+        Ok(releases) => match fs::write(&output, build_feed(&releases, feed_format, None)) {
+            Ok(()) => {
+                report.message = Some(format!(
+                    "The {} feed of the {} latest chapters has been written to {}",
+                    feed_format,
+                    releases.len().min(FEED_SIZE),
+                    output.display()
+                ))
+            }
+            Err(e) => report.error(None, format!("An error occurred : {}", e)),
+        },
+        Err(e) => report.error(None, format!("An error occurred : {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Serves the feed of the latest chapters over HTTP, until stopped with Ctrl-C or SIGTERM.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `bind`: the address to listen to, e.g. `127.0.0.1:8080`.
+/// * `feed_format`: the format of the feed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub async fn serve_feed_until_stopped(
+    path: Option<PathBuf>,
+    bind: &str,
+    feed_format: FeedFormat,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("feed");
+    match TcpListener::bind(bind) {
+        Ok(listener) => {
+            if format.is_table() {
+                if let Ok(address) = listener.local_addr() {
+                    println!(
+                        "Serving the {} feed on http://{}/feed. Press Ctrl-C to stop.",
+                        feed_format, address
+                    );
+                }
+            }
+            match serve_feed(listener, path, feed_format, wait_for_shutdown()).await {
+                Ok(()) => report.message = Some("Stopped serving the feed.".to_owned()),
+                Err(e) => report.error(None, format!("The server failed: {}", e)),
+            }
+        }
+        Err(e) => report.error(None, format!("Couldn't listen to {}: {}", bind, e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
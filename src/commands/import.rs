@@ -1,98 +1,345 @@
-use crate::file_ops::read_csv;
+use crate::file_ops::merge_base::{read_merge_base, write_merge_base};
+use crate::file_ops::review::queue_for_review;
 use crate::file_ops::write_file::update_csv;
-use crate::models::CSVLine;
+use crate::file_ops::{extract_path_or_default, read_csv};
+use crate::importers::{read_imported_lines, resolve_entries, SkippedManga};
+use crate::merge::{merge_lines, resolve_conflict, Conflict, MergeStrategy, Side};
+use crate::models::{CSVLine, ReviewItem};
+use crate::output::{ChangeAction, LibraryChange};
+use crate::urls::same_manga;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-/// Imports a CSV file corresponding to the one used by the program.
-/// It can either overwrite or just append to the current file, depending on the `overwrite` parameter.
-/// If the file is not a correct format (ie not properly separated CSV, or if the columns don't match), a panic is raised.
+/// How the imported lines are combined with the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Only the mangas missing from the library are added.
+    Append,
+    /// The imported lines replace the library.
+    Overwrite,
+    /// The changes of both sides since the last merge are kept, and the conflicts resolved with the strategy (See [`crate::merge::merge_lines`]).
+    Merge(MergeStrategy),
+}
+
+/// The outcome of an import, computed before anything is written (See [`write_import`]).
+pub struct ImportSummary {
+    /// The changes the import makes to the library.
+    pub changes: Vec<LibraryChange>,
+    /// The mangas of the imported file that couldn't be imported.
+    pub skipped: Vec<SkippedManga>,
+    /// The number of mangas of a tracker's list whose URL couldn't be found, added to the review queue.
+    pub to_review: usize,
+    /// The entries changed on both sides, for a merge.
+    pub conflicts: Vec<Conflict>,
+    /// The library once the import is written.
+    lines: Vec<CSVLine>,
+    /// The imported lines, remembered as the base of the next merge.
+    merge_base: Option<Vec<CSVLine>>,
+    /// The mangas of a tracker's list to add to the review queue.
+    review: Vec<ReviewItem>,
+}
+
+/// Imports a CSV file corresponding to the one used by the program, a Tachiyomi/Mihon backup, or a MyAnimeList/AniList list (See [`crate::importers::ImportFormat`]).
+/// It can either append the missing lines, overwrite the current file, or merge the changes of both files, depending on the `mode` parameter.
+/// The URLs of the mangas of a list are searched by title; the ones that can't be found are added to the review queue (See [`crate::file_ops::review`]).
+/// If the file is not a correct format (ie not properly separated CSV, or if the columns don't match), an error is returned.
+/// Nothing is written: the changes are only computed, so that they can be previewed, then written with [`write_import`].
 /// # Arguments:
 /// * `from`: the file to import from. If None, an error message is risen.
 /// * `to`: the file to copy to. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
-/// * `overwrite`: if true, the contents of the new file will replace the current one. If false, it will simply append missing lines.
+/// * `mode`: how the imported lines are combined with the current ones.
 /// * `verbose`: if true, more output messages will be shown.
 /// # Returns:
-/// This function returns `true` if no errors happened, or `false` if the import file was not set.
+/// This function returns the changes to make to the library and the mangas that couldn't be imported if no errors happened, or `None` if the import file was not set.
 /// An I/O Error will simply be carried over to the calling function.
-pub fn import_file(
+pub async fn import_file(
     from: Option<PathBuf>,
     to: Option<PathBuf>,
-    overwrite: bool,
+    mode: ImportMode,
     verbose: bool,
-) -> Result<bool, io::Error> {
-    match from {
-        Some(from_path) => {
-            let imported_lines = read_csv(&Some(from_path), &verbose)?;
-            if overwrite {
-                if verbose {
-                    println!("Overwrite is set, the old lines will be deleted.");
-                }
-                update_csv(&to, imported_lines)?;
-            } else {
-                let current_lines = read_csv(&to, &verbose)?;
-                let update = find_new_lines(imported_lines, current_lines);
-                if verbose {
-                    println!("This will add {} new lines to the CSV.", update.len());
-                }
-                update_csv(&to, update)?;
+) -> Result<Option<ImportSummary>, io::Error> {
+    let from_path = match from {
+        Some(from_path) => from_path,
+        None => return Ok(None),
+    };
+    let imported = read_imported_lines(&from_path, verbose)?;
+    let mut imported_lines = imported.lines;
+    let mut review = Vec::new();
+    if !imported.unresolved.is_empty() {
+        if verbose {
+            println!(
+                "Searching the URLs of {} mangas...",
+                imported.unresolved.len()
+            );
+        }
+        let (resolved, unresolved) = resolve_entries(imported.unresolved, verbose).await;
+        imported_lines.extend(resolved);
+        review = unresolved;
+    }
+    let mut conflicts = Vec::new();
+    let (current_lines, update) = match mode {
+        ImportMode::Overwrite => {
+            if verbose {
+                println!("Overwrite is set, the old lines will be deleted.");
+            }
+            let current_lines = read_csv(&to, &verbose).unwrap_or_default();
+            (current_lines, imported_lines.clone())
+        }
+        ImportMode::Append => {
+            let current_lines = read_csv(&to, &verbose)?;
+            let update = find_new_lines(imported_lines.clone(), current_lines.clone());
+            if verbose {
+                println!(
+                    "This will add {} new lines to the CSV.",
+                    update.len() - current_lines.len()
+                );
+            }
+            (current_lines, update)
+        }
+        ImportMode::Merge(strategy) => {
+            let current_lines = read_csv(&to, &verbose)?;
+            let base = read_merge_base(&to)?;
+            if verbose && base.is_none() {
+                println!("Nothing has been merged yet, so every entry that differs is a conflict.");
             }
-            Ok(true)
+            let source_is_newer = modified(&from_path) > modified(&extract_path_or_default(&to));
+            let result = merge_lines(
+                base.as_deref(),
+                &imported_lines,
+                &current_lines,
+                |conflict| {
+                    if strategy == MergeStrategy::Interactive {
+                        ask_side(conflict)
+                    } else {
+                        resolve_conflict(strategy, conflict, source_is_newer)
+                    }
+                },
+            );
+            conflicts = result.conflicts;
+            (current_lines, result.lines)
+        }
+    };
+    let changes = LibraryChange::diff(&current_lines, &update);
+    let merge_base = matches!(mode, ImportMode::Merge(_)).then_some(imported_lines);
+    Ok(Some(ImportSummary {
+        changes,
+        skipped: imported.skipped,
+        to_review: review.len(),
+        conflicts,
+        lines: update,
+        merge_base,
+        review,
+    }))
+}
+
+/// Writes an import computed by [`import_file`]: the library, the base of the next merge and the mangas to review.
+/// # Arguments:
+/// * `to`: the file imported to. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `summary`: the outcome of the import. What it writes is taken out of it.
+pub fn write_import(to: &Option<PathBuf>, summary: &mut ImportSummary) -> Result<(), io::Error> {
+    let review = std::mem::take(&mut summary.review);
+    if !review.is_empty() {
+        queue_for_review(to, review)?;
+    }
+    update_csv(to, std::mem::take(&mut summary.lines))?;
+    if let Some(merge_base) = summary.merge_base.take() {
+        write_merge_base(to, merge_base)?;
+    }
+    Ok(())
+}
+
+/// Gives the last modification time of a file, or None if it can't be read.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Describes a version of a conflicting entry.
+fn describe_version(line: &Option<CSVLine>) -> String {
+    match line {
+        Some(line) => {
+            let mut description = format!("chapter {}, {}", line.last_chapter_num, line.status);
+            if let Some(score) = line.score {
+                description.push_str(&format!(", {}/10", score));
+            }
+            description
+        }
+        None => "removed".to_owned(),
+    }
+}
+
+/// Asks the user which version of a conflicting entry to keep. The library's version is kept if nothing can be read.
+fn ask_side(conflict: &Conflict) -> Side {
+    println!("Conflict on {} ({}):", conflict.title, conflict.url);
+    println!(
+        "  [t] library:       {}",
+        describe_version(&conflict.target)
+    );
+    println!(
+        "  [s] imported file: {}",
+        describe_version(&conflict.source)
+    );
+    loop {
+        print!("Keep which version? [t/s] ");
+        let mut answer = String::new();
+        if io::stdout().flush().is_err()
+            || matches!(io::stdin().read_line(&mut answer), Ok(0) | Err(_))
+        {
+            return Side::Target;
         }
-        None => {
-            eprintln!("No file specified. Please use import -e [file] to import.");
-            Ok(false)
+        match answer.trim() {
+            "t" => return Side::Target,
+            "s" => return Side::Source,
+            _ => eprintln!("Please answer t or s."),
+        }
+    }
+}
+
+/// Asks the user whether the previewed changes of an import should be written.
+/// # Returns:
+/// True if the user agreed, false otherwise, or if nothing can be read.
+pub fn confirm_import() -> Result<bool, io::Error> {
+    print!("Write these changes to the library? [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Prints the changes of an import before they are written, then the conflicts and how they were resolved.
+/// # Argument:
+/// * `summary`: the outcome of the import.
+pub fn print_preview(summary: &ImportSummary) {
+    for change in &summary.changes {
+        let (sign, chapters) = match change.action {
+            ChangeAction::Added => (
+                "+",
+                format!("chapter {}", change.chapter_num.unwrap_or_default()),
+            ),
+            ChangeAction::Updated => (
+                "~",
+                format!(
+                    "chapter {} -> {}",
+                    change.previous_chapter_num.unwrap_or_default(),
+                    change.chapter_num.unwrap_or_default()
+                ),
+            ),
+            ChangeAction::Removed => (
+                "-",
+                format!(
+                    "chapter {}",
+                    change.previous_chapter_num.unwrap_or_default()
+                ),
+            ),
+        };
+        let line = format!("{} {}: {}", sign, change.title, chapters);
+        match change.action {
+            ChangeAction::Added => println!("{}", line.if_supports_color(Stdout, |t| t.green())),
+            ChangeAction::Updated => println!("{}", line.if_supports_color(Stdout, |t| t.yellow())),
+            ChangeAction::Removed => println!("{}", line.if_supports_color(Stdout, |t| t.red())),
         }
     }
+    for conflict in &summary.conflicts {
+        println!(
+            "{}",
+            format!(
+                "! {}: library {} / imported file {}, kept the {}",
+                conflict.title,
+                describe_version(&conflict.target),
+                describe_version(&conflict.source),
+                match conflict.kept {
+                    Side::Source => "imported file",
+                    Side::Target => "library",
+                }
+            )
+            .if_supports_color(Stdout, |t| t.magenta())
+        );
+    }
+}
+
+/// Counts the changes of an import by kind.
+/// # Returns:
+/// A summary such as `2 added, 1 changed, 0 removed, 1 conflict(s)`.
+pub fn count_changes(summary: &ImportSummary) -> String {
+    let count = |action: ChangeAction| {
+        summary
+            .changes
+            .iter()
+            .filter(|change| change.action == action)
+            .count()
+    };
+    format!(
+        "{} added, {} changed, {} removed, {} conflict(s)",
+        count(ChangeAction::Added),
+        count(ChangeAction::Updated),
+        count(ChangeAction::Removed),
+        summary.conflicts.len()
+    )
 }
 
 /// Finds the new lines in the imported file VS the current one, and appends them to it.
-/// It differentiates the lines bases on the URL. For example, if both files have the same URL but a different chapter stored, then the current one will be kept.
-/// It only adds new lines, i.e. URLs not found in the current file.
+/// It differentiates the lines bases on the URL, compared by its canonical form (See [`crate::urls::canonical_url`]). For example, if both files have the same URL but a different chapter stored, then the current one will be kept.
+/// It only adds new lines, i.e. URLs not found in the current file, even under another form.
 /// # Arguments:
 /// * `imported`: the lines found in the CSV to import.
 /// * `current`: the current lines found in the program's CSV.
 /// # Returns:
 /// A new Vec containing the old lines wih the new ones appended behind.
 fn find_new_lines(imported: Vec<CSVLine>, current: Vec<CSVLine>) -> Vec<CSVLine> {
-    let old_urls: Vec<_> = current.clone().into_iter().map(|line| line.url).collect();
-    let filtered_import: Vec<CSVLine> = imported
-        .into_iter()
-        .filter(|line| !old_urls.contains(&line.url))
-        .collect();
-
     let mut result = current;
-    result.extend(filtered_import);
+    for line in imported {
+        if !result.iter().any(|old| same_manga(&old.url, &line.url)) {
+            result.push(line);
+        }
+    }
     result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file_ops::write_file::create_file;
+    use crate::models::ReadingStatus;
+    use crate::test_utils::line;
+    use serial_test::serial;
 
     #[test]
+    #[allow(clippy::vec_init_then_push, clippy::get_first)]
     fn test_new_lines_found() {
         let mut imported: Vec<CSVLine> = Vec::new();
         imported.push(CSVLine {
             url: "url1".to_owned(),
             last_chapter_num: 1.0,
             title: "title1".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         imported.push(CSVLine {
             url: "url2".to_owned(),
             last_chapter_num: 2.0,
             title: "title2".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         let mut current: Vec<CSVLine> = Vec::new();
         current.push(CSVLine {
             url: "url1".to_owned(),
             last_chapter_num: 1.0,
             title: "title1".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         current.push(CSVLine {
             url: "url3".to_owned(),
             last_chapter_num: 3.0,
             title: "title3".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         assert_eq!(imported.get(0), current.get(0));
         let result = find_new_lines(imported.clone(), current.clone());
@@ -100,4 +347,44 @@ mod tests {
         assert_eq!(result.get(1), current.get(1));
         assert_eq!(result.get(2), imported.get(1));
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn merge_is_written_once_previewed() -> Result<(), io::Error> {
+        let to = Some(PathBuf::from("test_import.csv"));
+        let from = PathBuf::from("test_import_other.csv");
+        create_file(&to)?;
+        update_csv(&to, vec![line("url1", 1.0)])?;
+        create_file(&Some(from.clone()))?;
+        update_csv(
+            &Some(from.clone()),
+            vec![line("url1", 3.0), line("url2", 1.0)],
+        )?;
+
+        let mode = ImportMode::Merge(MergeStrategy::HighestChapter);
+        let mut summary = import_file(Some(from.clone()), to.clone(), mode, false)
+            .await?
+            .unwrap();
+        assert_eq!(summary.changes.len(), 2);
+        assert_eq!(summary.conflicts.len(), 1);
+        // Nothing is written before the preview is confirmed.
+        assert_eq!(read_csv(&to, &false)?, vec![line("url1", 1.0)]);
+        assert_eq!(read_merge_base(&to)?, None);
+
+        write_import(&to, &mut summary)?;
+        let merged = vec![line("url1", 3.0), line("url2", 1.0)];
+        assert_eq!(read_csv(&to, &false)?, merged);
+        assert_eq!(read_merge_base(&to)?, Some(merged));
+
+        for file in [
+            "test_import.csv",
+            "test_import.csv.bak",
+            "test_import.merge-base.csv",
+            "test_import_other.csv",
+            "test_import_other.csv.bak",
+        ] {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,143 @@
+use super::download::find_line;
+use crate::cadence::estimate_cadence;
+use crate::file_ops::read_csv;
+use crate::file_ops::releases::read_releases;
+use crate::models::{CSVLine, LineChapter};
+use crate::output::{
+    CommandReport, EntryReport, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::scraper::find_last_chapter;
+use crate::urls::same_manga;
+use chrono::{DateTime, Utc};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::path::PathBuf;
+
+/// Prints the details of a manga: its progress, its last chapter online, and its release cadence with the predicted next release.
+/// The cadence is estimated from the releases recorded by `list` and `watch` (See [`crate::cadence::estimate_cadence`]).
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `target`: the URL or the line number of the manga.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if the page of the manga couldn't be fetched.
+pub async fn show_info(
+    path: Option<PathBuf>,
+    target: &str,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("info");
+    let files = read_csv(&path, &verbose).and_then(|lines| Ok((lines, read_releases(&path)?)));
+    let (lines, releases) = match files {
+        Ok(files) => files,
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let position = match target.parse::<usize>() {
+        Ok(position) => position,
+        Err(_) => lines
+            .iter()
+            .position(|line| same_manga(&line.url, target))
+            .map_or(0, |index| index + 1),
+    };
+    let Some(line) = find_line(lines, target) else {
+        report.error(
+            None,
+            "The manga you asked for is not in the library (the list command may be helpful).",
+        );
+        report.print(format);
+        return EXIT_FATAL;
+    };
+
+    let mut entry = match find_last_chapter(&line.url, None, &verbose).await {
+        Ok(chapter) => EntryReport::from_line_chapter(
+            position,
+            &LineChapter {
+                line: line.clone(),
+                chapter,
+            },
+        ),
+        Err(e) => {
+            report.error(Some(&line.url), format!("{}: {}", line.title, e.reason));
+            EntryReport::from_line(position, &line)
+        }
+    };
+    let dates: Vec<DateTime<Utc>> = releases
+        .iter()
+        .filter(|release| release.manga_url == line.url)
+        .map(|release| release.detected_at)
+        .collect();
+    entry.release = estimate_cadence(&dates);
+    if format.is_table() {
+        print_info(&line, &entry, dates.len());
+    }
+    report.entries.push(entry);
+    report.print(format);
+    if report.errors.is_empty() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Prints the details of a manga.
+/// # Arguments:
+/// * `line`: the line of the manga.
+/// * `entry`: the manga, along with its last chapter online and its cadence.
+/// * `releases`: the number of releases recorded for the manga.
+fn print_info(line: &CSVLine, entry: &EntryReport, releases: usize) {
+    println!(
+        "{}: {}",
+        entry.position,
+        entry.title.if_supports_color(Stdout, |t| t.bold())
+    );
+    println!("  URL: {}", line.url);
+    let score = line
+        .score
+        .map(|score| format!(", scored {}", score))
+        .unwrap_or_default();
+    println!("  Status: {}{}", line.status, score);
+    println!("  Last chapter read: #{}", line.last_chapter_num);
+    if let (Some(num), Some(title)) = (entry.latest_chapter_num, &entry.latest_chapter_title) {
+        let new = if entry.has_new { " (new)" } else { "" };
+        println!(
+            "  Last chapter online: #{}: {}{}",
+            num,
+            title,
+            new.if_supports_color(Stdout, |t| t.green())
+        );
+    }
+    match &entry.release {
+        Some(estimate) => {
+            println!(
+                "  Release cadence: {} (every {:.1} day(s), from {} release(s))",
+                estimate.cadence, estimate.interval_days, releases
+            );
+            println!(
+                "  Last release: {}",
+                estimate.last_release.format("%Y-%m-%d")
+            );
+            match estimate.next_release {
+                Some(next) if next < Utc::now() => println!(
+                    "  Next release: {}",
+                    format!("late since {}", next.format("%Y-%m-%d"))
+                        .if_supports_color(Stdout, |t| t.yellow())
+                ),
+                Some(next) => println!(
+                    "  Next release: expected on {}",
+                    next.format("%Y-%m-%d")
+                        .if_supports_color(Stdout, |t| t.green())
+                ),
+                None => println!("  Next release: unpredictable"),
+            }
+        }
+        None => println!(
+            "  Release cadence: unknown, {} release(s) recorded (list and watch record them)",
+            releases
+        ),
+    }
+}
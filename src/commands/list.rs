@@ -1,11 +1,21 @@
+use crate::cadence::{estimate_all, ReleaseEstimate};
 use crate::commands::update::update_chapters;
 use crate::file_ops::read_csv;
+use crate::file_ops::releases::read_releases;
 use crate::models::{CSVLine, LineChapter};
-use crate::scraper::{create_client, find_last_chapter};
+use crate::notifiers::{announce_new_chapters, NotifierConfig};
+use crate::output::{
+    CommandReport, EntryReport, NewChapterReport, OutputFormat, EXIT_FATAL, EXIT_NEW_CHAPTERS,
+    EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::scraper::{concurrency, create_client, find_last_chapter};
 use crate::utils::ScraperError;
-use futures::future::join_all;
-use owo_colors::OwoColorize;
+use futures::stream::{self, StreamExt};
+use chrono::Utc;
+use owo_colors::{OwoColorize, Stream::Stdout};
 use reqwest::Client;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
 use text_io::try_read;
 
@@ -13,62 +23,107 @@ use text_io::try_read;
 /// For each manga, it searches for the most recent chapter, and compares it to the stored number:
 /// - If the retrieved number is higher, it notifies the user that a new chapter is available in green.
 /// - Otherwise, the user is told that there's no updates on this manga.
+///
 /// After listing, the user is invited to press a number corresponding to the manga it wants to open.
 /// If it corresponds to an actual manga, then the program will launch the browser with the chapter's URL.
+/// The user isn't prompted if `no_prompt` is set, or if stdin isn't a terminal (e.g. under cron or in a CI).
+/// The new chapters are announced to the notifiers the first time they are found (See [`crate::notifiers::announce_new_chapters`]).
+/// The next release of each manga is predicted from its recorded releases (See [`crate::cadence::estimate_cadence`]).
 /// # Arguments:
 /// * `file_path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
 /// * `only_new`: will only display new chapters.
 /// * `no_update`: will not update the opened chapter.
+/// * `no_prompt`: will not ask for a manga to open.
+/// * `notifiers`: the notifiers to call for each new chapter.
 /// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format. If it isn't the table one, the user won't be prompted.
+/// # Returns:
+/// The exit code of the command (See [`list_exit_code`]).
 pub async fn list_chapters(
     file_path: Option<PathBuf>,
     only_new: bool,
     no_update: bool,
+    no_prompt: bool,
+    notifiers: Vec<NotifierConfig>,
     verbose: bool,
-) {
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("list");
     match read_csv(&file_path, &verbose) {
         Ok(lines) => {
             let client = create_client().unwrap();
             if verbose {
                 println!("Fetching the pages for new chapters...");
             }
-            let mangas_futures: Vec<_> = lines
-                .into_iter()
-                .map(|line| search_manga(line, &client, &verbose))
-                .collect();
+            let futures = fetch_chapters(&lines, &client, &verbose).await;
+            // The chapters are kept with the index of their line, so that they are numbered by their position in the CSV, as in the JSON output.
+            let mut chapters: Vec<(usize, LineChapter)> = Vec::new();
+            let mut found: Vec<NewChapterReport> = Vec::new();
+            for (i, (line, result)) in lines.iter().zip(futures).enumerate() {
+                match result {
+                    Ok(chapter) => {
+                        if chapter.chapter.num > chapter.line.last_chapter_num {
+                            found.push(NewChapterReport::from_line_chapter(i + 1, &chapter));
+                        }
+                        if !format.is_table() {
+                            fill_report(&mut report, i + 1, &chapter, only_new);
+                        }
+                        chapters.push((i, chapter))
+                    }
+                    Err(e) => {
+                        report.error(Some(&line.url), e.reason);
+                        if !format.is_table() && !only_new {
+                            report.entries.push(EntryReport::from_line(i + 1, line));
+                        }
+                    }
+                }
+            }
+
+            let failed = report.errors.len();
+            match announce_new_chapters(&file_path, &notifiers, &client, found).await {
+                Ok((_, errors)) => report.errors.extend(errors),
+                Err(e) => report.error(None, format!("Error while recording the releases: {}", e)),
+            }
+            // The releases file is only missing the cadences if it can't be read, which has been reported above.
+            let estimates = read_releases(&file_path)
+                .map(|releases| estimate_all(&releases))
+                .unwrap_or_default();
+            for entry in report.entries.iter_mut() {
+                entry.release = estimates.get(&entry.url).cloned();
+            }
 
-            let futures: Vec<std::result::Result<LineChapter, ScraperError>> =
-                join_all(mangas_futures).await;
-            let (mangas, errors): (Vec<_>, Vec<_>) = futures.into_iter().partition(Result::is_ok);
+            if !format.is_table() {
+                report.emit(format);
+                return list_exit_code(lines.len(), failed, !report.new_chapters.is_empty());
+            }
 
-            if !errors.is_empty() {
+            for error in &report.errors[failed..] {
+                eprintln!("{}", error.reason);
+            }
+
+            if failed > 0 {
                 println!(
                     "{}",
                     "Some mangas couldn't be reached. Try running again with the -v option."
-                        .yellow()
+                        .if_supports_color(Stdout, |t| t.yellow())
                 );
             }
 
             if verbose {
-                println!("Collected {} chapters.", mangas.len());
-                if !errors.is_empty() {
-                    println!(
-                        "{} mangas were unavailable. Check the manga URL.",
-                        errors.len()
-                    );
-                    let errors_list: Vec<ScraperError> =
-                        errors.into_iter().map(Result::unwrap_err).collect();
-                    println!("Errors are: {:?}", errors_list);
+                println!("Collected {} chapters.", chapters.len());
+                if failed > 0 {
+                    println!("{} mangas were unavailable. Check the manga URL.", failed);
+                    println!("Errors are: {:?}", &report.errors[..failed]);
                 }
             }
 
-            if !mangas.is_empty() {
-                let chapters: Vec<LineChapter> = mangas.into_iter().map(Result::unwrap).collect();
-                if display_lines(&chapters, &only_new) {
-                    print!("{}", "Please enter the number of the manga you want to read to open it in the browser: ".yellow());
+            let has_new = display_lines(&chapters, &estimates, &only_new);
+            if !chapters.is_empty() {
+                if has_new && !no_prompt && io::stdin().is_terminal() {
+                    print!("{}", "Please enter the number of the manga you want to read to open it in the browser: ".if_supports_color(Stdout, |t| t.yellow()));
                     let res: Result<usize, _> = try_read!();
                     if let Ok(selected_chapter_index) = res {
-                        match chapters.get(selected_chapter_index - 1) {
+                        match find_selected(&chapters, selected_chapter_index) {
                             Some(chapter_last) => {
                                 if open::that(&chapter_last.chapter.url).is_err() {
                                     eprintln!("Error while opening the URL.");
@@ -77,6 +132,7 @@ pub async fn list_chapters(
                                         file_path,
                                         selected_chapter_index.to_string().as_str(),
                                         verbose,
+                                        format,
                                     )
                                     .await;
                                 }
@@ -84,15 +140,104 @@ pub async fn list_chapters(
                             None => eprintln!("The index you've given is out of range."),
                         }
                     }
-                } else {
+                } else if !has_new {
                     println!("No new chapters");
                 }
             }
+            list_exit_code(lines.len(), failed, has_new)
+        }
+        Err(e) => {
+            if format.is_table() {
+                println!("An error occurred : {}", e)
+            } else {
+                report.error(None, e);
+                report.emit(format);
+            }
+            EXIT_FATAL
         }
-        Err(e) => println!("An error occurred : {}", e),
     }
 }
 
+/// Computes the exit code of the list command, for scripts to branch on.
+/// Failures take precedence over new chapters, so that a script doesn't miss them.
+/// # Arguments:
+/// * `total`: the number of mangas in the CSV.
+/// * `failed`: the number of mangas that couldn't be fetched.
+/// * `has_new`: if true, new chapters have been found.
+/// # Returns:
+/// * `EXIT_FATAL` if no manga could be fetched at all.
+/// * `EXIT_PARTIAL_FAILURE` if some mangas couldn't be fetched.
+/// * `EXIT_NEW_CHAPTERS` if new chapters have been found.
+/// * `EXIT_SUCCESS` otherwise.
+fn list_exit_code(total: usize, failed: usize, has_new: bool) -> i32 {
+    if failed > 0 && failed == total {
+        EXIT_FATAL
+    } else if failed > 0 {
+        EXIT_PARTIAL_FAILURE
+    } else if has_new {
+        EXIT_NEW_CHAPTERS
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// Finds the manga picked by the user after listing.
+/// # Arguments:
+/// * `chapters`: the fetched chapters, along with the index of their line in the CSV.
+/// * `position`: the number typed by the user, i.e. the position of the line in the CSV.
+/// # Returns:
+/// The chapter of the manga, or None if the position is 0, out of range, or the manga couldn't be fetched.
+fn find_selected(chapters: &[(usize, LineChapter)], position: usize) -> Option<&LineChapter> {
+    let index = position.checked_sub(1)?;
+    chapters
+        .iter()
+        .find(|(i, _)| *i == index)
+        .map(|(_, chapter)| chapter)
+}
+
+/// Fills the report with a fetched chapter, following the same rules as [`display_lines`].
+/// # Arguments:
+/// * `report`: the report to fill.
+/// * `position`: the position of the line in the CSV.
+/// * `line_chapter`: the line along with its last chapter online.
+/// * `only_new`: will only add the entry if it has a new chapter.
+fn fill_report(
+    report: &mut CommandReport,
+    position: usize,
+    line_chapter: &LineChapter,
+    only_new: bool,
+) {
+    let entry = EntryReport::from_line_chapter(position, line_chapter);
+    if entry.has_new {
+        report
+            .new_chapters
+            .push(NewChapterReport::from_line_chapter(position, line_chapter));
+    }
+    if entry.has_new || !only_new {
+        report.entries.push(entry);
+    }
+}
+
+/// Fetches the last chapter of every line, sharing the client's connection pool between the requests.
+/// At most [`crate::scraper::concurrency`] pages are fetched at the same time.
+/// # Arguments:
+/// * `lines`: the lines to search the last chapter for.
+/// * `client`: the client to make connections with.
+/// * `verbose`: if true, more messages will be shown.
+/// # Returns:
+/// The result of each search, in the same order as the lines.
+pub async fn fetch_chapters(
+    lines: &[CSVLine],
+    client: &Client,
+    verbose: &bool,
+) -> Vec<Result<LineChapter, ScraperError>> {
+    stream::iter(lines.iter().cloned())
+        .map(|line| search_manga(line, client, verbose))
+        .buffered(concurrency())
+        .collect()
+        .await
+}
+
 /// Inner function for searching the last chapter of a manga.
 /// # Argument:
 /// * `manga`: The line to search the last chapter for.
@@ -111,30 +256,78 @@ async fn search_manga(
     })
 }
 
-fn display_lines(lines: &[LineChapter], only_new: &bool) -> bool {
+fn display_lines(
+    lines: &[(usize, LineChapter)],
+    estimates: &HashMap<String, ReleaseEstimate>,
+    only_new: &bool,
+) -> bool {
+    let now = Utc::now();
     let mut has_new = false;
-    for (i, line_chapter) in lines.iter().enumerate() {
+    for (i, line_chapter) in lines {
+        let release = estimates
+            .get(&line_chapter.line.url)
+            .map(|estimate| format!("Release cadence: {}\n", estimate.describe(now)))
+            .unwrap_or_default();
         if line_chapter.chapter.num > line_chapter.line.last_chapter_num {
             println!("{}: {}", i + 1, line_chapter.chapter.manga_title);
             has_new = true;
             println!(
                 "There's a new chapter: {green_hashtag}{num:#}: {title} (Previously was {red_hashtag}{last_num})",
-                num = line_chapter.chapter.num.green(),
-                title = line_chapter.chapter.chapter_title.green(),
-                last_num = line_chapter.line.last_chapter_num.red(),
-                green_hashtag = "#".green(),
-                red_hashtag = "#".red()
+                num = line_chapter.chapter.num.if_supports_color(Stdout, |t| t.green()),
+                title = line_chapter.chapter.chapter_title.if_supports_color(Stdout, |t| t.green()),
+                last_num = line_chapter.line.last_chapter_num.if_supports_color(Stdout, |t| t.red()),
+                green_hashtag = "#".if_supports_color(Stdout, |t| t.green()),
+                red_hashtag = "#".if_supports_color(Stdout, |t| t.red())
             );
+            print!("{}", release);
             println!("========================================");
         } else if !only_new {
             println!("{}: {}", i + 1, line_chapter.chapter.manga_title);
             println!(
                 "No updates available (Currently on chapter {}{})",
-                "#".green(),
-                line_chapter.chapter.num.green()
+                "#".if_supports_color(Stdout, |t| t.green()),
+                line_chapter.chapter.num.if_supports_color(Stdout, |t| t.green())
             );
+            print!("{}", release);
             println!("========================================");
         }
     }
     has_new
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{line, manga_chapter};
+
+    #[test]
+    fn exit_code_without_failures() {
+        assert_eq!(list_exit_code(3, 0, false), EXIT_SUCCESS);
+        assert_eq!(list_exit_code(3, 0, true), EXIT_NEW_CHAPTERS);
+        assert_eq!(list_exit_code(0, 0, false), EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn exit_code_with_failures() {
+        assert_eq!(list_exit_code(3, 1, true), EXIT_PARTIAL_FAILURE);
+        assert_eq!(list_exit_code(3, 1, false), EXIT_PARTIAL_FAILURE);
+        assert_eq!(list_exit_code(3, 3, false), EXIT_FATAL);
+    }
+
+    #[test]
+    fn selection_is_the_position_in_the_csv() {
+        let chapter = |url: &str| LineChapter {
+            line: line(url, 1.0),
+            chapter: manga_chapter(url, 2.0),
+        };
+        // The second manga couldn't be fetched.
+        let chapters = vec![(0, chapter("url1")), (2, chapter("url3"))];
+        assert_eq!(
+            find_selected(&chapters, 3).map(|c| c.line.url.as_str()),
+            Some("url3")
+        );
+        assert!(find_selected(&chapters, 2).is_none());
+        assert!(find_selected(&chapters, 0).is_none());
+        assert!(find_selected(&chapters, 4).is_none());
+    }
+}
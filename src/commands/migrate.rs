@@ -0,0 +1,34 @@
+use crate::output::{CommandReport, OutputFormat};
+use crate::paths::{legacy_files, migrate_legacy_files};
+
+/// Moves the library and the config file of the previous versions from the executable's folder to the data and config folders.
+/// # Arguments:
+/// * `verbose`: if true, each moved file is printed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn migrate_files(verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("migrate");
+    match legacy_files() {
+        Ok(files) if files.is_empty() => {
+            report.message = Some("There is nothing to migrate.".to_owned());
+        }
+        Ok(files) => match migrate_legacy_files(files) {
+            Ok(moved) => {
+                if verbose {
+                    for (from, to) in &moved {
+                        println!("Moved {} to {}", from.display(), to.display());
+                    }
+                }
+                report.message = Some(format!(
+                    "{} file(s) have been moved. The files next to the executable are not used anymore.",
+                    moved.len()
+                ));
+            }
+            Err(e) => report.error(None, format!("Error while migrating the files: {}", e)),
+        },
+        Err(e) => report.error(None, format!("Error while migrating the files: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
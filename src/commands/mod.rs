@@ -1,33 +1,97 @@
 /// Add command logic
 mod add;
+/// Completions command logic
+mod completions;
+/// Email digest command logic
+mod digest;
+/// Doctor command logic
+mod doctor;
+/// Download command logic
+mod download;
 /// Export command logic
 mod export;
+/// Feed command logic
+mod feed;
 /// Import command logic
 mod import;
+/// Info command logic
+mod info;
 /// List command logic
 mod list;
+/// Migrate command logic
+mod migrate;
 /// Open command logic
 mod open;
+/// Package command logic
+mod package;
+/// Profile commands logic
+mod profile;
+/// Queue command logic
+mod queue;
 /// Remove command logic
 mod remove;
+/// Review command logic
+mod review;
+/// Serve command logic
+mod serve;
+/// Stats command logic
+mod stats;
+/// Sync commands logic
+mod sync;
+/// Terminal UI command logic
+mod tui;
 /// Restore command logic
 mod undo;
 /// Unread command logic
 mod unread;
 /// Update command logic
 mod update;
+/// Watch command logic
+mod watch;
 
-use crate::commands::add::add_new_manga;
+use crate::commands::add::{add_from_text, add_new_manga};
+use crate::commands::completions::{print_completions, print_entries};
+use crate::commands::digest::send_email_digest;
+use crate::commands::download::download_chapters;
+use crate::commands::doctor::run_doctor;
 use crate::commands::export::export_data;
-use crate::commands::import::import_file;
+use crate::commands::feed::{serve_feed_until_stopped, write_feed};
+use crate::commands::import::{confirm_import, count_changes, import_file, print_preview, write_import};
+pub use crate::commands::import::ImportMode;
+use crate::commands::info::show_info;
 use crate::commands::list::list_chapters;
+use crate::commands::migrate::migrate_files;
 use crate::commands::open::open_manga;
+use crate::commands::package::package_chapters;
+use crate::commands::profile::{copy_profile, create_profile, delete_profile, list_all_profiles};
+pub use crate::commands::queue::QueueAction;
+use crate::commands::queue::run_queue;
 use crate::commands::remove::remove_manga;
+use crate::commands::review::review_imports;
+use crate::commands::serve::serve_library;
+use crate::commands::stats::show_stats;
+use crate::commands::sync::{init_sync, run_merge_driver, sync_library};
+use crate::commands::tui::browse_library;
 use crate::commands::undo::restore_csv;
 use crate::commands::unread::unread_chapter;
 use crate::commands::update::update_chapters;
+use crate::commands::watch::watch_chapters;
+use crate::config::Config;
+use crate::downloads::epub::Screen;
+use crate::email::{DigestPeriod, EmailConfig};
+use crate::feed::FeedFormat;
+use crate::exporters::ExportFormat;
+use crate::merge::MergeStrategy;
+use crate::models::ReadingStatus;
 use crate::file_ops::write_file::create_file;
+use crate::notifiers::NotifierConfig;
+use crate::queue::QueueOrder;
+use crate::output::{CommandReport, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
+use std::time::Duration;
+use structopt::clap::{App, Shell};
 
 /// Lists the different mangas and their possible updates.
 /// Passes the logic to the list mod.
@@ -35,68 +99,169 @@ use std::path::PathBuf;
 /// * `file_path`: the optional path to the CSV file.
 /// * `only_new`: will only display new chapters.
 /// * `no_update`: will not update the opened chapter.
+/// * `no_prompt`: will not ask for a manga to open.
+/// * `notifiers`: the notifiers to call for each new chapter.
 /// * `verbose`: if true, more messages will be shown.
-pub async fn list(file_path: Option<PathBuf>, only_new: bool, no_update: bool, verbose: bool) {
-    list_chapters(file_path, only_new, no_update, verbose).await
+/// * `format`: the output format.
+pub async fn list(
+    file_path: Option<PathBuf>,
+    only_new: bool,
+    no_update: bool,
+    no_prompt: bool,
+    notifiers: Vec<NotifierConfig>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    list_chapters(file_path, only_new, no_update, no_prompt, notifiers, verbose, format).await
 }
 
-/// Adds the manga to the database.
+/// Adds the manga to the database, or every manga whose URL is found in a file or in stdin.
 /// # Arguments
 /// * `file_path`: the optional path to the CSV file.
-/// * `manga_url`: the manganelo URL of the manga to add.
-pub async fn add(path: Option<PathBuf>, manga_url: Option<String>, verbose: bool) {
-    match manga_url {
-        Some(url) => add_new_manga(path, url.as_str(), verbose).await,
-        None => println!("An URL is required to be added."),
+/// * `manga_url`: the manganelo URL of the manga to add, or `-` to read the URLs from stdin.
+/// * `from_file`: the file to read the URLs from, instead of a single URL.
+/// * `format`: the output format.
+pub async fn add(path: Option<PathBuf>, manga_url: Option<String>, from_file: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    match (manga_url.as_deref(), from_file) {
+        (None, Some(file)) => add_from_text(path, Some(&file), verbose, format).await,
+        (Some("-"), None) => add_from_text(path, None, verbose, format).await,
+        (Some(url), None) => add_new_manga(path, url, verbose, format).await,
+        _ => usage_error("add", "Give either the URL of a manga, - to read URLs from stdin, or --from-file with a file of URLs.", format),
     }
 }
 
 /// Initiates the CSV file to store mangas.
 /// # Argument
 /// * `file_path`: the optional path to the CSV file.
-pub fn init(path: Option<PathBuf>) {
+/// * `format`: the output format.
+pub fn init(path: Option<PathBuf>, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("init");
     match create_file(&path) {
-        Ok(_) => println!("The file has been created, the program is ready to use."),
-        Err(e) => println!("Error creating the file: {}", e),
+        Ok(_) => {
+            report.message =
+                Some("The file has been created, the program is ready to use.".to_owned())
+        }
+        Err(e) => report.error(None, format!("Error creating the file: {}", e)),
     }
+    report.print(format);
+    report.exit_code()
 }
 
 ///Updates all or specified mangas.
 /// # Argument
 /// * `file_path`: the optional path to the CSV file.
 /// * `manga_url`: the manga to update. If None, defaults to update all.
-pub async fn update(path: Option<PathBuf>, manga_url: Option<String>, verbose: bool) {
+/// * `format`: the output format.
+pub async fn update(path: Option<PathBuf>, manga_url: Option<String>, verbose: bool, format: OutputFormat) -> i32 {
     match manga_url {
-        Some(url) => update_chapters(path, url.as_str(), verbose).await,
+        Some(url) => update_chapters(path, url.as_str(), verbose, format).await,
         None => {
-            println!("No URL provided. Defaults to all.");
-            update_chapters(path, "all", verbose).await
+            if format.is_table() {
+                println!("No URL provided. Defaults to all.");
+            }
+            update_chapters(path, "all", verbose, format).await
         }
     }
 }
 
-/// Copies the CSV file to another location.
+/// Copies the CSV file to another location, or writes the library in another format.
 /// # Arguments
 /// * `original_path`: the optional path to the CSV file used by the program.
-/// * `to`: the optional path to the folder to copy the file.
-pub fn export(original_path: Option<PathBuf>, to: Option<PathBuf>) {
-    export_data(original_path, to);
+/// * `to`: the optional path to the folder to copy the file, or to the file to write for the other formats.
+/// * `output`: the optional path to the file to write.
+/// * `export_format`: the format to export to. Defaults to a copy of the CSV file.
+/// * `statuses`: the statuses of the mangas to export. Defaults to all of them.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+#[allow(clippy::too_many_arguments)]
+pub async fn export(original_path: Option<PathBuf>, to: Option<PathBuf>, output: Option<PathBuf>, export_format: Option<ExportFormat>, statuses: Vec<ReadingStatus>, verbose: bool, format: OutputFormat) -> i32 {
+    export_data(original_path, to, output, export_format.unwrap_or_default(), statuses, verbose, format).await
 }
 
 /// Import a CSV file to the database.
 /// # Arguments
 /// * `from`: the optional path to the CSV file to import.
 /// * `to`: the optional path to the CSV file used by the program.
-/// * `overwrite`: if true, the destination file will be replaced.
-pub fn import(from: Option<PathBuf>, to: Option<PathBuf>, overwrite: bool, verbose: bool) {
-    match import_file(from, to, overwrite, verbose) {
-        Ok(imp) => {
-            if imp {
-                println!("The file has been imported.")
+/// * `mode`: whether to append the missing lines, replace the library or merge the changes.
+/// * `dry_run`: if true, the changes are shown but not written.
+/// * `yes`: if true, a merge is written without asking for a confirmation after its preview.
+/// * `format`: the output format. The interactive merge, and the confirmation of a merge, require the table one.
+#[allow(clippy::too_many_arguments)]
+pub async fn import(from: Option<PathBuf>, to: Option<PathBuf>, mode: ImportMode, dry_run: bool, yes: bool, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("import");
+    let merge = matches!(mode, ImportMode::Merge(_));
+    let interactive = format.is_table() && io::stdin().is_terminal();
+    if mode == ImportMode::Merge(MergeStrategy::Interactive) && !interactive {
+        return usage_error("import", "The interactive merge needs a terminal and the table format. Use another strategy.", format);
+    }
+    if merge && !dry_run && !yes && !interactive {
+        return usage_error("import", "A merge is written once its preview is confirmed. Use --yes to write it without being asked, or --dry-run to only see it.", format);
+    }
+    match import_file(from, to.clone(), mode, verbose).await {
+        Ok(Some(mut summary)) => {
+            if format.is_table() && (dry_run || merge) {
+                print_preview(&summary);
+            }
+            if merge && !dry_run && !yes {
+                match confirm_import() {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        report.message = Some("The merge has been cancelled, nothing has been written.".to_owned());
+                        report.print(format);
+                        return EXIT_SUCCESS;
+                    }
+                    Err(e) => {
+                        report.error(None, format!("Error while importing: {}", e));
+                        report.print(format);
+                        return report.exit_code();
+                    }
+                }
             }
+            if !dry_run {
+                if let Err(e) = write_import(&to, &mut summary) {
+                    report.error(None, format!("Error while importing: {}", e));
+                    report.print(format);
+                    return report.exit_code();
+                }
+            }
+            let imported = if dry_run {
+                format!("Dry run, nothing has been written: {}.", count_changes(&summary))
+            } else if merge {
+                format!("The file has been merged: {}.", count_changes(&summary))
+            } else {
+                "The file has been imported.".to_owned()
+            };
+            report.message = Some(if summary.to_review > 0 {
+                format!("{} {} manga(s) couldn't be found and need a review: run the review command to pick them.", imported, summary.to_review)
+            } else {
+                imported
+            });
+            report.changes = summary.changes;
+            report.conflicts = summary.conflicts;
+            for manga in summary.skipped {
+                report.error(manga.url.as_deref(), format!("{} has been skipped: {}", manga.title, manga.reason));
+            }
+            report.print(format);
+            // The skipped mangas don't prevent the others from being imported.
+            return if report.errors.is_empty() { EXIT_SUCCESS } else { EXIT_PARTIAL_FAILURE };
         }
-        Err(e) => eprintln!("Error while importing: {}", e),
+        Ok(None) => report.error(
+            None,
+            "No file specified. Please use import -e [file] to import.",
+        ),
+        Err(e) => report.error(None, format!("Error while importing: {}", e)),
     }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Asks the user to pick the URLs of the imported mangas that couldn't be found.
+/// # Arguments:
+/// * `path`: the optional path to where the CSV is located, if not the default location.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn review(path: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    review_imports(path, verbose, format).await
 }
 
 /// Removes a line from the CSV file.
@@ -104,17 +269,49 @@ pub fn import(from: Option<PathBuf>, to: Option<PathBuf>, overwrite: bool, verbo
 /// * `path`: the optional path to where the CSV is located, if not the default location.
 /// * `url`: the manga to delete from the CSV.
 /// * `verbose`: if true, more messages will be shown.
-pub fn remove(from: Option<PathBuf>, url: Option<String>, verbose: bool) {
-    match url {
-        None => {
-            println!("No URL provided. Please provide a manganelo URl or a line number to delete.")
-        }
-        Some(manga_url) => {
-            if let Err(e) = remove_manga(from, manga_url.as_str(), verbose) {
-                eprintln!("{}", e)
+/// * `format`: the output format.
+pub fn remove(from: Option<PathBuf>, url: String, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("remove");
+    match remove_manga(from, url.as_str(), verbose) {
+        Ok(changes) => {
+            report.changes = changes;
+            if format.is_table() {
+                println!("{}", "The manga has been deleted. Be aware that the order might have changed.".if_supports_color(Stdout, |t| t.green()));
             }
         }
+        Err(e) => report.error(None, e),
     }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Downloads chapters of a manga as CBZ archives.
+/// # Arguments:
+/// * `from_path`: the optional path to where the CSV is located, if not the default location.
+/// * `target`: the URL or the line number of the manga.
+/// * `from`: the first chapter to download. Without bounds, the unread chapters are downloaded.
+/// * `to`: the last chapter to download.
+/// * `download_dir`: the folder of the downloads given in the config, if any.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn download(from_path: Option<PathBuf>, target: String, from: Option<f32>, to: Option<f32>, download_dir: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    download_chapters(from_path, &target, from, to, download_dir, verbose, format).await
+}
+
+/// Packages downloaded chapters of a manga into an EPUB.
+/// # Arguments:
+/// * `from_path`: the optional path to where the CSV is located, if not the default location.
+/// * `target`: the URL or the line number of the manga.
+/// * `from`: the first chapter to package. Without bounds, every downloaded chapter is packaged.
+/// * `to`: the last chapter to package.
+/// * `epub`: whether an EPUB was asked for.
+/// * `downscale`: the screen to downscale the pages to, if any.
+/// * `download_dir`: the folder of the downloads given in the config, if any.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+#[allow(clippy::too_many_arguments)]
+pub async fn package(from_path: Option<PathBuf>, target: String, from: Option<f32>, to: Option<f32>, epub: bool, downscale: Option<Screen>, download_dir: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    package_chapters(from_path, &target, from, to, epub, downscale, download_dir, verbose, format).await
 }
 
 /// Opens a manga in the browser.
@@ -122,26 +319,266 @@ pub fn remove(from: Option<PathBuf>, url: Option<String>, verbose: bool) {
 /// * `from`: the optional path to where the CSV is located, if not the default location.
 /// * `url`: the manga to open.
 /// * `direct`: if true, the last chapter from the manga will be open.
+/// * `next`: if true, the first unread chapter will be open, and recorded as the last chapter read.
 /// * `verbose`: if true, more messages will be shown.
-pub async fn open(from: Option<PathBuf>, url: Option<String>, direct: bool, verbose: bool) {
-    match url {
-        None => {
-            println!("Usage: open [url/line chapter]. You can open a manga directly by entering the line number as shown with the list command.");
-            println!(
-                "Use -d to open the last chapter directly, otherwise, it will open the manga page."
+/// * `format`: the output format.
+pub async fn open(from: Option<PathBuf>, url: String, direct: bool, next: bool, verbose: bool, format: OutputFormat) -> i32 {
+    open_manga(from, url.as_str(), direct, next, verbose, format).await
+}
+
+/// Checks the library for problems, and applies the safe fixes if asked.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `fix`: if true, the fixes that lose nothing are applied.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn doctor(from: Option<PathBuf>, fix: bool, verbose: bool, format: OutputFormat) -> i32 {
+    run_doctor(from, fix, verbose, format).await
+}
+
+/// Prints the details of a manga, with its release cadence and its predicted next release.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `target`: the URL or the line number of the manga.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn info(from: Option<PathBuf>, target: String, verbose: bool, format: OutputFormat) -> i32 {
+    show_info(from, target.as_str(), verbose, format).await
+}
+
+/// Builds the reading queue, then prints it or acts on its first chapter.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `order`: how the series are ordered.
+/// * `action`: what to do with the queue.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn queue(from: Option<PathBuf>, order: QueueOrder, action: QueueAction, verbose: bool, format: OutputFormat) -> i32 {
+    run_queue(from, order, action, verbose, format).await
+}
+
+/// Prints the statistics of the library and of the reading history.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `stale_months`: the number of months without release after which a manga is stale.
+/// * `sparkline`: if true, the chapters read are also drawn as sparklines.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub async fn stats(from: Option<PathBuf>, stale_months: u32, sparkline: bool, verbose: bool, format: OutputFormat) -> i32 {
+    show_stats(from, stale_months, sparkline, verbose, format).await
+}
+
+pub fn unread(from: Option<PathBuf>, line_number: String, verbose: bool, format: OutputFormat) -> i32 {
+    unread_chapter(from, line_number.as_str(), verbose, format)
+}
+
+pub fn undo(from: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    restore_csv(from, verbose, format)
+}
+
+/// Watches the mangas periodically and records the new chapters.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `interval`: the delay between two checks. Defaults to 2 hours.
+/// * `jitter`: the maximum random delay added to the interval. Defaults to a tenth of the interval.
+/// * `adaptive`: if true, each manga is checked at its own pace, from its release cadence.
+/// * `config`: the settings, with the notifiers to call for each new chapter and the SMTP settings for the digest.
+/// * `digest`: the optional period of the email digest to send.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+#[allow(clippy::too_many_arguments)]
+pub async fn watch(
+    from: Option<PathBuf>,
+    interval: Option<Duration>,
+    jitter: Option<Duration>,
+    adaptive: bool,
+    config: Config,
+    digest: Option<DigestPeriod>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let digest = match (digest, config.email) {
+        (Some(period), Some(email)) => Some((period, email)),
+        (Some(_), None) => {
+            return usage_error(
+                "watch",
+                "The email settings are missing. Please add an [email] section to the config file.",
+                format,
             )
         }
-        Some(manga_url) => open_manga(from, manga_url.as_str(), direct, verbose).await,
+        (None, _) => None,
+    };
+    let interval = interval.unwrap_or(Duration::from_secs(2 * 3_600));
+    if interval.is_zero() {
+        return usage_error("watch", "The interval must be greater than zero.", format);
     }
+    let jitter = jitter.unwrap_or(interval / 10);
+    watch_chapters(from, interval, jitter, adaptive, config.notifiers, digest, verbose, format).await
 }
 
-pub fn unread(from: Option<PathBuf>, url: Option<String>, verbose: bool) {
-    match url {
-        None => println!("No number provided. Please provide a line number to reset."),
-        Some(line_number) => unread_chapter(from, line_number.as_str(), verbose),
+/// Sends an email listing the new chapters of the period.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `email`: the SMTP settings.
+/// * `period`: the period to cover. Defaults to daily.
+/// * `format`: the output format.
+pub async fn email_digest(
+    from: Option<PathBuf>,
+    email: Option<EmailConfig>,
+    period: Option<DigestPeriod>,
+    format: OutputFormat,
+) -> i32 {
+    send_email_digest(from, email, period.unwrap_or(DigestPeriod::Daily), format).await
+}
+
+/// Writes the feed of the latest chapters, or serves it over HTTP.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `feed_format`: the format of the feed, atom or rss. Defaults to atom.
+/// * `to`: the file to write the feed to. Ignored if the feed is served.
+/// * `bind`: the address to serve the feed on, if any.
+/// * `format`: the output format.
+pub async fn feed(
+    from: Option<PathBuf>,
+    feed_format: Option<FeedFormat>,
+    to: Option<PathBuf>,
+    bind: Option<String>,
+    format: OutputFormat,
+) -> i32 {
+    let feed_format = feed_format.unwrap_or_default();
+    match bind {
+        Some(bind) => serve_feed_until_stopped(from, &bind, feed_format, format).await,
+        None => write_feed(from, to, feed_format, format),
     }
 }
 
-pub fn undo(from: Option<PathBuf>, verbose: bool) {
-    restore_csv(from, verbose)
+/// Serves the library over HTTP, with a JSON API and a dashboard.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `bind`: the address to listen to. Defaults to 127.0.0.1:8080.
+/// * `notifiers`: the notifiers to call for each new chapter.
+/// * `verbose`: if true, the requests are printed.
+/// * `format`: the output format.
+pub async fn serve(
+    from: Option<PathBuf>,
+    bind: Option<String>,
+    notifiers: Vec<NotifierConfig>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let bind = bind.unwrap_or_else(|| "127.0.0.1:8080".to_owned());
+    serve_library(from, &bind, notifiers, verbose, format).await
+}
+
+/// Shows the library in a full-screen terminal UI.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `format`: the output format, used if the library can't be read.
+pub async fn tui(from: Option<PathBuf>, format: OutputFormat) -> i32 {
+    browse_library(from, format).await
+}
+
+/// Moves the files of the previous versions from the executable's folder to the data and config folders.
+/// # Arguments:
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+pub fn migrate(verbose: bool, format: OutputFormat) -> i32 {
+    migrate_files(verbose, format)
+}
+
+/// Creates a profile, with an empty library.
+/// # Arguments:
+/// * `name`: the name of the profile.
+/// * `format`: the output format.
+pub fn profile_create(name: String, format: OutputFormat) -> i32 {
+    create_profile(&name, format)
+}
+
+/// Lists the profiles.
+/// # Arguments:
+/// * `active`: the profile in use, if any.
+/// * `format`: the output format.
+pub fn profile_list(active: Option<String>, format: OutputFormat) -> i32 {
+    list_all_profiles(active.as_deref(), format)
+}
+
+/// Deletes a profile and its files.
+/// # Arguments:
+/// * `name`: the name of the profile.
+/// * `yes`: if true, the user isn't asked to confirm.
+/// * `format`: the output format.
+pub fn profile_delete(name: String, yes: bool, format: OutputFormat) -> i32 {
+    delete_profile(&name, yes, format)
+}
+
+/// Copies a profile, or the default library, to a new profile.
+/// # Arguments:
+/// * `from`: the profile to copy.
+/// * `to`: the name of the new profile.
+/// * `format`: the output format.
+pub fn profile_copy(from: String, to: String, format: OutputFormat) -> i32 {
+    copy_profile(&from, &to, format)
+}
+
+/// Sets up the sync of the library with a git remote, then syncs it.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `remote`: the URL or the path of the remote repository.
+/// * `force`: if true, the remote replaces the one the library is already synced with.
+/// * `verbose`: if true, the git commands are printed.
+/// * `format`: the output format.
+pub fn sync_init(from: Option<PathBuf>, remote: String, force: bool, verbose: bool, format: OutputFormat) -> i32 {
+    init_sync(from, &remote, force, verbose, format)
+}
+
+/// Syncs the library with its git remote.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+/// * `verbose`: if true, the git commands are printed.
+/// * `format`: the output format.
+pub fn sync(from: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    sync_library(from, verbose, format)
+}
+
+/// Merges two versions of the library, as the git merge driver.
+/// # Arguments:
+/// * `base`: the version both sides started from.
+/// * `ours`: the local version, replaced by the merged one.
+/// * `theirs`: the version being merged.
+pub fn merge_driver(base: PathBuf, ours: PathBuf, theirs: PathBuf) -> i32 {
+    run_merge_driver(&base, &ours, &theirs)
+}
+
+/// Prints the completion script of a shell.
+/// # Arguments:
+/// * `app`: the definition of the command line.
+/// * `shell`: the shell to complete.
+pub fn completions(app: App, shell: Shell) -> i32 {
+    print_completions(app, shell)
+}
+
+/// Prints the entries of the library, for the completion scripts.
+/// # Arguments:
+/// * `from`: the optional path to where the CSV is located, if not the default location.
+pub fn complete_entries(from: Option<PathBuf>) -> i32 {
+    print_entries(from)
+}
+
+/// Prints an error about a missing argument.
+/// In table mode, the message is printed as is to stdout, as it is a usage hint.
+/// # Arguments:
+/// * `command`: the name of the command.
+/// * `message`: the usage message.
+/// * `format`: the output format.
+/// # Returns:
+/// The fatal exit code, as the command couldn't run.
+pub fn usage_error(command: &str, message: &str, format: OutputFormat) -> i32 {
+    if format.is_table() {
+        println!("{}", message);
+    } else {
+        let mut report = CommandReport::new(command);
+        report.error(None, message);
+        report.emit(format);
+    }
+    EXIT_FATAL
 }
@@ -1,60 +1,153 @@
 use std::path::PathBuf;
+use crate::commands::unread::set_chapter;
 use crate::file_ops::read_csv;
-use crate::models::CSVLine;
-use crate::scraper::find_last_chapter;
+use crate::models::{CSVLine, ChapterLink};
+use crate::output::{CommandReport, OutputFormat};
+use crate::scraper::{create_client, find_chapters, find_last_chapter};
+use crate::urls::same_manga;
 
 /// Opens a manga in the browser.
 /// # Arguments
 /// * `path`: A path is a custom CSV location is defined.
 /// * `url`: the URL to open. Can be a number, to open a manga based on its line number.
 /// * `direct`: if true, the last chapter from the manga will be open.
+/// * `next`: if true, the first unread chapter will be open instead, and becomes the last chapter read.
 /// * `verbose`: if true, more messages will be shown.
-pub async fn open_manga(path: Option<PathBuf>, url: &str, direct: bool, verbose: bool) {
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub async fn open_manga(path: Option<PathBuf>, url: &str, direct: bool, next: bool, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("open");
     match read_csv(&path, &verbose) {
         Ok(lines) => {
             if verbose {
                 println!("Fetched {} lines in the CSV", lines.len());
             }
             match url.parse::<usize>() {
-                Ok(position) => extract(lines.get(position - 1), direct,
-                                        "The line number is out of bounds, please try again (the list command may be helpful)", &verbose).await,
+                Ok(position) => {
+                    let line = position.checked_sub(1).and_then(|index| lines.get(index)).map(|line| (position, line));
+                    let action = OpenAction { path: &path, direct, next };
+                    extract(line, action, "The line number is out of bounds, please try again (the list command may be helpful)", &verbose, &mut report).await
+                },
                 Err(_) => {
                     if verbose {
                         println!("Trying to open the manga based on its URL...");
                     }
-                    let by_url = lines.into_iter()
-                        .find(|elt| elt.url == url );
-                    extract(by_url.as_ref(), direct, "The URL you asked for is not present.", &verbose).await
+                    let by_url = lines.iter()
+                        .position(|elt| same_manga(&elt.url, url))
+                        .map(|index| (index + 1, &lines[index]));
+                    let action = OpenAction { path: &path, direct, next };
+                    extract(by_url, action, "The URL you asked for is not present.", &verbose, &mut report).await
                 }
             }
         },
-        Err(e) => eprintln!("An error occurred! {}", e)
+        Err(e) => report.error(None, format!("An error occurred! {}", e))
     }
+    report.print(format);
+    report.exit_code()
+}
+
+/// What to open, and where to record the progress.
+struct OpenAction<'a> {
+    /// The path to the CSV file, to record the chapter opened by `next`.
+    path: &'a Option<PathBuf>,
+    direct: bool,
+    next: bool,
 }
 
 /// This function matches the Option received and calls open to open in the browser.
-/// If the line is None, the error message is printed.
+/// If the line is None, the error message is added to the report.
 /// # Arguments:
-/// * `line`: the manga to open
-/// * `direct`: if true, the last chapter from the manga will be open.
+/// * `line`: the manga to open, along with its line number.
+/// * `action`: whether to open the page of the manga, its last chapter, or its first unread chapter.
 /// * `error_message`: the custom error message to show
-async fn extract(line: Option<&CSVLine>, direct: bool, error_message: &str, verbose: &bool) {
+/// * `report`: the report to fill with the opened URL or the error.
+async fn extract(line: Option<(usize, &CSVLine)>, action: OpenAction<'_>, error_message: &str, verbose: &bool, report: &mut CommandReport) {
     match line {
-        Some(l) => {
-            if direct {
+        Some((position, l)) => {
+            if action.next {
+                open_next(position, l, action.path, *verbose, report).await
+            } else if action.direct {
                 match find_last_chapter(l.url.as_str(), None, verbose).await {
-                    Ok(manga) => open(manga.url.as_str()),
-                    Err(e) => eprintln!("Error while fetching the last chapter: {}", e)
+                    Ok(manga) => open(manga.url.as_str(), report),
+                    Err(e) => report.error(Some(&l.url), format!("Error while fetching the last chapter: {}", e))
                 }
-            } else { open(&l.url) }
+            } else { open(&l.url, report) }
         },
-        None => println!("{}", error_message)
+        None => report.error(None, error_message)
     }
 }
 
-fn open(url: &str) {
-    if open::that(&url).is_err() {
-        eprintln!("Error while opening the URL.");
+/// Opens the first chapter after the last one read, and records it as the last chapter read.
+/// The chapters after it stay unread, unlike with the list command.
+/// # Arguments:
+/// * `position`: the line number of the manga.
+/// * `line`: the manga.
+/// * `path`: the path to the CSV file.
+/// * `report`: the report to fill with the opened URL and the change, or the error.
+async fn open_next(position: usize, line: &CSVLine, path: &Option<PathBuf>, verbose: bool, report: &mut CommandReport) {
+    let chapters = match create_client() {
+        Ok(client) => find_chapters(&line.url, &client).await,
+        Err(e) => {
+            report.error(None, format!("Error while creating the client: {}", e));
+            return;
+        }
+    };
+    match chapters {
+        Ok(chapters) => match next_chapter(chapters, line.last_chapter_num) {
+            Some(chapter) => {
+                open(&chapter.url, report);
+                if report.errors.is_empty() {
+                    match set_chapter(path, position, chapter.num, verbose) {
+                        Ok(changes) => {
+                            report.message = Some(format!("Opened {}, now the last chapter read of {}.", chapter.title, line.title));
+                            report.changes = changes;
+                        }
+                        Err(e) => report.error(None, format!("The chapter has been opened, but couldn't be recorded: {}", e)),
+                    }
+                }
+            }
+            None => report.message = Some(format!("There is no chapter after chapter {} of {}.", line.last_chapter_num, line.title)),
+        },
+        Err(e) => report.error(Some(&line.url), format!("Error while fetching the chapters: {}", e.reason)),
     }
 }
 
+/// Finds the first chapter after the last one read.
+/// # Arguments:
+/// * `chapters`: the chapters of the manga, from the first one to the last one.
+/// * `last_read`: the number of the last chapter read.
+fn next_chapter(chapters: Vec<ChapterLink>, last_read: f32) -> Option<ChapterLink> {
+    chapters.into_iter().find(|chapter| chapter.num > last_read)
+}
+
+fn open(url: &str, report: &mut CommandReport) {
+    if open::that(url).is_err() {
+        report.error(Some(url), "Error while opening the URL.");
+    } else {
+        report.message = Some(format!("Opened {}", url));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(num: f32) -> ChapterLink {
+        ChapterLink {
+            num,
+            title: format!("Chapter {}", num),
+            url: format!("http://localhost/chapter-{}", num),
+        }
+    }
+
+    #[test]
+    fn next_chapter_is_the_first_unread_one() {
+        let chapters = vec![chapter(1.0), chapter(2.0), chapter(2.5), chapter(3.0), chapter(7.0)];
+        assert_eq!(next_chapter(chapters.clone(), 2.0), Some(chapter(2.5)));
+        assert_eq!(next_chapter(chapters.clone(), 0.0), Some(chapter(1.0)));
+        // Missing chapters are skipped.
+        assert_eq!(next_chapter(chapters.clone(), 3.0), Some(chapter(7.0)));
+        assert_eq!(next_chapter(chapters, 7.0), None);
+    }
+}
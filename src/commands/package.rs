@@ -0,0 +1,205 @@
+use super::download::find_line;
+use crate::downloads::epub::{EpubMetadata, EpubWriter, Screen};
+use crate::downloads::{downloaded_chapters, extract_downloads_path, package_path, read_cbz};
+use crate::file_ops::read_csv;
+use crate::models::CSVLine;
+use crate::output::{CommandReport, OutputFormat, PackageReport};
+use crate::scraper::{create_client, download_image, find_last_chapter};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Packages downloaded chapters of a manga into a fixed-layout EPUB, to read them on an e-reader.
+/// Without bounds, every downloaded chapter is packaged. The cover of the manga is downloaded, or the first page is used instead.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `target`: the URL or the line number of the manga.
+/// * `from`: the first chapter to package, if any.
+/// * `to`: the last chapter to package, if any.
+/// * `epub`: whether an EPUB was asked for, the only format for now.
+/// * `downscale`: the screen to downscale the pages to, if any.
+/// * `download_dir`: the folder of the downloads given in the config, if any (See [`crate::downloads::extract_downloads_path`]).
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+#[allow(clippy::too_many_arguments)]
+pub async fn package_chapters(
+    path: Option<PathBuf>,
+    target: &str,
+    from: Option<f32>,
+    to: Option<f32>,
+    epub: bool,
+    downscale: Option<Screen>,
+    download_dir: Option<PathBuf>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("package");
+    if !epub {
+        report.error(
+            None,
+            "Choose the format of the package: only --epub is supported for now.",
+        );
+        report.print(format);
+        return report.exit_code();
+    }
+    let line = match read_csv(&path, &verbose) {
+        Ok(lines) => match find_line(lines, target) {
+            Some(line) => line,
+            None => {
+                report.error(None, "The manga you asked for is not in the library (the list command may be helpful).");
+                report.print(format);
+                return report.exit_code();
+            }
+        },
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let dir = extract_downloads_path(&path, &download_dir);
+    let chapters: Vec<(f32, PathBuf)> = match downloaded_chapters(&dir, &line.title) {
+        Ok(chapters) => chapters
+            .into_iter()
+            .filter(|(num, _)| {
+                from.is_none_or(|from| *num >= from) && to.is_none_or(|to| *num <= to)
+            })
+            .collect(),
+        Err(e) => {
+            report.error(
+                None,
+                format!("Error while reading {}: {}", dir.display(), e),
+            );
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let (first, last) = match (chapters.first(), chapters.last()) {
+        (Some((first, _)), Some((last, _))) => (*first, *last),
+        _ => {
+            report.error(
+                Some(&line.url),
+                format!(
+                    "No downloaded chapters of {} to package, download them first with the download command.",
+                    line.title
+                ),
+            );
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+
+    let cover = download_cover(&line, verbose).await;
+    let file = package_path(&dir, &line.title, first, last);
+    let title = if first == last {
+        format!("{} - Chapter {}", line.title, first)
+    } else {
+        format!("{} - Chapters {}-{}", line.title, first, last)
+    };
+    let identifier = format!("{}#chapters-{}-{}", line.url, first, last);
+    let metadata = EpubMetadata {
+        title: &title,
+        identifier: &identifier,
+    };
+    match write_epub(&file, &chapters, cover, downscale, &metadata) {
+        Ok((pages, cover)) => {
+            if format.is_table() {
+                println!(
+                    "{} {} chapter(s) into {}",
+                    "Packaged".if_supports_color(Stdout, |t| t.green()),
+                    chapters.len(),
+                    file.display()
+                );
+            }
+            report.message = Some(format!(
+                "{} chapter(s) of {} packaged, {} page(s).",
+                chapters.len(),
+                line.title,
+                pages
+            ));
+            report.packages.push(PackageReport {
+                title: line.title.clone(),
+                file: file.display().to_string(),
+                chapters: chapters.iter().map(|(num, _)| *num).collect(),
+                pages,
+                cover,
+            });
+        }
+        Err(e) => {
+            let _ = fs::remove_file(file.with_extension("epub.tmp"));
+            report.error(
+                None,
+                format!("Error while writing {}: {}", file.display(), e),
+            );
+        }
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Downloads the cover of a manga, from its page.
+/// # Returns:
+/// The image, None if it couldn't be downloaded. The reason is printed, as the first page is used instead.
+async fn download_cover(line: &CSVLine, verbose: bool) -> Option<Vec<u8>> {
+    let cover = async {
+        let client = create_client().map_err(|e| e.to_string())?;
+        let chapter = find_last_chapter(&line.url, Some(&client), &verbose)
+            .await
+            .map_err(|e| e.reason)?;
+        let url = chapter
+            .cover
+            .ok_or("the page of the manga has no cover".to_owned())?;
+        download_image(&url, &line.url, &client)
+            .await
+            .map(|(bytes, _)| bytes)
+            .map_err(|e| e.reason)
+    };
+    match cover.await {
+        Ok(bytes) => Some(bytes),
+        Err(reason) => {
+            eprintln!(
+                "The cover couldn't be downloaded, the first page is used instead: {}",
+                reason
+            );
+            None
+        }
+    }
+}
+
+/// Writes the chapters into an EPUB, one at a time.
+/// If the cover isn't a valid image, the first page is used instead.
+/// # Returns:
+/// The number of pages, and whether the cover of the manga has been added.
+fn write_epub(
+    file: &Path,
+    chapters: &[(f32, PathBuf)],
+    cover: Option<Vec<u8>>,
+    downscale: Option<Screen>,
+    metadata: &EpubMetadata,
+) -> Result<(usize, bool), io::Error> {
+    let mut epub = EpubWriter::create(file, downscale)?;
+    let has_cover = match cover.map(|cover| epub.add_cover(cover)) {
+        Some(Ok(())) => true,
+        Some(Err(e)) => {
+            eprintln!(
+                "The cover couldn't be read, the first page is used instead: {}",
+                e
+            );
+            false
+        }
+        None => false,
+    };
+    let mut pages = 0;
+    for (_, archive) in chapters {
+        let chapter = read_cbz(archive)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", archive.display(), e)))?;
+        pages += epub
+            .add_chapter(&chapter.title, chapter.pages)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", chapter.title, e)))?;
+    }
+    epub.finish(metadata)?;
+    Ok((pages, has_cover))
+}
@@ -0,0 +1,223 @@
+use crate::file_ops::read_csv;
+use crate::file_ops::releases::extract_releases_path;
+use crate::file_ops::write_file::create_file;
+use crate::output::{CommandReport, OutputFormat, ProfileReport};
+use crate::paths::{
+    check_profile_name, default_library_path, list_profiles, profile_config_path, profile_dir,
+    profile_library_path, DEFAULT_PROFILE,
+};
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// Creates a profile, with an empty library.
+/// # Arguments:
+/// * `name`: the name of the profile.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn create_profile(name: &str, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("profile create");
+    match create(name) {
+        Ok(path) => {
+            report.message = Some(format!(
+                "The profile {} has been created. Its library is {}.",
+                name,
+                path.display()
+            ))
+        }
+        Err(e) => report.error(None, format!("Error while creating the profile: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Creates the folder and the library of the profile.
+fn create(name: &str) -> Result<PathBuf, io::Error> {
+    check_profile_name(name)?;
+    check_free(name)?;
+    let path = profile_library_path(name);
+    create_file(&Some(path.clone()))?;
+    Ok(path)
+}
+
+/// Checks that no profile has the name yet.
+fn check_free(name: &str) -> Result<(), io::Error> {
+    if profile_dir(name).exists() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("The profile {} already exists.", name),
+        ));
+    }
+    Ok(())
+}
+
+/// Lists the default library and the profiles, along with the number of mangas in each of them.
+/// # Arguments:
+/// * `active`: the profile in use, if any.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn list_all_profiles(active: Option<&str>, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("profile list");
+    let names = match list_profiles() {
+        Ok(names) => names,
+        Err(e) => {
+            report.error(None, format!("Error while listing the profiles: {}", e));
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    report.profiles.push(profile_report(
+        DEFAULT_PROFILE,
+        default_library_path(),
+        active.is_none(),
+    ));
+    for name in names {
+        let library = profile_library_path(&name);
+        let is_active = active == Some(name.as_str());
+        report.profiles.push(profile_report(&name, library, is_active));
+    }
+    if format.is_table() {
+        for profile in &report.profiles {
+            let entries = match profile.entries {
+                Some(count) => format!("{} manga(s)", count),
+                None => "no library".to_owned(),
+            };
+            println!(
+                "{} {}: {} ({})",
+                if profile.active { "*" } else { " " },
+                profile.name,
+                entries,
+                profile.library
+            );
+        }
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Describes a profile, reading its library to count the mangas.
+fn profile_report(name: &str, library: PathBuf, active: bool) -> ProfileReport {
+    let path = Some(library.clone());
+    ProfileReport {
+        name: name.to_owned(),
+        library: library.display().to_string(),
+        entries: read_csv(&path, &false).ok().map(|lines| lines.len()),
+        active,
+    }
+}
+
+/// Deletes a profile, with its library, its backup, the recorded chapters and its settings.
+/// As this can't be undone, the user is asked to confirm, unless `yes` is set.
+/// # Arguments:
+/// * `name`: the name of the profile.
+/// * `yes`: if true, the profile is deleted without asking.
+/// * `format`: the output format. If it isn't the table one, `yes` is required.
+/// # Returns:
+/// The exit code of the command.
+pub fn delete_profile(name: &str, yes: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("profile delete");
+    match delete(name, yes, format) {
+        Ok(true) => report.message = Some(format!("The profile {} has been deleted.", name)),
+        Ok(false) => report.message = Some("Nothing has been deleted.".to_owned()),
+        Err(e) => report.error(None, format!("Error while deleting the profile: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Asks for a confirmation, then deletes the files of the profile.
+/// # Returns:
+/// True if the profile has been deleted, false if the user changed their mind.
+fn delete(name: &str, yes: bool, format: OutputFormat) -> Result<bool, io::Error> {
+    check_profile_name(name)?;
+    let dir = profile_dir(name);
+    if !dir.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("The profile {} doesn't exist.", name),
+        ));
+    }
+    if !yes {
+        if !format.is_table() || !io::stdin().is_terminal() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Use --yes to delete a profile without being asked.",
+            ));
+        }
+        print!(
+            "Delete the profile {} and its library? This can't be undone. [y/N] ",
+            name
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            return Ok(false);
+        }
+    }
+    fs::remove_dir_all(dir)?;
+    let settings = profile_config_path(name);
+    if settings.exists() {
+        fs::remove_file(settings)?;
+    }
+    Ok(true)
+}
+
+/// Copies a profile, or the default library, to a new profile.
+/// The library, the recorded chapters and the settings of the profile are copied, but not the backup.
+/// # Arguments:
+/// * `from`: the profile to copy, `default` for the library used without a profile.
+/// * `to`: the name of the new profile.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn copy_profile(from: &str, to: &str, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("profile copy");
+    match copy(from, to) {
+        Ok(()) => {
+            report.message = Some(format!("The profile {} has been copied to {}.", from, to))
+        }
+        Err(e) => report.error(None, format!("Error while copying the profile: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Copies the files of the profile.
+fn copy(from: &str, to: &str) -> Result<(), io::Error> {
+    check_profile_name(to)?;
+    check_free(to)?;
+    let library = if from == DEFAULT_PROFILE {
+        default_library_path()
+    } else {
+        check_profile_name(from)?;
+        profile_library_path(from)
+    };
+    if !library.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("The profile {} doesn't exist.", from),
+        ));
+    }
+    fs::create_dir_all(profile_dir(to))?;
+    let destination = profile_library_path(to);
+    fs::copy(&library, &destination)?;
+    let releases = extract_releases_path(&Some(library));
+    if releases.exists() {
+        fs::copy(releases, extract_releases_path(&Some(destination)))?;
+    }
+    if from != DEFAULT_PROFILE {
+        let settings = profile_config_path(from);
+        if settings.exists() {
+            let copied = profile_config_path(to);
+            if let Some(parent) = copied.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(settings, copied)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use crate::commands::unread::set_chapter;
+use crate::file_ops::queue::{read_skips, write_skips};
+use crate::file_ops::read_csv;
+use crate::file_ops::releases::read_releases;
+use crate::models::{ReadingStatus, Release};
+use crate::output::{CommandReport, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS};
+use crate::queue::{build_queue, skip_head, QueueItem, QueueOrder, SeriesChapters};
+use crate::scraper::{concurrency, create_client, find_chapters};
+use futures::stream::{self, StreamExt};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::io;
+use std::path::PathBuf;
+
+/// What to do with the reading queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueAction {
+    /// Print the queue.
+    Show,
+    /// Open the head of the queue, and mark it as read.
+    Next,
+    /// Mark the head of the queue as read, without opening it.
+    Pop,
+    /// Put the series at the head of the queue at the back of it, until the chapter is read.
+    Skip,
+}
+
+/// Builds the reading queue, i.e. every unread chapter of the mangas being read, then acts on it.
+/// The chapters of every manga are fetched, so that the queue is at chapter level.
+/// Marking a chapter as read sets it as the last chapter read of its manga, so the chapters after it stay in the queue.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `order`: how the series are ordered (See [`QueueOrder`]).
+/// * `action`: what to do with the queue.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if the chapters of some mangas couldn't be fetched.
+pub async fn run_queue(
+    path: Option<PathBuf>,
+    order: QueueOrder,
+    action: QueueAction,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("queue");
+    let queue = match load_queue(&path, order, verbose, &mut report).await {
+        Ok(queue) => queue,
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return report.exit_code();
+        }
+    };
+    let failed = report.errors.len();
+    match (action, queue.first()) {
+        (QueueAction::Show, _) => {
+            if format.is_table() {
+                print_queue(&queue);
+            }
+            report.message = Some(format!(
+                "{} unread chapter(s) in the queue, by {}.",
+                queue.len(),
+                order
+            ));
+            report.queue = queue;
+        }
+        (_, None) => {
+            report.message = Some("The queue is empty: every chapter has been read.".to_owned())
+        }
+        (QueueAction::Next, Some(head)) => {
+            if open::that(&head.chapter_url).is_err() {
+                report.error(Some(&head.chapter_url), "Error while opening the URL.");
+            } else {
+                mark_read(&path, head, queue.len(), verbose, &mut report);
+            }
+            report.queue.push(head.clone());
+        }
+        (QueueAction::Pop, Some(head)) => {
+            mark_read(&path, head, queue.len(), verbose, &mut report);
+            report.queue.push(head.clone());
+        }
+        (QueueAction::Skip, Some(head)) => {
+            let skips =
+                read_skips(&path).and_then(|skips| write_skips(&path, &skip_head(&queue, &skips)));
+            match skips {
+                Ok(()) => {
+                    report.message = Some(format!(
+                        "Skipped {}: its chapters are at the back of the queue until {} is read.",
+                        head.title, head.chapter_title
+                    ))
+                }
+                Err(e) => report.error(None, format!("Error while recording the skip: {}", e)),
+            }
+            report.queue.push(head.clone());
+        }
+    }
+    report.print(format);
+    // The mangas that couldn't be fetched only leave the queue incomplete, unlike an action that failed.
+    if report.errors.is_empty() {
+        EXIT_SUCCESS
+    } else if report.errors.len() > failed {
+        EXIT_FATAL
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Fetches the chapters of the mangas being read, and builds the queue.
+/// The mangas whose chapters couldn't be fetched are added to the errors of the report, and left out of the queue.
+async fn load_queue(
+    path: &Option<PathBuf>,
+    order: QueueOrder,
+    verbose: bool,
+    report: &mut CommandReport,
+) -> Result<Vec<QueueItem>, io::Error> {
+    let lines = read_csv(path, &verbose)?;
+    let releases: Vec<Release> = read_releases(path)?;
+    let skips = read_skips(path)?;
+    let client = create_client().map_err(io::Error::other)?;
+    if verbose {
+        println!("Fetching the chapters of the mangas being read...");
+    }
+    let reading = lines
+        .into_iter()
+        .enumerate()
+        .filter(|(_, line)| line.status == ReadingStatus::Reading);
+    let results: Vec<_> = stream::iter(reading)
+        .map(|(index, line)| {
+            let client = &client;
+            async move {
+                let chapters = find_chapters(&line.url, client).await;
+                (index + 1, line, chapters)
+            }
+        })
+        .buffered(concurrency())
+        .collect()
+        .await;
+    let mut series: Vec<SeriesChapters> = Vec::new();
+    for (position, line, chapters) in results {
+        match chapters {
+            Ok(chapters) => series.push(SeriesChapters {
+                position,
+                line,
+                chapters,
+            }),
+            Err(e) => report.error(Some(&line.url), format!("{}: {}", line.title, e.reason)),
+        }
+    }
+    Ok(build_queue(series, &releases, &skips, order))
+}
+
+/// Marks a chapter as read, by setting it as the last chapter read of its manga.
+fn mark_read(
+    path: &Option<PathBuf>,
+    item: &QueueItem,
+    total: usize,
+    verbose: bool,
+    report: &mut CommandReport,
+) {
+    match set_chapter(path, item.position, item.chapter_num, verbose) {
+        Ok(changes) => {
+            report.changes = changes;
+            report.message = Some(format!(
+                "{} of {} is read, {} chapter(s) left in the queue.",
+                item.chapter_title,
+                item.title,
+                total - 1
+            ));
+        }
+        Err(e) => report.error(
+            None,
+            format!("The chapter couldn't be marked as read: {}", e),
+        ),
+    }
+}
+
+/// Prints the queue, the head first.
+fn print_queue(queue: &[QueueItem]) {
+    for (index, item) in queue.iter().enumerate() {
+        let released = item
+            .released_at
+            .map(|date| format!(" (released on {})", date.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        let skipped = if item.skipped { " [skipped]" } else { "" };
+        println!(
+            "{}: {} - {}{}{}",
+            index + 1,
+            item.title,
+            item.chapter_title.if_supports_color(Stdout, |t| t.green()),
+            released,
+            skipped.if_supports_color(Stdout, |t| t.yellow())
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use owo_colors::OwoColorize;
-
 use crate::file_ops::read_csv;
 use crate::file_ops::write_file::update_csv;
 use std::path::PathBuf;
 use std::io;
 use crate::models::CSVLine;
+use crate::output::LibraryChange;
+use crate::urls::same_manga;
 
 /// Removes an element by its position in the list, or by the manga URL.
 /// # Arguments:
@@ -12,25 +12,19 @@ use crate::models::CSVLine;
 /// * `url`: the manga to delete from the CSV.
 /// * `verbose`: if true, more messages will be shown.
 /// # Returns:
-/// A Result with void OK and an io::Error if something went wrong with the CSV.
-pub fn remove_manga(path: Option<PathBuf>, url: &str, verbose: bool) -> Result<(), io::Error> {
+/// A Result with the removed lines as changes, and an io::Error if something went wrong with the CSV.
+pub fn remove_manga(path: Option<PathBuf>, url: &str, verbose: bool) -> Result<Vec<LibraryChange>, io::Error> {
     let mut current_lines: Vec<CSVLine> = read_csv(&path, &verbose)?;
+    let before = current_lines.clone();
     if let Ok(number) = url.parse::<usize>() {
         if verbose {
             println!("Removing manga at position {}", number);
         }
         current_lines.remove(number-1);
     } else {
-        current_lines = current_lines
-            .into_iter()
-            .filter(|elt| elt.url != url)
-            .collect();
-    }
-    match update_csv(&path, current_lines) {
-        Ok(_) => {
-            println!("{}", "The manga has been deleted. Be aware that the order might have changed.".green());
-            Ok(())
-        },
-        Err(e) => Err(e)
+        current_lines.retain(|elt| !same_manga(&elt.url, url));
     }
+    let changes = LibraryChange::diff(&before, &current_lines);
+    update_csv(&path, current_lines)?;
+    Ok(changes)
 }
@@ -0,0 +1,221 @@
+use crate::file_ops::read_csv;
+use crate::file_ops::review::{read_review_queue, write_review_queue};
+use crate::file_ops::write_file::update_csv;
+use crate::models::{CSVLine, ReviewItem, SearchResult};
+use crate::output::{CommandReport, LibraryChange, OutputFormat};
+use crate::scraper::search_titles;
+use crate::urls::same_manga;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::io;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+/// What the user answered for a manga to review.
+#[derive(Debug, PartialEq)]
+enum Answer {
+    /// One of the search results, by its index.
+    Pick(usize),
+    /// A URL pasted by the user.
+    Url(String),
+    /// A new search, with the title of the manga if the query is empty.
+    Search(String),
+    /// Keep the manga in the queue for later.
+    Skip,
+    /// Remove the manga from the queue without adding it.
+    Drop,
+    /// Stop reviewing, keeping the remaining mangas in the queue.
+    Quit,
+    Invalid,
+}
+
+/// Reads an answer typed by the user.
+/// # Arguments:
+/// * `input`: the line typed by the user.
+/// * `candidates`: the number of search results shown.
+fn parse_answer(input: &str, candidates: usize) -> Answer {
+    let input = input.trim();
+    if let Some(query) = input.strip_prefix('/') {
+        return Answer::Search(query.trim().to_owned());
+    }
+    if input.starts_with("http://") || input.starts_with("https://") {
+        return Answer::Url(input.to_owned());
+    }
+    match input {
+        "" | "s" => Answer::Skip,
+        "d" => Answer::Drop,
+        "q" => Answer::Quit,
+        number => match number.parse::<usize>() {
+            Ok(index) if index >= 1 && index <= candidates => Answer::Pick(index - 1),
+            _ => Answer::Invalid,
+        },
+    }
+}
+
+/// Goes through the imported mangas whose URL couldn't be found, and asks the user to pick it.
+/// For each manga, the user picks a search result, pastes a URL, searches again, skips it for later or drops it.
+/// The picked mangas are added to the library, unless their URL is already in it.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format. As the review is interactive, only the table one is accepted.
+/// # Returns:
+/// The exit code of the command.
+pub async fn review_imports(path: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("review");
+    match review(&path, verbose, format).await {
+        Ok((changes, left)) => {
+            report.message = Some(format!(
+                "{} manga(s) added to the library, {} left to review.",
+                changes.len(),
+                left
+            ));
+            report.changes = changes;
+        }
+        Err(e) => report.error(None, format!("Error while reviewing: {}", e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Runs the review, then saves the library and the mangas left in the queue.
+/// # Returns:
+/// The changes made to the library, and the number of mangas left to review.
+async fn review(
+    path: &Option<PathBuf>,
+    verbose: bool,
+    format: OutputFormat,
+) -> Result<(Vec<LibraryChange>, usize), io::Error> {
+    let queue = read_review_queue(path)?;
+    if queue.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    if !format.is_table() || !io::stdin().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The review is interactive, and needs a terminal and the table format.",
+        ));
+    }
+    let current_lines = read_csv(path, &verbose)?;
+    let mut lines = current_lines.clone();
+    let mut left: Vec<ReviewItem> = Vec::new();
+    let total = queue.len();
+    let mut items = queue.into_iter().enumerate();
+    while let Some((index, mut item)) = items.next() {
+        println!(
+            "\n[{}/{}] {} ({} chapter(s) read, {})",
+            index + 1,
+            total,
+            item.entry.title.if_supports_color(Stdout, |t| t.bold()),
+            item.entry.read_chapters,
+            item.entry.status
+        );
+        loop {
+            print_candidates(&item.candidates);
+            print!(
+                "{}",
+                "Pick a number, paste a URL, /words to search again, s to skip, d to drop, q to quit: "
+                    .if_supports_color(Stdout, |t| t.yellow())
+            );
+            io::stdout().flush()?;
+            let mut input = String::new();
+            let answer = if io::stdin().read_line(&mut input)? == 0 {
+                Answer::Quit
+            } else {
+                parse_answer(&input, item.candidates.len())
+            };
+            let line = match answer {
+                Answer::Pick(index) => {
+                    let candidate = &item.candidates[index];
+                    Some(
+                        item.entry
+                            .clone()
+                            .into_line(candidate.url.clone(), Some(candidate.title.clone())),
+                    )
+                }
+                Answer::Url(url) => Some(item.entry.clone().into_line(url, None)),
+                Answer::Search(query) => {
+                    let query = if query.is_empty() {
+                        item.entry.title.clone()
+                    } else {
+                        query
+                    };
+                    match search_titles(&query, None).await {
+                        Ok(candidates) => item.candidates = candidates,
+                        Err(e) => eprintln!("{}", e.reason),
+                    }
+                    continue;
+                }
+                Answer::Skip => {
+                    left.push(item);
+                    break;
+                }
+                Answer::Drop => break,
+                Answer::Quit => {
+                    left.push(item);
+                    left.extend(items.by_ref().map(|(_, item)| item));
+                    break;
+                }
+                Answer::Invalid => {
+                    eprintln!("Unknown answer.");
+                    continue;
+                }
+            };
+            if let Some(line) = line {
+                add_line(&mut lines, line);
+            }
+            break;
+        }
+    }
+    let changes = LibraryChange::diff(&current_lines, &lines);
+    if !changes.is_empty() {
+        update_csv(path, lines)?;
+    }
+    write_review_queue(path, &left)?;
+    Ok((changes, left.len()))
+}
+
+/// Prints the search results, numbered from 1.
+fn print_candidates(candidates: &[SearchResult]) {
+    if candidates.is_empty() {
+        println!("  No search results.");
+    }
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!("  {}. {} - {}", index + 1, candidate.title, candidate.url);
+    }
+}
+
+/// Adds the picked manga to the library, unless its URL is already in it.
+fn add_line(lines: &mut Vec<CSVLine>, line: CSVLine) {
+    if lines
+        .iter()
+        .any(|current| same_manga(&current.url, &line.url))
+    {
+        println!("{} is already in the library.", line.url);
+    } else {
+        lines.push(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_are_parsed() {
+        assert_eq!(parse_answer("2\n", 3), Answer::Pick(1));
+        assert_eq!(parse_answer("4", 3), Answer::Invalid);
+        assert_eq!(parse_answer("0", 3), Answer::Invalid);
+        assert_eq!(
+            parse_answer(" https://manganato.com/manga-ab123 ", 0),
+            Answer::Url("https://manganato.com/manga-ab123".to_owned())
+        );
+        assert_eq!(
+            parse_answer("/ roxy is serious", 0),
+            Answer::Search("roxy is serious".to_owned())
+        );
+        assert_eq!(parse_answer("/", 0), Answer::Search(String::new()));
+        assert_eq!(parse_answer("\n", 0), Answer::Skip);
+        assert_eq!(parse_answer("d", 0), Answer::Drop);
+        assert_eq!(parse_answer("q", 0), Answer::Quit);
+    }
+}
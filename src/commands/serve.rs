@@ -0,0 +1,404 @@
+use crate::commands::add::add_to_library;
+use crate::commands::list::fetch_chapters;
+use crate::commands::remove::remove_manga;
+use crate::commands::undo::restore_library;
+use crate::commands::unread::{set_chapter, set_status};
+use crate::feed::FeedFormat;
+use crate::file_ops::read_csv;
+use crate::file_ops::save::extract_backup_path;
+use crate::models::ReadingStatus;
+use crate::notifiers::{announce_new_chapters, NotifierConfig};
+use crate::output::{CommandReport, EntryReport, LibraryChange, NewChapterReport, OutputFormat};
+use crate::scraper::create_client;
+use crate::server::{
+    feed_response, html_response, json_response, plain_response, read_json, serve, DASHBOARD,
+};
+use crate::utils::wait_for_shutdown;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use reqwest::Client;
+use serde::Deserialize;
+use std::io;
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// What the server needs to answer the requests, shared between them.
+struct ServeState {
+    path: Option<PathBuf>,
+    client: Client,
+    notifiers: Vec<NotifierConfig>,
+    verbose: bool,
+    /// Held while the library is written, so that two requests don't overwrite each other's changes.
+    write_lock: Mutex<()>,
+}
+
+/// The body of a request adding a manga.
+#[derive(Deserialize)]
+struct AddRequest {
+    url: String,
+}
+
+/// The body of a request setting the last chapter read.
+#[derive(Deserialize)]
+struct ChapterRequest {
+    chapter: f32,
+}
+
+/// The body of a request setting the reading status.
+#[derive(Deserialize)]
+struct StatusRequest {
+    status: String,
+}
+
+/// Serves the library over HTTP, with a JSON API mirroring the commands and an HTML dashboard, until stopped with Ctrl-C or SIGTERM.
+/// The API is made of:
+/// * `GET /api/mangas`: the entries of the library, without fetching their pages.
+/// * `GET /api/updates`: the entries along with their last chapter online. The new chapters are announced to the notifiers, as with list.
+/// * `POST /api/mangas` with `{"url": "..."}`: adds a manga.
+/// * `DELETE /api/mangas/{position}`: removes a manga.
+/// * `PUT /api/mangas/{position}/chapter` with `{"chapter": 12}`: sets the last chapter read.
+/// * `PUT /api/mangas/{position}/status` with `{"status": "completed"}`: sets the reading status.
+/// * `POST /api/undo`: undoes the last write operation.
+///
+/// The dashboard is served at `/`, and the feed of the new chapters at `/feed`.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `bind`: the address to listen to, e.g. `127.0.0.1:8080`.
+/// * `notifiers`: the notifiers to call for each new chapter.
+/// * `verbose`: if true, the requests are printed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub async fn serve_library(
+    path: Option<PathBuf>,
+    bind: &str,
+    notifiers: Vec<NotifierConfig>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("serve");
+    match TcpListener::bind(bind) {
+        Ok(listener) => {
+            if format.is_table() {
+                if let Ok(address) = listener.local_addr() {
+                    println!(
+                        "Serving the library on http://{}. Press Ctrl-C to stop.",
+                        address
+                    );
+                }
+            }
+            let state = Arc::new(ServeState {
+                path,
+                client: create_client().unwrap(),
+                notifiers,
+                verbose,
+                write_lock: Mutex::new(()),
+            });
+            let handler = move |request: Request<Body>| route(state.clone(), request);
+            match serve(listener, handler, wait_for_shutdown()).await {
+                Ok(()) => report.message = Some("Stopped serving the library.".to_owned()),
+                Err(e) => report.error(None, format!("The server failed: {}", e)),
+            }
+        }
+        Err(e) => report.error(None, format!("Couldn't listen to {}: {}", bind, e)),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Dispatches a request to the right endpoint.
+async fn route(state: Arc<ServeState>, request: Request<Body>) -> Response<Body> {
+    if state.verbose {
+        println!("{} {}", request.method(), request.uri());
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match (method, segments.as_slice()) {
+        (Method::GET, []) => html_response(DASHBOARD),
+        (Method::GET, ["feed"]) => feed_response(&request, &state.path, FeedFormat::Atom),
+        (Method::GET, ["api", "mangas"]) => list_entries(&state),
+        (Method::GET, ["api", "updates"]) => check_updates(&state).await,
+        (Method::POST, ["api", "mangas"]) => match read_json::<AddRequest>(request).await {
+            Ok(body) => add_entry(&state, &body.url).await,
+            Err(response) => response,
+        },
+        (Method::DELETE, ["api", "mangas", position]) => match position.parse::<usize>() {
+            Ok(position) => remove_entry(&state, position).await,
+            Err(_) => plain_response(StatusCode::BAD_REQUEST, "The position must be a number."),
+        },
+        (Method::PUT, ["api", "mangas", position, "chapter"]) => {
+            let position = match position.parse::<usize>() {
+                Ok(position) => position,
+                Err(_) => {
+                    return plain_response(
+                        StatusCode::BAD_REQUEST,
+                        "The position must be a number.",
+                    )
+                }
+            };
+            match read_json::<ChapterRequest>(request).await {
+                Ok(body) => {
+                    edit_entry(&state, "set-chapter", |path| {
+                        set_chapter(path, position, body.chapter, false)
+                    })
+                    .await
+                }
+                Err(response) => response,
+            }
+        }
+        (Method::PUT, ["api", "mangas", position, "status"]) => {
+            let position = match position.parse::<usize>() {
+                Ok(position) => position,
+                Err(_) => {
+                    return plain_response(
+                        StatusCode::BAD_REQUEST,
+                        "The position must be a number.",
+                    )
+                }
+            };
+            match read_json::<StatusRequest>(request).await {
+                Ok(body) => match body.status.parse::<ReadingStatus>() {
+                    Ok(status) => {
+                        edit_entry(&state, "set-status", |path| {
+                            set_status(path, position, status, false)
+                        })
+                        .await
+                    }
+                    Err(e) => plain_response(StatusCode::BAD_REQUEST, &e),
+                },
+                Err(response) => response,
+            }
+        }
+        (Method::POST, ["api", "undo"]) => undo(&state).await,
+        _ => plain_response(StatusCode::NOT_FOUND, "Not found."),
+    }
+}
+
+/// Lists the entries of the library, without fetching their pages.
+fn list_entries(state: &ServeState) -> Response<Body> {
+    let mut report = CommandReport::new("list");
+    match read_csv(&state.path, &false) {
+        Ok(lines) => {
+            report.entries = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| EntryReport::from_line(i + 1, line))
+                .collect();
+            json_response(StatusCode::OK, &report)
+        }
+        Err(e) => {
+            report.error(None, format!("An error occurred : {}", e));
+            json_response(StatusCode::INTERNAL_SERVER_ERROR, &report)
+        }
+    }
+}
+
+/// Fetches the last chapter of every entry, and announces the new ones to the notifiers.
+/// The mangas that couldn't be fetched are listed in the errors, and still appear in the entries.
+async fn check_updates(state: &ServeState) -> Response<Body> {
+    let mut report = CommandReport::new("list");
+    let lines = match read_csv(&state.path, &false) {
+        Ok(lines) => lines,
+        Err(e) => {
+            report.error(None, format!("An error occurred : {}", e));
+            return json_response(StatusCode::INTERNAL_SERVER_ERROR, &report);
+        }
+    };
+    let mut found: Vec<NewChapterReport> = Vec::new();
+    for (i, (line, result)) in lines
+        .iter()
+        .zip(fetch_chapters(&lines, &state.client, &false).await)
+        .enumerate()
+    {
+        match result {
+            Ok(line_chapter) => {
+                let entry = EntryReport::from_line_chapter(i + 1, &line_chapter);
+                if entry.has_new {
+                    found.push(NewChapterReport::from_line_chapter(i + 1, &line_chapter));
+                }
+                report.entries.push(entry);
+            }
+            Err(e) => {
+                report.entries.push(EntryReport::from_line(i + 1, line));
+                report.error(Some(&line.url), e.reason);
+            }
+        }
+    }
+    report.new_chapters = found.clone();
+    match announce_new_chapters(&state.path, &state.notifiers, &state.client, found).await {
+        Ok((_, errors)) => report.errors.extend(errors),
+        Err(e) => report.error(None, format!("Error while recording the releases: {}", e)),
+    }
+    json_response(StatusCode::OK, &report)
+}
+
+/// Adds a manga to the library.
+async fn add_entry(state: &ServeState, url: &str) -> Response<Body> {
+    let _guard = state.write_lock.lock().await;
+    let mut report = CommandReport::new("add");
+    add_to_library(state.path.clone(), url, &state.client, false, &mut report).await;
+    let status = if !report.errors.is_empty() {
+        StatusCode::BAD_REQUEST
+    } else if report.changes.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    json_response(status, &report)
+}
+
+/// Removes the manga at the position.
+async fn remove_entry(state: &ServeState, position: usize) -> Response<Body> {
+    let _guard = state.write_lock.lock().await;
+    let mut report = CommandReport::new("remove");
+    let count = match read_csv(&state.path, &false) {
+        Ok(lines) => lines.len(),
+        Err(e) => {
+            report.error(None, format!("An error occurred : {}", e));
+            return json_response(StatusCode::INTERNAL_SERVER_ERROR, &report);
+        }
+    };
+    if position == 0 || position > count {
+        report.error(None, format!("There is no manga at position {}.", position));
+        return json_response(StatusCode::NOT_FOUND, &report);
+    }
+    match remove_manga(state.path.clone(), &position.to_string(), false) {
+        Ok(changes) => {
+            report.changes = changes;
+            report.message = Some("The manga has been removed.".to_owned());
+            json_response(StatusCode::OK, &report)
+        }
+        Err(e) => {
+            report.error(None, format!("An error occurred : {}", e));
+            json_response(StatusCode::INTERNAL_SERVER_ERROR, &report)
+        }
+    }
+}
+
+/// Edits an entry of the library, and reports the change.
+/// # Arguments:
+/// * `state`: the state of the server.
+/// * `command`: the name of the command, for the report.
+/// * `edit`: the edition to make, given the path to the CSV.
+async fn edit_entry(
+    state: &ServeState,
+    command: &str,
+    edit: impl FnOnce(&Option<PathBuf>) -> Result<Vec<LibraryChange>, io::Error>,
+) -> Response<Body> {
+    let _guard = state.write_lock.lock().await;
+    let mut report = CommandReport::new(command);
+    match edit(&state.path) {
+        Ok(changes) => {
+            report.changes = changes;
+            json_response(StatusCode::OK, &report)
+        }
+        Err(e) => {
+            let status = if e.kind() == ErrorKind::InvalidInput {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            report.error(None, e);
+            json_response(status, &report)
+        }
+    }
+}
+
+/// Undoes the last write operation on the library.
+async fn undo(state: &ServeState) -> Response<Body> {
+    let _guard = state.write_lock.lock().await;
+    let mut report = CommandReport::new("undo");
+    restore_library(extract_backup_path(&state.path), false, &mut report);
+    let status = if report.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    json_response(status, &report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_ops::write_file::{create_file, update_csv};
+    use crate::models::CSVLine;
+    use crate::test_utils::line;
+    use hyper::body::to_bytes;
+    use serial_test::serial;
+    use std::fs;
+
+    fn state(path: &str) -> Arc<ServeState> {
+        Arc::new(ServeState {
+            path: Some(PathBuf::from(path)),
+            client: create_client().unwrap(),
+            notifiers: Vec::new(),
+            verbose: false,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    fn request(method: Method, uri: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    async fn json(response: Response<Body>) -> serde_json::Value {
+        serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn api_sets_the_chapter_and_undoes() -> Result<(), std::io::Error> {
+        let path = Some(PathBuf::from("test_serve.csv"));
+        create_file(&path)?;
+        update_csv(
+            &path,
+            vec![CSVLine {
+                title: "title1".to_owned(),
+                ..line("url1", 3.0)
+            }],
+        )?;
+        let state = state("test_serve.csv");
+
+        let response = route(state.clone(), request(Method::GET, "/api/mangas", "")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["entries"][0]["last_chapter_num"], 3.0);
+
+        let response = route(
+            state.clone(),
+            request(Method::PUT, "/api/mangas/1/chapter", r#"{"chapter": 5}"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let report = json(response).await;
+        assert_eq!(report["changes"][0]["previous_chapter_num"], 3.0);
+        assert_eq!(report["changes"][0]["chapter_num"], 5.0);
+
+        let response = route(
+            state.clone(),
+            request(Method::PUT, "/api/mangas/2/chapter", r#"{"chapter": 5}"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = route(state.clone(), request(Method::POST, "/api/undo", "")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["changes"][0]["chapter_num"], 3.0);
+
+        let response = route(state.clone(), request(Method::DELETE, "/api/mangas/3", "")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = route(state, request(Method::POST, "/api/mangas", "{}")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        fs::remove_file("test_serve.csv")?;
+        fs::remove_file("test_serve.csv.bak")?;
+        fs::remove_file("test_serve.history.csv")?;
+        Ok(())
+    }
+}
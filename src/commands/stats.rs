@@ -0,0 +1,180 @@
+use crate::commands::list::fetch_chapters;
+use crate::file_ops::history::read_history;
+use crate::file_ops::read_csv;
+use crate::file_ops::releases::read_releases;
+use crate::models::{CSVLine, ReadingStatus};
+use crate::output::{CommandReport, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS};
+use crate::scraper::create_client;
+use crate::stats::{compute_stats, sparkline, LibraryStats, PeriodCount, ACTIVITY_DAYS};
+use chrono::Utc;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::path::PathBuf;
+
+/// Computes the statistics of the library: its size, the chapters read from the reading history, the backlog, and the release cadence of the mangas.
+/// The last chapter of the mangas being read is fetched to measure the backlog.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `stale_months`: the number of months without release after which a manga is stale.
+/// * `with_sparkline`: if true, the chapters read per week and per month are drawn as sparklines in table mode.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: [`EXIT_PARTIAL_FAILURE`] if some mangas couldn't be fetched, leaving them out of the backlog.
+pub async fn show_stats(
+    path: Option<PathBuf>,
+    stale_months: u32,
+    with_sparkline: bool,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("stats");
+    let files = read_csv(&path, &verbose)
+        .and_then(|lines| Ok((lines, read_history(&path)?, read_releases(&path)?)));
+    let (lines, history, releases) = match files {
+        Ok(files) => files,
+        Err(e) => {
+            report.error(None, format!("An error occurred! {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let latest = fetch_latest(&lines, verbose, &mut report).await;
+    let stats = compute_stats(
+        &lines,
+        &history,
+        &releases,
+        latest.as_deref(),
+        stale_months,
+        Utc::now(),
+    );
+    if format.is_table() {
+        print_stats(&stats, with_sparkline);
+    }
+    report.message = Some(format!(
+        "{} manga(s), {} chapter(s) read.",
+        stats.series, stats.chapters_read
+    ));
+    report.stats = Some(stats);
+    report.print(format);
+    if report.errors.is_empty() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_PARTIAL_FAILURE
+    }
+}
+
+/// Fetches the last chapter of the mangas being read.
+/// The mangas that couldn't be fetched are added to the errors of the report.
+/// # Returns:
+/// The URL and the number of the last chapter of each manga fetched, None if the client couldn't be created.
+async fn fetch_latest(
+    lines: &[CSVLine],
+    verbose: bool,
+    report: &mut CommandReport,
+) -> Option<Vec<(String, f32)>> {
+    let client = match create_client() {
+        Ok(client) => client,
+        Err(e) => {
+            report.error(None, format!("The backlog couldn't be measured: {}", e));
+            return None;
+        }
+    };
+    let reading: Vec<CSVLine> = lines
+        .iter()
+        .filter(|line| line.status == ReadingStatus::Reading)
+        .cloned()
+        .collect();
+    if verbose {
+        println!("Fetching the last chapter of the mangas being read...");
+    }
+    let mut latest: Vec<(String, f32)> = Vec::new();
+    for (line, result) in reading
+        .iter()
+        .zip(fetch_chapters(&reading, &client, &verbose).await)
+    {
+        match result {
+            Ok(found) => latest.push((found.line.url, found.chapter.num)),
+            Err(e) => report.error(Some(&line.url), format!("{}: {}", line.title, e.reason)),
+        }
+    }
+    Some(latest)
+}
+
+/// Prints the statistics, section by section.
+fn print_stats(stats: &LibraryStats, with_sparkline: bool) {
+    println!("{}", "Library".if_supports_color(Stdout, |t| t.bold()));
+    println!("  Mangas: {}", stats.series);
+    for (status, count) in &stats.statuses {
+        println!("    {}: {}", status, count);
+    }
+    println!("  Chapters read: {}", stats.chapters_read);
+    match (stats.backlog, stats.backlog_series) {
+        (Some(backlog), Some(series)) => println!(
+            "  Backlog: {} unread chapter(s) in {} manga(s)",
+            backlog.if_supports_color(Stdout, |t| t.green()),
+            series
+        ),
+        _ => println!("  Backlog: unknown"),
+    }
+
+    print_periods("Chapters read per week", &stats.weekly, with_sparkline);
+    print_periods("Chapters read per month", &stats.monthly, with_sparkline);
+
+    println!(
+        "{}",
+        format!("Most active over the last {} days", ACTIVITY_DAYS)
+            .if_supports_color(Stdout, |t| t.bold())
+    );
+    if stats.most_active.is_empty() {
+        println!("  No release recorded.");
+    }
+    for series in &stats.most_active {
+        let cadence = series
+            .days_between_releases
+            .map(|days| format!(", one every {:.1} day(s)", days))
+            .unwrap_or_default();
+        println!(
+            "  {}: {} release(s){}",
+            series.title, series.releases, cadence
+        );
+    }
+
+    println!(
+        "{}",
+        format!("Without release for {} month(s)", stats.stale_months)
+            .if_supports_color(Stdout, |t| t.bold())
+    );
+    if stats.stale.is_empty() {
+        println!("  None.");
+    }
+    for series in &stats.stale {
+        let last = series
+            .last_release
+            .map(|date| format!("last release on {}", date.format("%Y-%m-%d")))
+            .unwrap_or_else(|| "no release recorded".to_owned());
+        println!(
+            "  {} ({})",
+            series.title,
+            last.if_supports_color(Stdout, |t| t.yellow())
+        );
+    }
+}
+
+/// Prints the chapters read during each period, the current one last.
+fn print_periods(title: &str, periods: &[PeriodCount], with_sparkline: bool) {
+    println!("{}", title.if_supports_color(Stdout, |t| t.bold()));
+    if with_sparkline {
+        let counts: Vec<usize> = periods.iter().map(|period| period.chapters).collect();
+        if let (Some(first), Some(last)) = (periods.first(), periods.last()) {
+            println!(
+                "  {} {} to {}",
+                sparkline(&counts),
+                first.start.format("%Y-%m-%d"),
+                last.start.format("%Y-%m-%d")
+            );
+        }
+    }
+    for period in periods {
+        println!("  {}: {}", period.start.format("%Y-%m-%d"), period.chapters);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::file_ops::{extract_path_or_default, read_csv};
+use crate::output::{CommandReport, LibraryChange, OutputFormat, EXIT_FATAL, EXIT_SUCCESS};
+use crate::sync::{init, merge_files, sync, SyncSummary};
+use std::env::current_exe;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Sets up the sync of the library with a git remote, then syncs it.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `remote`: the URL or the path of the remote repository.
+/// * `force`: if true, the remote replaces the one the library is already synced with.
+/// * `verbose`: if true, the git commands are printed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn init_sync(path: Option<PathBuf>, remote: &str, force: bool, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("sync init");
+    run_sync(&mut report, &path, |library| {
+        init(library, remote, &merge_driver_command()?, force, verbose)
+    });
+    report.print(format);
+    report.exit_code()
+}
+
+/// Syncs the library with its git remote.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `verbose`: if true, the git commands are printed.
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn sync_library(path: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("sync");
+    run_sync(&mut report, &path, |library| sync(library, verbose));
+    report.print(format);
+    report.exit_code()
+}
+
+/// Runs a sync, and fills the report with the changes it made to the library.
+fn run_sync(
+    report: &mut CommandReport,
+    path: &Option<PathBuf>,
+    run: impl FnOnce(&Path) -> Result<SyncSummary, io::Error>,
+) {
+    let library = extract_path_or_default(path);
+    let before = if library.exists() {
+        read_csv(path, &false).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    match run(&library) {
+        Ok(summary) => {
+            let after = read_csv(path, &false).unwrap_or_default();
+            report.changes = LibraryChange::diff(&before, &after);
+            report.message = Some(format!(
+                "The library is synced: {} commit(s) received, {} sent, {} conflict(s) resolved by the highest chapter.",
+                summary.pulled,
+                summary.pushed,
+                summary.conflicts.len()
+            ));
+            report.conflicts = summary.conflicts;
+        }
+        Err(e) => report.error(None, format!("Error while syncing: {}", e)),
+    }
+}
+
+/// The command git runs to merge two versions of the library: this program, with the hidden merge-driver command.
+fn merge_driver_command() -> Result<String, io::Error> {
+    Ok(format!(
+        "\"{}\" merge-driver %O %A %B",
+        current_exe()?.display()
+    ))
+}
+
+/// Merges two versions of the library for git (See [`crate::sync::merge_files`]).
+/// Nothing is printed on success, as git runs it while merging.
+/// # Arguments:
+/// * `base`: the version both sides started from.
+/// * `ours`: the local version, replaced by the merged one.
+/// * `theirs`: the version being merged.
+/// # Returns:
+/// The exit code of the command. Git considers the merge failed unless it is 0.
+pub fn run_merge_driver(base: &Path, ours: &Path, theirs: &Path) -> i32 {
+    match merge_files(base, ours, theirs) {
+        Ok(_) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("An error occurred while merging the library: {}", e);
+            EXIT_FATAL
+        }
+    }
+}
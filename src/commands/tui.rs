@@ -0,0 +1,148 @@
+use crate::commands::undo::restore_library;
+use crate::commands::unread::{set_chapter, set_status};
+use crate::file_ops::read_csv;
+use crate::file_ops::save::extract_backup_path;
+use crate::models::{CSVLine, MangaChapter};
+use crate::output::{CommandReport, OutputFormat, EXIT_FATAL, EXIT_SUCCESS};
+use crate::scraper::{concurrency, create_client, find_last_chapter};
+use crate::tui::{Action, App};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use reqwest::Client;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::Semaphore;
+
+/// The delay between two redraws when nothing happens, so that the fetched pages show up.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Shows the library in a full-screen terminal UI.
+/// The pages are fetched in the background, and the rows are updated as they come.
+/// From there, the user can open the next chapter, mark a manga as read or unread, change its status and undo.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `format`: the output format, used if the library can't be read.
+/// # Returns:
+/// The exit code of the command.
+pub async fn browse_library(path: Option<PathBuf>, format: OutputFormat) -> i32 {
+    let lines = match read_csv(&path, &false) {
+        Ok(lines) => lines,
+        Err(e) => {
+            let mut report = CommandReport::new("tui");
+            report.error(None, format!("An error occurred : {}", e));
+            report.print(format);
+            return EXIT_FATAL;
+        }
+    };
+    let client = create_client().unwrap();
+    let (sender, mut receiver) = unbounded_channel();
+    fetch_all(&lines, &client, &sender);
+    let mut app = App::new(lines);
+
+    let mut terminal = ratatui::init();
+    let mut run = || -> Result<(), io::Error> {
+        loop {
+            while let Ok((url, result)) = receiver.try_recv() {
+                app.set_fetched(&url, result);
+            }
+            terminal.draw(|frame| app.draw(frame))?;
+            if !event::poll(TICK)? {
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            app.message = None;
+            let action = match app.handle_key(key) {
+                Some(action) => action,
+                None => continue,
+            };
+            match action {
+                Action::Quit => break,
+                Action::Open(url) => {
+                    app.message = Some(match open::that(&url) {
+                        Ok(()) => format!("Opened {}", url),
+                        Err(e) => format!("Error while opening {}: {}", url, e),
+                    })
+                }
+                Action::SetChapter(position, chapter) => {
+                    let result = set_chapter(&path, position, chapter, false);
+                    app.message = Some(match result {
+                        Ok(_) => format!("The last chapter read is now #{}.", chapter),
+                        Err(e) => e.to_string(),
+                    });
+                    reload(&mut app, &path);
+                }
+                Action::SetStatus(position, status) => {
+                    let result = set_status(&path, position, status, false);
+                    app.message = Some(match result {
+                        Ok(_) => format!("The status is now {}.", status),
+                        Err(e) => e.to_string(),
+                    });
+                    reload(&mut app, &path);
+                }
+                Action::Undo => {
+                    let mut report = CommandReport::new("undo");
+                    restore_library(extract_backup_path(&path), false, &mut report);
+                    app.message = match report.errors.first() {
+                        Some(error) => Some(error.reason.clone()),
+                        None => report.message,
+                    };
+                    reload(&mut app, &path);
+                }
+                Action::Refresh => {
+                    if let Ok(lines) = read_csv(&path, &false) {
+                        app.set_lines(lines.clone());
+                        app.reset_fetches();
+                        fetch_all(&lines, &client, &sender);
+                    }
+                }
+            }
+        }
+        Ok(())
+    };
+    let result = run();
+    ratatui::restore();
+
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("An error occurred : {}", e);
+            EXIT_FATAL
+        }
+    }
+}
+
+/// Fetches the page of every line in the background, and sends the results as they come.
+/// At most [`crate::scraper::concurrency`] pages are fetched at the same time.
+fn fetch_all(
+    lines: &[CSVLine],
+    client: &Client,
+    sender: &UnboundedSender<(String, Result<MangaChapter, String>)>,
+) {
+    let permits = Arc::new(Semaphore::new(concurrency()));
+    for line in lines {
+        let url = line.url.clone();
+        let client = client.clone();
+        let sender = sender.clone();
+        let permits = permits.clone();
+        tokio::spawn(async move {
+            let _permit = permits.acquire().await;
+            let result = find_last_chapter(&url, Some(&client), &false)
+                .await
+                .map_err(|e| e.reason);
+            let _ = sender.send((url, result));
+        });
+    }
+}
+
+/// Reads the library again after it has been written.
+fn reload(app: &mut App, path: &Option<PathBuf>) {
+    match read_csv(path, &false) {
+        Ok(lines) => app.set_lines(lines),
+        Err(e) => app.message = Some(format!("An error occurred : {}", e)),
+    }
+}
@@ -1,13 +1,106 @@
-use crate::file_ops::save::restore_file;
+use crate::file_ops::read_csv;
+use crate::file_ops::save::{extract_backup_path, restore_file};
+use crate::output::{CommandReport, LibraryChange, OutputFormat};
 use std::path::PathBuf;
 
-/// Restores the CSV with a backup.
+/// Restores the CSV with its backup.
 /// # Arguments:
-/// * `path`: The path to restore from. If empty, a default path will be used.
+/// * `path`: The path to the CSV file, e.g. the library of a profile. If empty, the default library will be restored.
 /// * `verbose`: If set, the command will be a little more verbose.
-pub fn restore_csv(path: Option<PathBuf>, verbose: bool) {
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn restore_csv(path: Option<PathBuf>, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("undo");
+    restore_library(extract_backup_path(&path), verbose, &mut report);
+    report.print(format);
+    report.exit_code()
+}
+
+/// Restores the CSV with a backup, and fills the report with the changes or the error.
+/// # Arguments:
+/// * `path`: The backup to restore from (See [`extract_backup_path`]). If empty, the backup of the default library will be used.
+/// * `verbose`: If set, the command will be a little more verbose.
+/// * `report`: the report to fill.
+pub fn restore_library(path: Option<PathBuf>, verbose: bool, report: &mut CommandReport) {
+    let csv_path = restored_csv_path(&path);
+    let before = read_csv(&csv_path, &false).unwrap_or_default();
     match restore_file(&path, &verbose) {
-        Ok(()) => println!("The CSV has been restored to the previous state."),
-        Err(e) => eprintln!("An error happened: {:?}", e)
+        Ok(()) => {
+            let after = read_csv(&csv_path, &false).unwrap_or_default();
+            report.changes = LibraryChange::diff(&before, &after);
+            report.message = Some("The CSV has been restored to the previous state.".to_owned())
+        }
+        Err(e) => report.error(None, format!("An error happened: {:?}", e)),
+    }
+}
+
+/// Finds the CSV restored by the backup, i.e. the backup path without its .bak extension.
+/// If no path is given, the default CSV will be restored.
+fn restored_csv_path(path: &Option<PathBuf>) -> Option<PathBuf> {
+    path.as_ref().map(|backup| backup.with_extension(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::profile::create_profile;
+    use crate::config::load_config;
+    use crate::file_ops::write_file::{create_file, update_csv};
+    use crate::output::EXIT_SUCCESS;
+    use crate::paths::{profile_library_path, DATA_DIR_VAR};
+    use crate::test_utils::line;
+    use serial_test::serial;
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    /// Changes the chapter of the library twice, undoes the last change, and checks that the first one is back.
+    fn check_undo(library: &Path) -> Result<(), io::Error> {
+        let path = Some(library.to_path_buf());
+        update_csv(&path, vec![line("url1", 1.0)])?;
+        update_csv(&path, vec![line("url1", 2.0)])?;
+        assert_eq!(
+            restore_csv(path.clone(), false, OutputFormat::Json),
+            EXIT_SUCCESS
+        );
+        assert_eq!(read_csv(&path, &false)?, vec![line("url1", 1.0)]);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn undo_restores_the_library_of_a_profile() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("manga_updater_undo_profile_test");
+        let _ = fs::remove_dir_all(&dir);
+        let previous = env::var_os(DATA_DIR_VAR);
+        env::set_var(DATA_DIR_VAR, &dir);
+        let created = create_profile("alice", OutputFormat::Json);
+        let result = check_undo(&profile_library_path("alice"));
+        match previous {
+            Some(previous) => env::set_var(DATA_DIR_VAR, previous),
+            None => env::remove_var(DATA_DIR_VAR),
+        }
+        assert_eq!(created, EXIT_SUCCESS);
+        result?;
+        fs::remove_dir_all(&dir)
+    }
+
+    #[test]
+    #[serial]
+    fn undo_restores_the_library_of_the_config() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("manga_updater_undo_config_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let config_path = dir.join("config.toml");
+        fs::write(
+            &config_path,
+            format!("library = {:?}\n", dir.join("library.csv")),
+        )?;
+        let config = load_config(&Some(config_path), None)?;
+        create_file(&config.library)?;
+        check_undo(config.library.as_deref().unwrap())?;
+        fs::remove_dir_all(&dir)
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,12 @@
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout};
 
+use crate::file_ops::history::{record_reading, warn_history};
 use crate::file_ops::read_csv;
 use crate::file_ops::write_file::update_csv;
-use crate::models::CSVLine;
+use crate::models::{CSVLine, ReadingStatus};
+use crate::output::{CommandReport, LibraryChange, OutputFormat};
+use std::io;
+use std::io::{Error, ErrorKind};
 use std::path::PathBuf;
 
 /// Sets a manga to the previous chapter. The url param is the line of the manga to reset.
@@ -10,7 +14,11 @@ use std::path::PathBuf;
 /// * `path`: The path to the source file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default]`).
 /// * `url`: The line number of the manga to reset.
 /// * `verbose`: if true, more messages will be shown.
-pub fn unread_chapter(path: Option<PathBuf>, url: &str, verbose: bool) {
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command.
+pub fn unread_chapter(path: Option<PathBuf>, url: &str, verbose: bool, format: OutputFormat) -> i32 {
+    let mut report = CommandReport::new("unread");
     match read_csv(&path, &verbose) {
         Ok(lines) => {
             if verbose {
@@ -24,17 +32,73 @@ pub fn unread_chapter(path: Option<PathBuf>, url: &str, verbose: bool) {
                     println!("Resetting chapter at position {}", number);
                 }
                 let reset_lines = search_and_reset(&lines, number - 1);
+                let changes = LibraryChange::diff(&lines, &reset_lines);
                 match update_csv(&path, reset_lines) {
-                    Ok(_) => println!(
-                        "{}",
-                        "The manga has been reset to its previous chapter.".green()
-                    ),
-                    Err(e) => eprintln!("{}", e),
+                    Ok(_) => {
+                        report.changes = changes;
+                        if format.is_table() {
+                            println!(
+                                "{}",
+                                "The manga has been reset to its previous chapter.".if_supports_color(Stdout, |t| t.green())
+                            )
+                        }
+                    }
+                    Err(e) => report.error(None, e),
                 }
+            } else {
+                report.error(None, format!("{} is not a line number.", url));
             }
         }
-        Err(e) => eprintln!("{}", e),
+        Err(e) => report.error(None, e),
+    }
+    report.print(format);
+    report.exit_code()
+}
+
+/// Sets the last chapter read of a manga, be it before or after the current one.
+/// # Arguments:
+/// * `path`: The path to the source file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default]`).
+/// * `position`: the line number of the manga, starting at 1.
+/// * `chapter`: the chapter number to set.
+/// * `verbose`: if true, more messages will be shown.
+/// # Returns:
+/// The change made to the library, and an io::Error if the line doesn't exist or something went wrong with the CSV.
+pub fn set_chapter(path: &Option<PathBuf>, position: usize, chapter: f32, verbose: bool) -> Result<Vec<LibraryChange>, io::Error> {
+    edit_line(path, position, verbose, |line| line.last_chapter_num = chapter)
+}
+
+/// Sets the reading status of a manga.
+/// # Arguments:
+/// * `path`: The path to the source file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default]`).
+/// * `position`: the line number of the manga, starting at 1.
+/// * `status`: the status to set.
+/// * `verbose`: if true, more messages will be shown.
+/// # Returns:
+/// The change made to the library, and an io::Error if the line doesn't exist or something went wrong with the CSV.
+pub fn set_status(path: &Option<PathBuf>, position: usize, status: ReadingStatus, verbose: bool) -> Result<Vec<LibraryChange>, io::Error> {
+    edit_line(path, position, verbose, |line| line.status = status)
+}
+
+/// Edits the line at the position, and writes the CSV.
+/// The chapters read are recorded in the history (See [`crate::file_ops::history::record_reading`]).
+/// # Errors:
+/// * `io::InvalidInput` if there is no line at the position.
+fn edit_line(path: &Option<PathBuf>, position: usize, verbose: bool, edit: impl FnOnce(&mut CSVLine)) -> Result<Vec<LibraryChange>, io::Error> {
+    let lines = read_csv(path, &verbose)?;
+    let mut updated_lines = lines.clone();
+    match position.checked_sub(1).and_then(|index| updated_lines.get_mut(index)) {
+        Some(line) => edit(line),
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("There is no manga at position {}.", position),
+            ))
+        }
     }
+    let changes = LibraryChange::diff(&lines, &updated_lines);
+    update_csv(path, updated_lines)?;
+    warn_history(record_reading(path, &changes));
+    Ok(changes)
 }
 
 /// Middle function to search and reset the specified line.
@@ -66,6 +130,8 @@ fn inner_search(
             url: vec[current_pos].clone().url,
             last_chapter_num: vec[current_pos].last_chapter_num - 1f32,
             title: vec[current_pos].clone().title,
+            status: vec[current_pos].status,
+            score: vec[current_pos].score,
         };
         new_vec.push(line);
         inner_search(vec, new_vec, current_pos + 1, to_reset)
@@ -74,6 +140,8 @@ fn inner_search(
             url: vec[current_pos].clone().url,
             last_chapter_num: vec[current_pos].last_chapter_num,
             title: vec[current_pos].clone().title,
+            status: vec[current_pos].status,
+            score: vec[current_pos].score,
         };
         new_vec.push(line);
         inner_search(vec, new_vec, current_pos + 1, to_reset)
@@ -84,21 +152,28 @@ fn inner_search(
 mod tests {
     use super::*;
 
+    #[allow(clippy::vec_init_then_push)]
     fn prepare_lines() -> Vec<CSVLine> {
         let line1 = CSVLine {
             url: String::from("Url1"),
             last_chapter_num: 3f32,
             title: "title1".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let line2 = CSVLine {
             url: String::from("Url2"),
             last_chapter_num: 4f32,
             title: "title2".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let line3 = CSVLine {
             url: String::from("Url3"),
             last_chapter_num: 5f32,
             title: "title3".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let mut lines = Vec::new();
         lines.push(line1);
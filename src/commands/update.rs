@@ -1,34 +1,72 @@
+use crate::file_ops::history::{record_reading, warn_history};
 use crate::file_ops::read_csv;
 use crate::file_ops::write_file::update_csv;
 use crate::models::CSVLine;
-use crate::scraper::{create_client, find_last_chapter};
-use crate::utils::{update_chapter_in_vec, update_chapters_multiple};
-use futures::future::try_join_all;
-use owo_colors::OwoColorize;
+use crate::output::{
+    CommandReport, LibraryChange, OutputFormat, EXIT_FATAL, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::scraper::{concurrency, create_client, find_last_chapter};
+use crate::utils::{update_chapter_in_vec, update_chapters_multiple, ScraperError};
+use futures::stream::{self, StreamExt};
+use owo_colors::{OwoColorize, Stream::Stdout};
 use reqwest::Client;
 use std::num::ParseIntError;
 use std::path::PathBuf;
 
+/// Fetches the last chapter of every line given, and keeps only the successful ones.
+/// The failures are added to the report.
+/// # Arguments
+/// * `client`: the reqwest client to send requests with.
+/// * `lines`: the lines to search an update for.
+/// * `verbose`: The verbose option.
+/// * `report`: the report in which the errors are stored.
+/// # Returns
+/// The updated lines that could be fetched.
+async fn search_updates(
+    client: &Client,
+    lines: Vec<CSVLine>,
+    verbose: bool,
+    report: &mut CommandReport,
+) -> Vec<CSVLine> {
+    let urls: Vec<String> = lines.iter().map(|line| line.url.clone()).collect();
+    let results: Vec<_> = stream::iter(lines)
+        .map(|line| search_update(line, Some(client), &verbose))
+        .buffered(concurrency())
+        .collect()
+        .await;
+    let mut chapters: Vec<CSVLine> = Vec::new();
+    for (url, result) in urls.iter().zip(results) {
+        match result {
+            Ok(chapter) => chapters.push(chapter),
+            Err(e) => report.error(Some(url), e.reason),
+        }
+    }
+    if verbose {
+        println!("{} chapters retrieved.", chapters.len());
+    }
+    chapters
+}
+
 /// Searches for all updates in the csv file.
+/// The mangas that couldn't be reached keep their current chapter.
 /// # Arguments
 /// * `client`: the reqwest client to send requests with.
 /// * `lines`: the original CSV lines
 /// * `verbose`: The verbose option.
+/// * `report`: the report in which the errors are stored.
 /// # Returns
 /// An option containing the list of CSV lines to update the file.
-async fn update_all(client: Client, lines: Vec<CSVLine>, verbose: bool) -> Option<Vec<CSVLine>> {
+async fn update_all(
+    client: Client,
+    lines: Vec<CSVLine>,
+    verbose: bool,
+    report: &mut CommandReport,
+) -> Option<Vec<CSVLine>> {
     if verbose {
         println!("Client created, fetching the chapters asynchronously...");
     }
-    let chapters_future: Vec<_> = lines
-        .into_iter()
-        .map(|line| search_update(line, Some(&client), &verbose))
-        .collect();
-    let chapters = try_join_all(chapters_future).await.unwrap();
-    if verbose {
-        println!("{} chapters retrieved.", chapters.len());
-    }
-    Some(chapters)
+    let chapters = search_updates(&client, lines.clone(), verbose, report).await;
+    Some(update_chapters_multiple(lines, chapters))
 }
 
 /// Parses the given input and updates the selected lines.
@@ -37,6 +75,7 @@ async fn update_all(client: Client, lines: Vec<CSVLine>, verbose: bool) -> Optio
 /// * `input_numbers`: the string containing the numbers to update separated by a space.
 /// * `lines`: the original CSV lines
 /// * `verbose`: The verbose option.
+/// * `report`: the report in which the errors are stored.
 /// # Returns
 /// An option containing the list of CSV lines to update the file.
 async fn update_multiple(
@@ -44,6 +83,7 @@ async fn update_multiple(
     imput_numbers: &str,
     lines: Vec<CSVLine>,
     verbose: bool,
+    report: &mut CommandReport,
 ) -> Option<Vec<CSVLine>> {
     if verbose {
         println!("Trying to parse all the numbers in ({})", imput_numbers);
@@ -53,27 +93,12 @@ async fn update_multiple(
         .map(|n| n.parse::<usize>())
         .collect();
 
-    let chapters_future: Vec<_> = numbers
+    let selected: Vec<CSVLine> = numbers
         .into_iter()
-        .map(|n| {
-            if n.is_ok() {
-                if let Some(line) = lines.get(n.unwrap() - 1) {
-                    Some(line.clone())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .flatten()
-        .map(|line| search_update(line, Some(&client), &verbose))
+        .filter_map(|n| n.ok().and_then(|n| lines.get(n - 1).cloned()))
         .collect();
 
-    let chapters = try_join_all(chapters_future).await.unwrap();
-    if verbose {
-        println!("{} chapters retrieved.", chapters.len());
-    }
+    let chapters = search_updates(&client, selected, verbose, report).await;
     Some(update_chapters_multiple(lines, chapters))
 }
 
@@ -83,6 +108,7 @@ async fn update_multiple(
 /// * `input_numbers`: the string containing the numbers to update separated by a space.
 /// * `lines`: the original CSV lines
 /// * `verbose`: The verbose option.
+/// * `report`: the report in which the errors are stored.
 /// # Returns
 /// An option containing the list of CSV lines to update the file.
 async fn update_one(
@@ -90,6 +116,7 @@ async fn update_one(
     url: &str,
     lines: Vec<CSVLine>,
     verbose: bool,
+    report: &mut CommandReport,
 ) -> Option<Vec<CSVLine>> {
     if verbose {
         println!(
@@ -102,9 +129,13 @@ async fn update_one(
             println!("Updating chapter at position {}", number - 1);
         }
         if let Some(line) = lines.get(number - 1) {
-            let updated_line = search_update(line.clone(), Some(&client), &verbose)
-                .await
-                .unwrap();
+            let updated_line = match search_update(line.clone(), Some(&client), &verbose).await {
+                Ok(updated_line) => updated_line,
+                Err(e) => {
+                    report.error(Some(&line.url), e.reason);
+                    return None;
+                }
+            };
             if verbose {
                 println!(
                     "New chapter for {} is {} (stored is {})",
@@ -112,15 +143,17 @@ async fn update_one(
                 );
             }
             if line.last_chapter_num == updated_line.last_chapter_num {
-                println!("This manga is already up to date!");
+                report.message = Some("This manga is already up to date!".to_owned());
                 None
             } else {
                 Some(update_chapter_in_vec(lines, updated_line))
             }
         } else {
+            report.error(None, format!("There is no manga at position {}.", number));
             None
         }
     } else {
+        report.error(Some(url), "The argument is neither all nor a line number.");
         None
     }
 }
@@ -129,15 +162,23 @@ async fn update_one(
 /// # Arguments
 /// * `path`: The optional path to the CSV.
 /// * `values`: The values to overwrite the CSV with.
-fn update_csv_with_values(path: &Option<PathBuf>, values: Option<Vec<CSVLine>>) {
+/// * `report`: the report in which the errors are stored.
+/// # Returns
+/// True if the CSV has been written.
+fn update_csv_with_values(
+    path: &Option<PathBuf>,
+    values: Option<Vec<CSVLine>>,
+    report: &mut CommandReport,
+) -> bool {
     match values {
-        Some(val) => match update_csv(&path, val) {
-            Ok(_) => {
-                println!("{}" ,"All the mangas have been updated to their most recent chapter.".green())
+        Some(val) => match update_csv(path, val) {
+            Ok(_) => true,
+            Err(e) => {
+                report.error(None, e);
+                false
             }
-            Err(e) => eprintln!("{}", e),
         },
-        None => eprintln!("No values provided to update."),
+        None => false,
     }
 }
 
@@ -151,12 +192,14 @@ async fn search_update(
     manga: CSVLine,
     client: Option<&Client>,
     verbose: &bool,
-) -> Result<CSVLine, Box<dyn std::error::Error>> {
+) -> Result<CSVLine, ScraperError> {
     let chapter = find_last_chapter(manga.url.as_str(), client, verbose).await?;
     Ok(CSVLine {
         url: manga.url,
         last_chapter_num: chapter.num,
         title: chapter.manga_title,
+        status: manga.status,
+        score: manga.score,
     })
 }
 
@@ -164,18 +207,67 @@ async fn search_update(
 /// # Arguments:
 /// * `path`: The path to the source file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`]).
 /// * `url`: The URl to the manga to update. It can also be _all_, as it will update every stored manga. It can also be line numbers separated by spaces.
-/// It can also be a line number.
+///   It can also be a line number.
 /// * `verbose`: if true, more messages will be shown.
-pub async fn update_chapters(path: Option<PathBuf>, url: &str, verbose: bool) {
+/// * `format`: the output format.
+/// # Returns:
+/// The exit code of the command: partial failure if some mangas couldn't be fetched but the CSV has been written.
+pub async fn update_chapters(
+    path: Option<PathBuf>,
+    url: &str,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    let mut report = CommandReport::new("update");
     let client = create_client().unwrap();
-    let chapters = match read_csv(&path, &verbose) {
-        Ok(lines) if url.eq("all") => update_all(client, lines, verbose).await,
-        Ok(lines) if url.contains(' ') => update_multiple(client, url, lines, verbose).await,
-        Ok(lines) => update_one(client, url, lines, verbose).await,
+    let (before, chapters) = match read_csv(&path, &verbose) {
+        Ok(lines) if url.eq("all") => (
+            lines.clone(),
+            update_all(client, lines, verbose, &mut report).await,
+        ),
+        Ok(lines) if url.contains(' ') => (
+            lines.clone(),
+            update_multiple(client, url, lines, verbose, &mut report).await,
+        ),
+        Ok(lines) => (
+            lines.clone(),
+            update_one(client, url, lines, verbose, &mut report).await,
+        ),
         Err(e) => {
-            eprintln!("{}", e);
-            None
+            report.error(None, e);
+            (Vec::new(), None)
         }
     };
-    update_csv_with_values(&path, chapters)
+    let after = chapters.clone().unwrap_or_default();
+    let written = update_csv_with_values(&path, chapters, &mut report);
+    if written {
+        report.changes = LibraryChange::diff(&before, &after);
+        warn_history(record_reading(&path, &report.changes));
+    }
+    if format.is_table() {
+        for error in &report.errors {
+            match &error.url {
+                Some(url) => eprintln!("Error while updating {}: {}", url, error.reason),
+                None => eprintln!("{}", error.reason),
+            }
+        }
+        if let Some(message) = &report.message {
+            println!("{}", message);
+        } else if written {
+            println!(
+                "{}",
+                "All the mangas have been updated to their most recent chapter.".if_supports_color(Stdout, |t| t.green())
+            )
+        } else if report.errors.is_empty() {
+            eprintln!("No values provided to update.")
+        }
+    } else {
+        report.emit(format);
+    }
+    match (written, report.errors.is_empty()) {
+        (_, true) => EXIT_SUCCESS,
+        (true, false) => EXIT_PARTIAL_FAILURE,
+        (false, false) => EXIT_FATAL,
+    }
 }
+
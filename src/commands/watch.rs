@@ -0,0 +1,275 @@
+use crate::cadence::{estimate_all, next_check};
+use crate::commands::digest::send_period_digest;
+use crate::commands::list::fetch_chapters;
+use crate::email::{DigestPeriod, EmailConfig};
+use crate::file_ops::read_csv;
+use crate::models::CSVLine;
+use crate::file_ops::releases::read_releases;
+use crate::notifiers::{announce_new_chapters, NotifierConfig};
+use crate::output::{CommandReport, NewChapterReport, OutputFormat, EXIT_FATAL, EXIT_SUCCESS};
+use crate::scraper::create_client;
+use crate::utils::wait_for_shutdown;
+use chrono::{DateTime, Local, Utc};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use rand::Rng;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::sleep;
+
+/// Watches the mangas of the CSV file, and records their new chapters in the releases file.
+/// The pages are fetched right away, then every `interval` plus a random delay up to `jitter`, so that the requests aren't sent at fixed times.
+/// The same client is kept for the whole session. The CSV is read at each check to take the changes into account, but it is never written.
+/// When a chapter is found for the first time, every notifier is called (See [`crate::notifiers::announce_new_chapters`]).
+/// If `adaptive` is set, each manga is checked at its own pace instead, from its release cadence (See [`crate::cadence::next_check`]).
+/// The mangas without a regular cadence are still checked every `interval`, and the loop wakes up at least as often to pick up the new mangas.
+/// If a digest is requested, an email listing the new chapters is sent each time the period has elapsed, the first one a period after the start.
+/// The program stops on SIGTERM or Ctrl-C, once the check in progress is done.
+/// # Arguments:
+/// * `path`: The path to the CSV file. If None, the default path will be used (See [`crate::file_ops::extract_path_or_default`])
+/// * `interval`: the delay between two checks.
+/// * `jitter`: the maximum random delay added to the interval.
+/// * `adaptive`: if true, each manga is checked at its own pace.
+/// * `notifiers`: the notifiers to call for each new chapter.
+/// * `digest`: the optional period of the email digest, along with the SMTP settings.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format. With json formats, a report is emitted after each check.
+/// # Returns:
+/// The exit code of the command: fatal if the CSV can't be read at start.
+#[allow(clippy::too_many_arguments)]
+pub async fn watch_chapters(
+    path: Option<PathBuf>,
+    interval: Duration,
+    jitter: Duration,
+    adaptive: bool,
+    notifiers: Vec<NotifierConfig>,
+    digest: Option<(DigestPeriod, EmailConfig)>,
+    verbose: bool,
+    format: OutputFormat,
+) -> i32 {
+    if let Err(e) = read_csv(&path, &verbose) {
+        let mut report = CommandReport::new("watch");
+        report.error(None, format!("An error occurred : {}", e));
+        report.print(format);
+        return EXIT_FATAL;
+    }
+    let client = create_client().unwrap();
+    let (shutdown_sender, mut shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        wait_for_shutdown().await;
+        let _ = shutdown_sender.send(true);
+    });
+    if format.is_table() {
+        println!(
+            "Watching for new chapters every {}. Press Ctrl-C to stop.",
+            format_duration(interval)
+        );
+    }
+
+    let mut last_digest = Utc::now();
+    // When each manga should be checked next, by URL.
+    let mut schedule: Option<HashMap<String, DateTime<Utc>>> = adaptive.then(HashMap::new);
+    loop {
+        check_chapters(&path, &client, &notifiers, &mut schedule, interval, verbose, format).await;
+        if let Some((period, email)) = &digest {
+            if Utc::now() - last_digest >= period.duration() {
+                let mut report = CommandReport::new("email-digest");
+                if let Err(e) = send_period_digest(&path, email, *period, &mut report).await {
+                    report.error(None, e);
+                }
+                report.print(format);
+                last_digest = Utc::now();
+            }
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+        let delay = match &schedule {
+            Some(schedule) => next_wake_up(schedule, interval),
+            None => interval,
+        } + random_jitter(jitter);
+        if verbose {
+            println!("Next check in {}.", format_duration(delay));
+        }
+        tokio::select! {
+            _ = sleep(delay) => {},
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    if format.is_table() {
+        println!("Stopped watching.");
+    }
+    EXIT_SUCCESS
+}
+
+/// Fetches the pages once, and records the chapters that haven't been seen before.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file.
+/// * `client`: the client shared between the checks.
+/// * `notifiers`: the notifiers to call for each new chapter.
+/// * `schedule`: when each manga should be checked next, if they are checked at their own pace. Only the mangas due are fetched, then rescheduled.
+/// * `interval`: the delay between two checks.
+/// * `verbose`: if true, more messages will be shown.
+/// * `format`: the output format.
+async fn check_chapters(
+    path: &Option<PathBuf>,
+    client: &Client,
+    notifiers: &[NotifierConfig],
+    schedule: &mut Option<HashMap<String, DateTime<Utc>>>,
+    interval: Duration,
+    verbose: bool,
+    format: OutputFormat,
+) {
+    let mut report = CommandReport::new("watch");
+    let lines = match read_csv(path, &verbose) {
+        Ok(lines) => lines,
+        Err(e) => {
+            report.error(None, format!("An error occurred : {}", e));
+            print_check(&report, format);
+            return;
+        }
+    };
+    let now = Utc::now();
+    let (positions, due): (Vec<usize>, Vec<CSVLine>) = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            schedule.as_ref().is_none_or(|schedule| {
+                schedule.get(&line.url).is_none_or(|next| *next <= now)
+            })
+        })
+        .map(|(i, line)| (i + 1, line.clone()))
+        .unzip();
+    if verbose && schedule.is_some() {
+        println!("{} of {} manga(s) due for a check.", due.len(), lines.len());
+    }
+    let mut found: Vec<NewChapterReport> = Vec::new();
+    for ((position, line), result) in positions
+        .iter()
+        .zip(&due)
+        .zip(fetch_chapters(&due, client, &verbose).await)
+    {
+        match result {
+            Ok(chapter) if chapter.chapter.num > chapter.line.last_chapter_num => {
+                found.push(NewChapterReport::from_line_chapter(*position, &chapter))
+            }
+            Ok(_) => {}
+            Err(e) => report.error(Some(&line.url), e.reason),
+        }
+    }
+
+    match announce_new_chapters(path, notifiers, client, found).await {
+        Ok((announced, errors)) => {
+            report.new_chapters = announced;
+            report.errors.extend(errors);
+        }
+        Err(e) => report.error(None, format!("Error while recording the releases: {}", e)),
+    }
+    if let Some(schedule) = schedule {
+        // Without the releases, the mangas are checked at every interval.
+        let estimates = read_releases(path)
+            .map(|releases| estimate_all(&releases))
+            .unwrap_or_default();
+        let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+        schedule.retain(|url, _| lines.iter().any(|line| &line.url == url));
+        for line in &due {
+            schedule.insert(
+                line.url.clone(),
+                next_check(estimates.get(&line.url), now, interval),
+            );
+        }
+    }
+    print_check(&report, format);
+}
+
+/// Computes the delay until the next manga is due for a check, at most the interval so that the new mangas are checked.
+fn next_wake_up(schedule: &HashMap<String, DateTime<Utc>>, interval: Duration) -> Duration {
+    schedule
+        .values()
+        .min()
+        .map_or(interval, |next| {
+            (*next - Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+                .min(interval)
+        })
+}
+
+/// Prints the result of a check.
+/// In table mode, the new chapters are printed along with the time of the check.
+fn print_check(report: &CommandReport, format: OutputFormat) {
+    if !format.is_table() {
+        report.emit(format);
+        return;
+    }
+    let now = Local::now().format("%Y-%m-%d %H:%M");
+    if report.new_chapters.is_empty() {
+        println!("[{}] No new chapters.", now);
+    }
+    for chapter in &report.new_chapters {
+        println!(
+            "[{}] {}: {green_hashtag}{num}: {title}",
+            now,
+            chapter.manga_title,
+            num = chapter.num.if_supports_color(Stdout, |t| t.green()),
+            title = chapter.chapter_title.if_supports_color(Stdout, |t| t.green()),
+            green_hashtag = "#".if_supports_color(Stdout, |t| t.green())
+        );
+    }
+    for error in &report.errors {
+        match &error.url {
+            Some(url) => eprintln!("[{}] Error with {}: {}", now, url, error.reason),
+            None => eprintln!("[{}] {}", now, error.reason),
+        }
+    }
+}
+
+/// Picks a random delay between zero and the maximum jitter.
+fn random_jitter(jitter: Duration) -> Duration {
+    if jitter.is_zero() {
+        Duration::ZERO
+    } else {
+        Duration::from_secs(rand::thread_rng().gen_range(0..=jitter.as_secs()))
+    }
+}
+
+/// Formats a duration in the same way it is given on the command line, e.g. `1h30m`.
+fn format_duration(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    let mut formatted = String::new();
+    for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if secs >= size {
+            formatted.push_str(&format!("{}{}", secs / size, unit));
+            secs %= size;
+        }
+    }
+    if formatted.is_empty() {
+        formatted.push_str("0s");
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_duration;
+
+    #[test]
+    fn format_duration_is_parsable() {
+        let duration = Duration::from_secs(93_784);
+        assert_eq!(format_duration(duration), "1d2h3m4s");
+        assert_eq!(parse_duration(&format_duration(duration)), Ok(duration));
+    }
+
+    #[test]
+    fn jitter_stays_in_range() {
+        let jitter = Duration::from_secs(60);
+        for _ in 0..20 {
+            assert!(random_jitter(jitter) <= jitter);
+        }
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+    }
+}
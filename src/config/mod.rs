@@ -0,0 +1,263 @@
+use crate::email::EmailConfig;
+use crate::notifiers::NotifierConfig;
+use crate::output::OutputFormat;
+use crate::paths::{default_config_path, profile_config_path};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// The settings of the program, read from a TOML file.
+/// Every section is optional, so an empty or missing file gives the default settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The library to use when no path is given, instead of `mangas.csv` in the data folder.
+    pub library: Option<PathBuf>,
+    /// The maximum number of pages fetched at the same time.
+    pub concurrency: Option<usize>,
+    /// The user agent sent with the requests, instead of the default one.
+    pub user_agent: Option<String>,
+    /// The search page of the website, followed by the query, used to find the URLs of the imported titles.
+    pub search_url: Option<String>,
+    /// The maximum number of requests sent per second, across the whole program. Unlimited if None.
+    pub rate_limit: Option<f64>,
+    /// The folder of the downloaded chapters, instead of the one next to the library.
+    pub download_dir: Option<PathBuf>,
+    /// When to colour the output.
+    pub colours: Colours,
+    /// The options used when they aren't given on the command line.
+    pub defaults: Defaults,
+    /// The notifiers to call when new chapters are found.
+    pub notifiers: Vec<NotifierConfig>,
+    /// The SMTP settings used to send the digests.
+    pub email: Option<EmailConfig>,
+}
+
+/// When to colour the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colours {
+    /// Only when the output is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The options used when they aren't given on the command line.
+/// The flags can only be turned on: a flag set here can't be turned off for a single command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// The output format.
+    pub format: Option<OutputFormat>,
+    /// Be more verbose about the process.
+    pub verbose: bool,
+    /// Display only the new chapters with list.
+    pub new: bool,
+    /// Do not ask for a manga to open after listing.
+    pub no_prompt: bool,
+    /// Open the last chapter directly with open.
+    pub direct: bool,
+}
+
+/// Checks if the optional path is defined, and if so, returns it.
+/// If None, the default path will be returned instead: `config.toml` in the config folder (See [`crate::paths::default_config_path`]).
+/// # Argument:
+/// * `config_path`: the optional config path.
+/// # Returns:
+/// The path to the config file, be it custom or default.
+fn extract_config_path_or_default(config_path: &Option<PathBuf>) -> PathBuf {
+    match config_path {
+        Some(path) => path.clone(),
+        None => default_config_path(),
+    }
+}
+
+/// Reads the config file, and the settings of the profile if any.
+/// The settings of the profile are applied over the ones of the config file: the tables are merged, and the other values replaced.
+/// If no path is given and the default file doesn't exist, the default settings are returned.
+/// # Arguments:
+/// * `config_path`: the optional config path.
+/// * `profile`: the optional profile, whose settings are read from its own file (See [`crate::paths::profile_config_path`]).
+/// # Returns:
+/// The settings read from the files.
+/// # Errors:
+/// * `io::NotFound` if a custom path is given but the file doesn't exist.
+/// * `io::InvalidData` if a file isn't valid TOML, or if the settings are unknown.
+pub fn load_config(config_path: &Option<PathBuf>, profile: Option<&str>) -> Result<Config, io::Error> {
+    let path = extract_config_path_or_default(config_path);
+    let mut sources: Vec<PathBuf> = Vec::new();
+    let mut settings = Table::new();
+    if config_path.is_some() || path.exists() {
+        settings = read_settings(&path)?;
+        sources.push(path);
+    }
+    if let Some(profile) = profile {
+        let profile_path = profile_config_path(profile);
+        if profile_path.exists() {
+            merge_settings(&mut settings, read_settings(&profile_path)?);
+            sources.push(profile_path);
+        }
+    }
+    Value::Table(settings).try_into().map_err(|e| {
+        let files: Vec<String> = sources.iter().map(|path| path.display().to_string()).collect();
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("The config file {} is invalid: {}", files.join(", "), e),
+        )
+    })
+}
+
+/// Reads a TOML file, without checking the settings it holds.
+fn read_settings(path: &PathBuf) -> Result<Table, io::Error> {
+    let contents = fs::read_to_string(path)?;
+    contents.parse::<Table>().map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("The config file {} is invalid: {}", path.display(), e),
+        )
+    })
+}
+
+/// Applies the settings over the base ones.
+/// The tables are merged recursively, while the other values, arrays included, are replaced.
+fn merge_settings(base: &mut Table, settings: Table) {
+    for (key, value) in settings {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(table)) => merge_settings(base_table, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::SmtpSecurity;
+
+    /// Parses the contents of a config file.
+    fn parse_config(contents: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    #[test]
+    fn parse_empty_config() -> Result<(), toml::de::Error> {
+        let config = parse_config("")?;
+        assert!(config.notifiers.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_notifiers() -> Result<(), toml::de::Error> {
+        let config = parse_config(
+            r#"
+            [[notifiers]]
+            type = "discord"
+            url = "http://localhost/discord"
+
+            [[notifiers]]
+            type = "ntfy"
+            server = "https://ntfy.sh"
+            topic = "mangas"
+
+            [[notifiers]]
+            type = "shell"
+            command = "echo $MANGA_TITLE"
+            "#,
+        )?;
+        assert_eq!(config.notifiers.len(), 3);
+        assert!(matches!(
+            &config.notifiers[1],
+            NotifierConfig::Ntfy { topic, .. } if topic == "mangas"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_email() -> Result<(), toml::de::Error> {
+        let config = parse_config(
+            r#"
+            [email]
+            host = "smtp.example.com"
+            port = 465
+            security = "tls"
+            username = "bot"
+            password = "secret"
+            from = "bot@example.com"
+            to = ["reader@example.com"]
+            "#,
+        )?;
+        let email = config.email.unwrap();
+        assert_eq!(email.port, Some(465));
+        assert_eq!(email.security, SmtpSecurity::Tls);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_settings() -> Result<(), toml::de::Error> {
+        let config = parse_config(
+            r#"
+            library = "/srv/mangas.csv"
+            concurrency = 4
+            user_agent = "manga_updater"
+            colours = "never"
+
+            [defaults]
+            format = "json"
+            no_prompt = true
+            "#,
+        )?;
+        assert_eq!(config.library, Some(PathBuf::from("/srv/mangas.csv")));
+        assert_eq!(config.concurrency, Some(4));
+        assert_eq!(config.colours, Colours::Never);
+        assert_eq!(config.defaults.format, Some(OutputFormat::Json));
+        assert!(config.defaults.no_prompt);
+        assert!(!config.defaults.direct);
+        Ok(())
+    }
+
+    #[test]
+    fn profile_settings_are_merged() -> Result<(), toml::de::Error> {
+        let mut settings: Table = r#"
+            concurrency = 4
+            [defaults]
+            format = "json"
+            new = true
+            [[notifiers]]
+            type = "ntfy"
+            topic = "everyone"
+            "#
+        .parse()?;
+        let profile: Table = r#"
+            concurrency = 2
+            [defaults]
+            new = false
+            [[notifiers]]
+            type = "ntfy"
+            topic = "alice"
+            "#
+        .parse()?;
+        merge_settings(&mut settings, profile);
+        let config: Config = Value::Table(settings).try_into()?;
+        assert_eq!(config.concurrency, Some(2));
+        assert_eq!(config.defaults.format, Some(OutputFormat::Json));
+        assert!(!config.defaults.new);
+        assert_eq!(config.notifiers.len(), 1);
+        assert!(matches!(
+            &config.notifiers[0],
+            NotifierConfig::Ntfy { topic, .. } if topic == "alice"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert!(parse_config("colour = true").is_err());
+    }
+}
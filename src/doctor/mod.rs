@@ -0,0 +1,349 @@
+use crate::models::CSVLine;
+use crate::urls::canonical_url;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// The number of days the backup may be older than the library before it is stale.
+pub const STALE_BACKUP_DAYS: u64 = 30;
+
+/// The kind of problem found in the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A line of the CSV, or its headers, can't be read as it should.
+    MalformedRow,
+    /// The same manga is in the library more than once, under different forms of its URL.
+    Duplicate,
+    /// The page of the manga can't be downloaded.
+    Unreachable,
+    /// The page of the manga doesn't match the selectors anymore.
+    LayoutChanged,
+    /// The last chapter read is above the last chapter online.
+    AheadOfSite,
+    /// The backup is much older than the library, so `undo` would revert every change since then.
+    StaleBackup,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IssueKind::MalformedRow => write!(f, "malformed row"),
+            IssueKind::Duplicate => write!(f, "duplicate"),
+            IssueKind::Unreachable => write!(f, "unreachable"),
+            IssueKind::LayoutChanged => write!(f, "layout changed"),
+            IssueKind::AheadOfSite => write!(f, "ahead of the site"),
+            IssueKind::StaleBackup => write!(f, "stale backup"),
+        }
+    }
+}
+
+/// A problem found in the library, along with the way to fix it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// The line number of the manga, if the problem concerns one.
+    pub position: Option<usize>,
+    pub url: Option<String>,
+    pub problem: String,
+    pub suggestion: String,
+    /// True if the fix loses nothing, so that `doctor --fix` applies it.
+    pub fixable: bool,
+    pub fixed: bool,
+}
+
+impl Issue {
+    pub fn new(
+        kind: IssueKind,
+        position: Option<usize>,
+        url: Option<&str>,
+        problem: impl ToString,
+        suggestion: impl ToString,
+        fixable: bool,
+    ) -> Issue {
+        Issue {
+            kind,
+            position,
+            url: url.map(str::to_owned),
+            problem: problem.to_string(),
+            suggestion: suggestion.to_string(),
+            fixable,
+            fixed: false,
+        }
+    }
+}
+
+/// A line of the CSV, read without stopping at the malformed values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// The line number of the manga, starting from 1.
+    pub position: usize,
+    /// The manga, None if the line can't be read at all. The unknown statuses and scores are left to their defaults.
+    pub line: Option<CSVLine>,
+}
+
+/// Reads the lines of the library, reporting the malformed ones instead of failing like [`crate::file_ops::parse_csv`].
+/// The lines with an unknown status, a score that isn't a number or extra columns are still read, and can be fixed by writing them back.
+/// # Argument:
+/// * `contents`: the reader of the CSV.
+/// # Returns:
+/// The lines, along with the problems found.
+pub fn read_rows<R: io::Read>(contents: R) -> Result<(Vec<Row>, Vec<Issue>), io::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents);
+    let mut issues: Vec<Issue> = Vec::new();
+    let headers = reader.headers()?.clone();
+    if headers.get(0) != Some("URL")
+        || headers.get(1) != Some("Last chapter")
+        || headers.get(2) != Some("Title")
+    {
+        issues.push(Issue::new(
+            IssueKind::MalformedRow,
+            None,
+            None,
+            format!(
+                "The headers are \"{}\" instead of \"URL,Last chapter,Title,Status,Score\".",
+                headers.iter().collect::<Vec<_>>().join(",")
+            ),
+            "Replace the first line of the CSV file with the expected headers.",
+            false,
+        ));
+    }
+
+    let mut rows: Vec<Row> = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let position = index + 1;
+        let unreadable = |problem: String| {
+            Issue::new(
+                IssueKind::MalformedRow,
+                Some(position),
+                None,
+                problem,
+                format!(
+                    "Edit or remove line {} of the CSV file: the library can't be read until then.",
+                    position
+                ),
+                false,
+            )
+        };
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                issues.push(unreadable(format!("The line can't be read: {}.", e)));
+                rows.push(Row {
+                    position,
+                    line: None,
+                });
+                continue;
+            }
+        };
+        let url = record.get(0).unwrap_or("");
+        let chapter = record.get(1).unwrap_or("");
+        if record.len() < 3 || url.is_empty() {
+            issues.push(unreadable(if url.is_empty() {
+                "The line has no URL.".to_owned()
+            } else {
+                format!(
+                    "The line has {} column(s), but the URL, the last chapter and the title are needed.",
+                    record.len()
+                )
+            }));
+            rows.push(Row {
+                position,
+                line: None,
+            });
+            continue;
+        }
+        let Ok(last_chapter_num) = chapter.parse::<f32>() else {
+            issues.push(unreadable(format!(
+                "The last chapter \"{}\" isn't a number.",
+                chapter
+            )));
+            rows.push(Row {
+                position,
+                line: None,
+            });
+            continue;
+        };
+
+        let mut fixable = |problem: String, suggestion: &str| {
+            issues.push(Issue::new(
+                IssueKind::MalformedRow,
+                Some(position),
+                Some(url),
+                problem,
+                suggestion,
+                true,
+            ))
+        };
+        let status = record.get(3).unwrap_or("");
+        let status = match status.parse() {
+            Ok(status) => status,
+            Err(_) => {
+                if !status.is_empty() {
+                    fixable(
+                        format!("The status \"{}\" is unknown.", status),
+                        "Set the status to reading.",
+                    );
+                }
+                Default::default()
+            }
+        };
+        let score = record.get(4).unwrap_or("");
+        let parsed_score = score.parse().ok();
+        if parsed_score.is_none() && !score.is_empty() {
+            fixable(
+                format!("The score \"{}\" isn't a number.", score),
+                "Clear the score.",
+            );
+        }
+        if record.len() > 5 {
+            fixable(
+                format!("The line has {} columns instead of 5.", record.len()),
+                "Remove the extra columns.",
+            );
+        }
+        rows.push(Row {
+            position,
+            line: Some(CSVLine {
+                url: url.to_owned(),
+                last_chapter_num,
+                title: record.get(2).unwrap_or("").to_owned(),
+                status,
+                score: parsed_score,
+            }),
+        });
+    }
+    Ok((rows, issues))
+}
+
+/// Finds the mangas present more than once in the library, under the same URL or different forms of it (See [`crate::urls::canonical_url`]).
+/// # Argument:
+/// * `lines`: the line number and the manga of each line that can be read.
+/// # Returns:
+/// The lines once every duplicate is merged into its first line, keeping the highest chapter read, along with an issue per duplicate.
+pub fn merge_duplicates(lines: &[(usize, CSVLine)]) -> (Vec<CSVLine>, Vec<Issue>) {
+    let mut merged: Vec<(String, usize, CSVLine)> = Vec::new();
+    let mut issues: Vec<Issue> = Vec::new();
+    for (position, line) in lines {
+        let key = canonical_url(&line.url);
+        match merged.iter_mut().find(|(other, _, _)| *other == key) {
+            Some((_, first, kept)) => {
+                kept.last_chapter_num = kept.last_chapter_num.max(line.last_chapter_num);
+                kept.score = kept.score.or(line.score);
+                issues.push(Issue::new(
+                    IssueKind::Duplicate,
+                    Some(*position),
+                    Some(&line.url),
+                    format!(
+                        "{} is already on line {}, as {}.",
+                        line.title, first, kept.url
+                    ),
+                    format!(
+                        "Remove line {}, keeping the highest chapter read on line {}.",
+                        position, first
+                    ),
+                    true,
+                ));
+            }
+            None => merged.push((key, *position, line.clone())),
+        }
+    }
+    (
+        merged.into_iter().map(|(_, _, line)| line).collect(),
+        issues,
+    )
+}
+
+/// Tells if the backup of the library is stale, i.e. older than the library by more than [`STALE_BACKUP_DAYS`] days.
+/// The backup is made before each change, so it only gets that old when the library is edited by hand or by git.
+/// # Arguments:
+/// * `library`: when the library has been modified.
+/// * `backup`: when the backup has been modified.
+pub fn is_stale_backup(library: SystemTime, backup: SystemTime) -> bool {
+    library
+        .duration_since(backup)
+        .is_ok_and(|age| age > Duration::from_secs(STALE_BACKUP_DAYS * 86_400))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReadingStatus;
+    use crate::test_utils;
+
+    #[test]
+    fn malformed_rows_are_reported() -> Result<(), io::Error> {
+        let csv = "URL,Last chapter,Title,Status,Score\n\
+            url1,3,One,reading,8\n\
+            url2,abc,Two\n\
+            url3,4,Three,someday,great\n\
+            ,1,Four\n\
+            url5,5,Five,completed,,extra\n";
+        let (rows, issues) = read_rows(csv.as_bytes())?;
+        assert_eq!(rows.len(), 5);
+        assert!(rows[1].line.is_none() && rows[3].line.is_none());
+        let three = rows[2].line.as_ref().unwrap();
+        assert_eq!(three.status, ReadingStatus::Reading);
+        assert_eq!(three.score, None);
+        assert_eq!(
+            rows[4].line.as_ref().unwrap().status,
+            ReadingStatus::Completed
+        );
+
+        let positions: Vec<(Option<usize>, bool)> = issues
+            .iter()
+            .map(|issue| (issue.position, issue.fixable))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (Some(2), false),
+                (Some(3), true),
+                (Some(3), true),
+                (Some(4), false),
+                (Some(5), true)
+            ]
+        );
+
+        let (_, issues) = read_rows("url,chapter\nurl1,1,One\n".as_bytes())?;
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].position, None);
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates_are_merged_into_the_first_line() {
+        let line = |url: &str, chapter: f32, score: Option<f32>| CSVLine {
+            title: "title".to_owned(),
+            score,
+            ..test_utils::line(url, chapter)
+        };
+        let lines = vec![
+            (1, line("https://manganato.com/manga-ab", 3.0, None)),
+            (2, line("https://manganato.com/manga-abc", 1.0, None)),
+            (
+                3,
+                line("https://readmanganato.com/manga-ab/", 7.0, Some(9.0)),
+            ),
+        ];
+        let (merged, issues) = merge_duplicates(&lines);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].url, "https://manganato.com/manga-ab");
+        assert_eq!(merged[0].last_chapter_num, 7.0);
+        assert_eq!(merged[0].score, Some(9.0));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].position, Some(3));
+    }
+
+    #[test]
+    fn only_old_backups_are_stale() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86_400);
+        assert!(is_stale_backup(now, now - day * 31));
+        assert!(!is_stale_backup(now, now - day));
+        assert!(!is_stale_backup(now - day, now));
+    }
+}
@@ -0,0 +1,544 @@
+use crate::utils::escape_html;
+use chrono::Utc;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::io::Reader;
+use image::ImageFormat;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The quality of the pages re-encoded once downscaled.
+const JPEG_QUALITY: u8 = 85;
+
+/// The screen of an e-reader, whose size the pages are downscaled to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// The Kindle Paperwhite and Oasis, at 1236x1648.
+    Kindle,
+    /// The Kobo Clara and Libra, at 1264x1680.
+    Kobo,
+    /// A custom size, given as `<width>x<height>`.
+    Custom(u32, u32),
+}
+
+impl Screen {
+    /// Gives the width and the height of the screen, in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        match self {
+            Screen::Kindle => (1236, 1648),
+            Screen::Kobo => (1264, 1680),
+            Screen::Custom(width, height) => (*width, *height),
+        }
+    }
+}
+
+impl FromStr for Screen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kindle" => Ok(Screen::Kindle),
+            "kobo" => Ok(Screen::Kobo),
+            other => other
+                .split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                .filter(|(width, height)| *width > 0 && *height > 0)
+                .map(|(width, height)| Screen::Custom(width, height))
+                .ok_or(format!(
+                    "Unknown screen {}. Use kindle, kobo or a size such as 1072x1448.",
+                    other
+                )),
+        }
+    }
+}
+
+/// The metadata of an EPUB.
+#[derive(Debug)]
+pub struct EpubMetadata<'a> {
+    pub title: &'a str,
+    /// A unique identifier of the book, e.g. the URL of the manga along with the chapters.
+    pub identifier: &'a str,
+}
+
+/// An image ready to be added to an EPUB.
+#[derive(Debug)]
+struct PreparedImage {
+    bytes: Vec<u8>,
+    extension: &'static str,
+    width: u32,
+    height: u32,
+}
+
+impl PreparedImage {
+    fn media_type(&self) -> &'static str {
+        match self.extension {
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            _ => "image/jpeg",
+        }
+    }
+}
+
+/// A file of the EPUB, listed in the manifest of the OPF.
+#[derive(Debug)]
+struct ManifestItem {
+    id: String,
+    href: String,
+    media_type: &'static str,
+    properties: Option<&'static str>,
+}
+
+/// Writes a fixed-layout EPUB3, one page per image, read from right to left.
+/// The chapters are added one at a time, so that only one of them is in memory.
+pub struct EpubWriter {
+    archive: ZipWriter<File>,
+    path: PathBuf,
+    temporary: PathBuf,
+    screen: Option<Screen>,
+    /// The size of the first page, given to the readers as the size of the book.
+    resolution: Option<(u32, u32)>,
+    items: Vec<ManifestItem>,
+    /// The ids of the pages, in the reading order.
+    spine: Vec<String>,
+    /// The title and the first page of each chapter.
+    toc: Vec<(String, String)>,
+    has_cover: bool,
+}
+
+impl EpubWriter {
+    /// Starts an EPUB, written to a temporary file until [`EpubWriter::finish`] is called.
+    /// # Arguments:
+    /// * `path`: the path to the EPUB.
+    /// * `screen`: the screen to downscale the pages to, if any. The smaller pages are kept as they are.
+    pub fn create(path: &Path, screen: Option<Screen>) -> Result<EpubWriter, io::Error> {
+        let temporary = path.with_extension("epub.tmp");
+        let mut archive = ZipWriter::new(File::create(&temporary)?);
+        // The readers expect the MIME type first, uncompressed.
+        archive.start_file("mimetype", stored())?;
+        archive.write_all(b"application/epub+zip")?;
+        archive.start_file("META-INF/container.xml", deflated())?;
+        archive.write_all(CONTAINER.as_bytes())?;
+        archive.start_file("OEBPS/style.css", deflated())?;
+        archive.write_all(STYLE.as_bytes())?;
+        Ok(EpubWriter {
+            archive,
+            path: path.to_owned(),
+            temporary,
+            screen,
+            resolution: None,
+            items: vec![
+                ManifestItem {
+                    id: "nav".to_owned(),
+                    href: "nav.xhtml".to_owned(),
+                    media_type: "application/xhtml+xml",
+                    properties: Some("nav"),
+                },
+                ManifestItem {
+                    id: "ncx".to_owned(),
+                    href: "toc.ncx".to_owned(),
+                    media_type: "application/x-dtbncx+xml",
+                    properties: None,
+                },
+                ManifestItem {
+                    id: "style".to_owned(),
+                    href: "style.css".to_owned(),
+                    media_type: "text/css",
+                    properties: None,
+                },
+            ],
+            spine: Vec::new(),
+            toc: Vec::new(),
+            has_cover: false,
+        })
+    }
+
+    /// Adds the cover, shown as the first page.
+    pub fn add_cover(&mut self, bytes: Vec<u8>) -> Result<(), io::Error> {
+        let image = prepare_image(bytes, self.screen)?;
+        self.add_page("cover", image, Some("cover-image"))?;
+        self.has_cover = true;
+        Ok(())
+    }
+
+    /// Adds a chapter, with an entry in the table of contents.
+    /// # Arguments:
+    /// * `title`: the title of the chapter.
+    /// * `pages`: the images of the pages, in the reading order.
+    /// # Returns:
+    /// The number of pages added.
+    pub fn add_chapter(&mut self, title: &str, pages: Vec<Vec<u8>>) -> Result<usize, io::Error> {
+        let chapter = self.toc.len() + 1;
+        let count = pages.len();
+        for (index, bytes) in pages.into_iter().enumerate() {
+            let id = format!("c{:03}-p{:03}", chapter, index + 1);
+            if index == 0 {
+                self.toc
+                    .push((title.to_owned(), format!("pages/{}.xhtml", id)));
+            }
+            let image = prepare_image(bytes, self.screen)?;
+            self.add_page(&id, image, None)?;
+        }
+        Ok(count)
+    }
+
+    /// Writes an image and the page showing it.
+    fn add_page(
+        &mut self,
+        id: &str,
+        image: PreparedImage,
+        properties: Option<&'static str>,
+    ) -> Result<(), io::Error> {
+        let image_href = format!("images/{}.{}", id, image.extension);
+        self.archive
+            .start_file(format!("OEBPS/{}", image_href), stored())?;
+        self.archive.write_all(&image.bytes)?;
+        self.items.push(ManifestItem {
+            id: format!("{}-image", id),
+            href: image_href.clone(),
+            media_type: image.media_type(),
+            properties,
+        });
+
+        let page_href = format!("pages/{}.xhtml", id);
+        self.archive
+            .start_file(format!("OEBPS/{}", page_href), deflated())?;
+        self.archive
+            .write_all(page_xhtml(id, &image_href, image.width, image.height).as_bytes())?;
+        self.items.push(ManifestItem {
+            id: id.to_owned(),
+            href: page_href,
+            media_type: "application/xhtml+xml",
+            properties: None,
+        });
+        self.spine.push(id.to_owned());
+        self.resolution.get_or_insert((image.width, image.height));
+        Ok(())
+    }
+
+    /// Writes the table of contents and the OPF, then moves the EPUB to its path.
+    /// Without a cover, the first page is used as the cover.
+    pub fn finish(mut self, metadata: &EpubMetadata) -> Result<(), io::Error> {
+        if !self.has_cover {
+            if let Some(first) = self
+                .items
+                .iter_mut()
+                .find(|item| item.media_type.starts_with("image/"))
+            {
+                first.properties = Some("cover-image");
+            }
+        }
+        let nav = nav_xhtml(metadata.title, &self.toc);
+        self.archive.start_file("OEBPS/nav.xhtml", deflated())?;
+        self.archive.write_all(nav.as_bytes())?;
+        let ncx = toc_ncx(metadata, &self.toc);
+        self.archive.start_file("OEBPS/toc.ncx", deflated())?;
+        self.archive.write_all(ncx.as_bytes())?;
+        let opf = self.content_opf(metadata);
+        self.archive.start_file("OEBPS/content.opf", deflated())?;
+        self.archive.write_all(opf.as_bytes())?;
+        self.archive.finish()?;
+        fs::rename(&self.temporary, &self.path)
+    }
+
+    /// Writes the OPF: the metadata, the manifest of the files and the reading order.
+    fn content_opf(&self, metadata: &EpubMetadata) -> String {
+        let mut opf = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        opf.push_str("<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" prefix=\"rendition: http://www.idpf.org/vocab/rendition/#\">\n");
+        opf.push_str("  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+        opf.push_str(&format!(
+            "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n",
+            escape_html(metadata.identifier)
+        ));
+        opf.push_str(&format!(
+            "    <dc:title>{}</dc:title>\n",
+            escape_html(metadata.title)
+        ));
+        opf.push_str("    <dc:language>en</dc:language>\n");
+        opf.push_str(&format!(
+            "    <meta property=\"dcterms:modified\">{}</meta>\n",
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+        ));
+        opf.push_str("    <meta property=\"rendition:layout\">pre-paginated</meta>\n");
+        opf.push_str("    <meta property=\"rendition:orientation\">portrait</meta>\n");
+        opf.push_str("    <meta property=\"rendition:spread\">none</meta>\n");
+        // Read by the Kindle tools, which ignore the EPUB3 properties.
+        opf.push_str("    <meta name=\"cover\" content=\"");
+        opf.push_str(
+            self.items
+                .iter()
+                .find(|item| item.properties == Some("cover-image"))
+                .map(|item| item.id.as_str())
+                .unwrap_or_default(),
+        );
+        opf.push_str("\"/>\n");
+        opf.push_str("    <meta name=\"fixed-layout\" content=\"true\"/>\n");
+        opf.push_str("    <meta name=\"book-type\" content=\"comic\"/>\n");
+        if let Some((width, height)) = self.screen.map(|screen| screen.size()).or(self.resolution) {
+            opf.push_str(&format!(
+                "    <meta name=\"original-resolution\" content=\"{}x{}\"/>\n",
+                width, height
+            ));
+        }
+        opf.push_str("  </metadata>\n  <manifest>\n");
+        for item in &self.items {
+            opf.push_str(&format!(
+                "    <item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>\n",
+                item.id,
+                item.href,
+                item.media_type,
+                item.properties
+                    .map(|properties| format!(" properties=\"{}\"", properties))
+                    .unwrap_or_default()
+            ));
+        }
+        opf.push_str("  </manifest>\n");
+        opf.push_str("  <spine toc=\"ncx\" page-progression-direction=\"rtl\">\n");
+        for id in &self.spine {
+            opf.push_str(&format!("    <itemref idref=\"{}\"/>\n", id));
+        }
+        opf.push_str("  </spine>\n</package>\n");
+        opf
+    }
+}
+
+fn stored() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Stored)
+}
+
+fn deflated() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// Reads the format and the size of an image, and downscales it to fit the screen if it is larger.
+/// A downscaled image is re-encoded as a JPEG, in shades of grey if it has no colour.
+fn prepare_image(bytes: Vec<u8>, screen: Option<Screen>) -> Result<PreparedImage, io::Error> {
+    let to_error = |e: image::ImageError| io::Error::new(io::ErrorKind::InvalidData, e);
+    let reader = Reader::new(Cursor::new(&bytes)).with_guessed_format()?;
+    let format = reader.format();
+    let extension = match format {
+        Some(ImageFormat::Png) => "png",
+        Some(ImageFormat::Jpeg) => "jpg",
+        Some(ImageFormat::Gif) => "gif",
+        Some(ImageFormat::WebP) => "webp",
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "A page isn't a PNG, JPEG, GIF or WebP image.",
+            ))
+        }
+    };
+    let (width, height) = reader.into_dimensions().map_err(to_error)?;
+    match screen.map(|screen| screen.size()) {
+        Some((max_width, max_height)) if width > max_width || height > max_height => {
+            let image = image::load_from_memory(&bytes).map_err(to_error)?.resize(
+                max_width,
+                max_height,
+                FilterType::Lanczos3,
+            );
+            let mut output = Vec::new();
+            let mut encoder = JpegEncoder::new_with_quality(&mut output, JPEG_QUALITY);
+            if image.color().has_color() {
+                encoder.encode_image(&image.to_rgb8())
+            } else {
+                encoder.encode_image(&image.to_luma8())
+            }
+            .map_err(to_error)?;
+            Ok(PreparedImage {
+                bytes: output,
+                extension: "jpg",
+                width: image.width(),
+                height: image.height(),
+            })
+        }
+        _ => Ok(PreparedImage {
+            bytes,
+            extension,
+            width,
+            height,
+        }),
+    }
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE: &str =
+    "html, body { margin: 0; padding: 0; }\nimg { display: block; width: 100%; height: 100%; }\n";
+
+/// Writes the page showing an image, at the size of the image.
+fn page_xhtml(id: &str, image_href: &str, width: u32, height: u32) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{id}</title>
+  <meta name="viewport" content="width={width}, height={height}"/>
+  <link rel="stylesheet" type="text/css" href="../style.css"/>
+</head>
+<body>
+  <img src="../{image_href}" alt="{id}" width="{width}" height="{height}"/>
+</body>
+</html>
+"#
+    )
+}
+
+/// Writes the EPUB3 table of contents, with an entry per chapter.
+fn nav_xhtml(title: &str, toc: &[(String, String)]) -> String {
+    let mut nav = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n");
+    nav.push_str("<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n");
+    nav.push_str(&format!(
+        "<head><title>{}</title></head>\n<body>\n  <nav epub:type=\"toc\" id=\"toc\">\n    <ol>\n",
+        escape_html(title)
+    ));
+    for (chapter, href) in toc {
+        nav.push_str(&format!(
+            "      <li><a href=\"{}\">{}</a></li>\n",
+            href,
+            escape_html(chapter)
+        ));
+    }
+    nav.push_str("    </ol>\n  </nav>\n</body>\n</html>\n");
+    nav
+}
+
+/// Writes the EPUB2 table of contents, still read by older readers.
+fn toc_ncx(metadata: &EpubMetadata, toc: &[(String, String)]) -> String {
+    let mut ncx = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    ncx.push_str("<ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n");
+    ncx.push_str(&format!(
+        "  <head><meta name=\"dtb:uid\" content=\"{}\"/></head>\n",
+        escape_html(metadata.identifier)
+    ));
+    ncx.push_str(&format!(
+        "  <docTitle><text>{}</text></docTitle>\n  <navMap>\n",
+        escape_html(metadata.title)
+    ));
+    for (index, (chapter, href)) in toc.iter().enumerate() {
+        ncx.push_str(&format!(
+            "    <navPoint id=\"chapter-{0}\" playOrder=\"{0}\"><navLabel><text>{1}</text></navLabel><content src=\"{2}\"/></navPoint>\n",
+            index + 1,
+            escape_html(chapter),
+            href
+        ));
+    }
+    ncx.push_str("  </navMap>\n</ncx>\n");
+    ncx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageOutputFormat, RgbImage};
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn chapters_are_packaged_with_a_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = std::env::temp_dir().join("manga_updater_epub_test");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("book.epub");
+        let mut epub = EpubWriter::create(&path, None)?;
+        assert_eq!(
+            epub.add_chapter("Chapter 1: Start", vec![png(40, 60), png(40, 60)])?,
+            2
+        );
+        assert_eq!(epub.add_chapter("Chapter 2 & more", vec![png(30, 50)])?, 1);
+        epub.finish(&EpubMetadata {
+            title: "Berserk - Chapters 1-2",
+            identifier: "http://localhost/manga#1-2",
+        })?;
+        assert!(!path.with_extension("epub.tmp").exists());
+
+        let mut archive = ZipArchive::new(File::open(&path)?)?;
+        {
+            let mimetype = archive.by_index(0)?;
+            assert_eq!(mimetype.name(), "mimetype");
+            assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        }
+        for name in [
+            "META-INF/container.xml",
+            "OEBPS/nav.xhtml",
+            "OEBPS/toc.ncx",
+            "OEBPS/images/c001-p002.png",
+            "OEBPS/pages/c002-p001.xhtml",
+        ] {
+            assert!(archive.by_name(name).is_ok(), "{} is missing", name);
+        }
+        let mut page = String::new();
+        archive
+            .by_name("OEBPS/pages/c002-p001.xhtml")?
+            .read_to_string(&mut page)?;
+        assert!(page.contains("content=\"width=30, height=50\""));
+
+        let mut opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")?
+            .read_to_string(&mut opf)?;
+        assert!(opf.contains("<dc:title>Berserk - Chapters 1-2</dc:title>"));
+        assert!(opf.contains("<meta property=\"rendition:layout\">pre-paginated</meta>"));
+        // Without a cover, the first page is the cover.
+        assert!(opf.contains("<item id=\"c001-p001-image\" href=\"images/c001-p001.png\" media-type=\"image/png\" properties=\"cover-image\"/>"));
+        assert!(opf.contains("<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>"));
+        assert!(opf.contains("<meta name=\"original-resolution\" content=\"40x60\"/>"));
+        assert_eq!(opf.matches("media-type=\"image/png\"").count(), 3);
+        assert_eq!(opf.matches("<itemref ").count(), 3);
+        assert!(
+            opf.find("idref=\"c001-p002\"").unwrap() < opf.find("idref=\"c002-p001\"").unwrap()
+        );
+
+        let mut nav = String::new();
+        archive
+            .by_name("OEBPS/nav.xhtml")?
+            .read_to_string(&mut nav)?;
+        assert!(nav.contains("<a href=\"pages/c001-p001.xhtml\">Chapter 1: Start</a>"));
+        assert!(nav.contains("<a href=\"pages/c002-p001.xhtml\">Chapter 2 &amp; more</a>"));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn pages_are_downscaled_to_the_screen() -> Result<(), io::Error> {
+        let small = png(600, 800);
+        let kept = prepare_image(small.clone(), Some(Screen::Kindle))?;
+        assert_eq!(kept.bytes, small);
+        assert_eq!((kept.width, kept.height), (600, 800));
+
+        let mut large = Vec::new();
+        GrayImage::new(2000, 3000)
+            .write_to(&mut Cursor::new(&mut large), ImageOutputFormat::Png)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let downscaled = prepare_image(large, Some(Screen::Kindle))?;
+        assert_eq!(downscaled.extension, "jpg");
+        assert_eq!((downscaled.width, downscaled.height), (1099, 1648));
+
+        assert_eq!("kobo".parse(), Ok(Screen::Kobo));
+        assert_eq!("1072x1448".parse(), Ok(Screen::Custom(1072, 1448)));
+        assert!("0x10".parse::<Screen>().is_err());
+        Ok(())
+    }
+}
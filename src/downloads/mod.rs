@@ -0,0 +1,381 @@
+pub mod epub;
+
+use crate::file_ops::extract_path_or_default;
+use crate::utils::escape_html;
+use serde::Deserialize;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// The metadata of a downloaded chapter, written to the `ComicInfo.xml` of its archive for the comic readers.
+#[derive(Debug)]
+pub struct ComicInfo<'a> {
+    /// The title of the manga.
+    pub series: &'a str,
+    pub number: f32,
+    /// The title of the chapter, e.g. `Chapter 74: Forest Rain`.
+    pub title: &'a str,
+    pub page_count: usize,
+    /// The page of the chapter.
+    pub web: &'a str,
+}
+
+impl ComicInfo<'_> {
+    /// Writes the metadata in the ComicRack format, read by most comic readers.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n");
+        xml.push_str(&format!("  <Title>{}</Title>\n", escape_html(self.title)));
+        xml.push_str(&format!(
+            "  <Series>{}</Series>\n",
+            escape_html(self.series)
+        ));
+        xml.push_str(&format!("  <Number>{}</Number>\n", self.number));
+        xml.push_str(&format!("  <PageCount>{}</PageCount>\n", self.page_count));
+        xml.push_str(&format!("  <Web>{}</Web>\n", escape_html(self.web)));
+        xml.push_str("  <Manga>YesAndRightToLeft</Manga>\n");
+        xml.push_str("</ComicInfo>\n");
+        xml
+    }
+}
+
+/// The fields of a `ComicInfo.xml` read back when a chapter is packaged.
+#[derive(Debug, Deserialize)]
+struct ComicInfoFields {
+    #[serde(rename = "Title")]
+    title: Option<String>,
+    #[serde(rename = "Number")]
+    number: Option<f32>,
+}
+
+/// A chapter read back from its archive.
+#[derive(Debug)]
+pub struct ArchivedChapter {
+    pub title: String,
+    /// The images of the pages, in the reading order.
+    pub pages: Vec<Vec<u8>>,
+}
+
+/// Finds the folder of the downloaded chapters.
+/// It is the folder given in the config, or `mangas.downloads` next to the CSV file, so that each library has its own.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `download_dir`: the folder given in the config, if any.
+/// # Returns:
+/// The folder of the downloads. Each manga has its own folder inside it.
+pub fn extract_downloads_path(
+    file_path: &Option<PathBuf>,
+    download_dir: &Option<PathBuf>,
+) -> PathBuf {
+    match download_dir {
+        Some(dir) => dir.clone(),
+        None => extract_path_or_default(file_path).with_extension("downloads"),
+    }
+}
+
+/// Replaces the characters that aren't allowed in file names on some systems.
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() {
+        "manga".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Finds the archive of a chapter: `<series>/<series> - Chapter <number>.cbz` in the download folder.
+/// The number is padded, so that the archives are sorted by chapter.
+/// # Arguments:
+/// * `dir`: the folder of the downloads.
+/// * `series`: the title of the manga.
+/// * `number`: the number of the chapter.
+pub fn chapter_path(dir: &Path, series: &str, number: f32) -> PathBuf {
+    let series = sanitize_file_name(series);
+    dir.join(&series).join(format!(
+        "{} - Chapter {}.cbz",
+        series,
+        padded_number(number)
+    ))
+}
+
+/// Pads the whole part of a chapter number to 3 digits, e.g. `007` or `074.5`.
+fn padded_number(number: f32) -> String {
+    let whole = number.trunc();
+    if number == whole {
+        format!("{:03}", whole)
+    } else {
+        format!(
+            "{:03}{}",
+            whole,
+            number.to_string().trim_start_matches(|c: char| c != '.')
+        )
+    }
+}
+
+/// Finds the EPUB of some chapters: `<series>/<series> - Chapters <first>-<last>.epub` in the download folder.
+/// # Arguments:
+/// * `dir`: the folder of the downloads.
+/// * `series`: the title of the manga.
+/// * `first`: the number of the first chapter.
+/// * `last`: the number of the last chapter.
+pub fn package_path(dir: &Path, series: &str, first: f32, last: f32) -> PathBuf {
+    let series = sanitize_file_name(series);
+    let name = if first == last {
+        format!("{} - Chapter {}.epub", series, padded_number(first))
+    } else {
+        format!(
+            "{} - Chapters {}-{}.epub",
+            series,
+            padded_number(first),
+            padded_number(last)
+        )
+    };
+    dir.join(&series).join(name)
+}
+
+/// Lists the chapters of a manga downloaded as archives.
+/// # Arguments:
+/// * `dir`: the folder of the downloads.
+/// * `series`: the title of the manga.
+/// # Returns:
+/// The number and the archive of each chapter, sorted by number. It is empty if nothing has been downloaded.
+pub fn downloaded_chapters(dir: &Path, series: &str) -> Result<Vec<(f32, PathBuf)>, io::Error> {
+    let series_dir = dir.join(sanitize_file_name(series));
+    if !series_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut chapters: Vec<(f32, PathBuf)> = fs::read_dir(series_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "cbz"))
+        .filter_map(|path| Some((archive_number(&path)?, path)))
+        .collect();
+    chapters.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    Ok(chapters)
+}
+
+/// Reads the number of a chapter from the name of its archive (See [`chapter_path`]).
+fn archive_number(path: &Path) -> Option<f32> {
+    path.file_stem()?
+        .to_str()?
+        .rsplit_once(" - Chapter ")?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Reads a chapter back from its archive, along with the title of its `ComicInfo.xml`.
+/// # Arguments:
+/// * `path`: the path to the archive.
+/// # Returns:
+/// The chapter, whose pages are sorted by name.
+pub fn read_cbz(path: &Path) -> Result<ArchivedChapter, io::Error> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut info = ComicInfoFields {
+        title: None,
+        number: None,
+    };
+    let mut pages: Vec<(String, Vec<u8>)> = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if file.name() == "ComicInfo.xml" {
+            info = quick_xml::de::from_str(&String::from_utf8_lossy(&bytes))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        } else {
+            pages.push((file.name().to_owned(), bytes));
+        }
+    }
+    pages.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let number = info.number.or(archive_number(path)).unwrap_or(0.0);
+    Ok(ArchivedChapter {
+        title: info.title.unwrap_or_else(|| format!("Chapter {}", number)),
+        pages: pages.into_iter().map(|(_, bytes)| bytes).collect(),
+    })
+}
+
+/// Finds the folder where the pages of a chapter are downloaded before being archived.
+/// It is kept if the download is interrupted, so that the next one resumes it.
+pub fn part_dir(archive: &Path) -> PathBuf {
+    archive.with_extension("part")
+}
+
+/// Finds a page already downloaded in the folder of the chapter, whatever its extension.
+/// # Arguments:
+/// * `part_dir`: the folder of the pages.
+/// * `index`: the index of the page, from 0.
+/// # Returns:
+/// The path to the page, None if it hasn't been downloaded yet.
+pub fn existing_page(part_dir: &Path, index: usize) -> Option<PathBuf> {
+    let stem = page_stem(index);
+    fs::read_dir(part_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| {
+            path.file_stem().is_some_and(|name| name == stem.as_str())
+                && path.extension().is_some_and(|extension| extension != "tmp")
+        })
+}
+
+/// Names a page after its index, from 001.
+fn page_stem(index: usize) -> String {
+    format!("{:03}", index + 1)
+}
+
+/// Gives the extension of an image from its MIME type.
+fn image_extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/avif" => "avif",
+        _ => "jpg",
+    }
+}
+
+/// Saves a downloaded page in the folder of the chapter.
+/// The page is written to a temporary file first, so that a page cut by an interruption is downloaded again.
+/// # Arguments:
+/// * `part_dir`: the folder of the pages.
+/// * `index`: the index of the page, from 0.
+/// * `bytes`: the image.
+/// * `content_type`: the MIME type of the image.
+/// # Returns:
+/// The path to the page.
+pub fn save_page(
+    part_dir: &Path,
+    index: usize,
+    bytes: &[u8],
+    content_type: &str,
+) -> Result<PathBuf, io::Error> {
+    let path = part_dir.join(format!(
+        "{}.{}",
+        page_stem(index),
+        image_extension(content_type)
+    ));
+    let temporary = path.with_extension(format!("{}.tmp", image_extension(content_type)));
+    fs::write(&temporary, bytes)?;
+    fs::rename(temporary, &path)?;
+    Ok(path)
+}
+
+/// Packs the pages of a chapter in a CBZ archive, along with its `ComicInfo.xml`.
+/// The images are stored as they are, as they are already compressed.
+/// The archive is written to a temporary file first, so that an archive cut by an interruption isn't mistaken for a downloaded chapter.
+/// # Arguments:
+/// * `path`: the path to the archive.
+/// * `pages`: the pages, in the reading order.
+/// * `info`: the metadata of the chapter.
+pub fn write_cbz(path: &Path, pages: &[PathBuf], info: &ComicInfo) -> Result<(), io::Error> {
+    let temporary = path.with_extension("cbz.tmp");
+    let mut archive = ZipWriter::new(File::create(&temporary)?);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    for page in pages {
+        let name = page
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        archive.start_file(name, stored)?;
+        archive.write_all(&fs::read(page)?)?;
+    }
+    archive.start_file(
+        "ComicInfo.xml",
+        FileOptions::default().compression_method(CompressionMethod::Deflated),
+    )?;
+    archive.write_all(info.to_xml().as_bytes())?;
+    archive.finish()?;
+    fs::rename(temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapters_are_named_and_sorted() {
+        let dir = PathBuf::from("downloads");
+        assert_eq!(
+            chapter_path(&dir, "Re:Zero / Part 2?", 7.0),
+            dir.join("Re_Zero _ Part 2_")
+                .join("Re_Zero _ Part 2_ - Chapter 007.cbz")
+        );
+        assert_eq!(
+            chapter_path(&dir, "Berserk", 74.5).file_name().unwrap(),
+            "Berserk - Chapter 074.5.cbz"
+        );
+        assert_eq!(
+            part_dir(&chapter_path(&dir, "Berserk", 1.0))
+                .file_name()
+                .unwrap(),
+            "Berserk - Chapter 001.part"
+        );
+        assert_eq!(
+            package_path(&dir, "Berserk", 1.0, 12.0)
+                .file_name()
+                .unwrap(),
+            "Berserk - Chapters 001-012.epub"
+        );
+        assert_eq!(
+            archive_number(&chapter_path(&dir, "Berserk", 74.5)),
+            Some(74.5)
+        );
+    }
+
+    #[test]
+    fn pages_are_packed_with_metadata() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_cbz_test");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let part = dir.join("chapter.part");
+        fs::create_dir_all(&part)?;
+        let first = save_page(&part, 0, b"first", "image/png")?;
+        // A page cut by an interruption is downloaded again.
+        fs::write(part.join("002.jpg.tmp"), b"cut")?;
+        assert_eq!(existing_page(&part, 0), Some(first.clone()));
+        assert_eq!(existing_page(&part, 1), None);
+        let second = save_page(&part, 1, b"second", "image/jpeg")?;
+
+        let path = dir.join("chapter.cbz");
+        let info = ComicInfo {
+            series: "Naruto & Co",
+            number: 12.0,
+            title: "Chapter 12",
+            page_count: 2,
+            web: "http://localhost/chapter-12",
+        };
+        write_cbz(&path, &[first, second], &info)?;
+        let mut archive = ZipArchive::new(File::open(&path)?)?;
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(archive.by_index(0)?.name(), "001.png");
+        assert_eq!(archive.by_index(1)?.name(), "002.jpg");
+        let mut xml = String::new();
+        archive.by_name("ComicInfo.xml")?.read_to_string(&mut xml)?;
+        assert!(xml.contains("<Series>Naruto &amp; Co</Series>"));
+        assert!(xml.contains("<Number>12</Number>"));
+        assert!(xml.contains("<PageCount>2</PageCount>"));
+        drop(archive);
+
+        let chapter = read_cbz(&path)?;
+        assert_eq!(chapter.title, "Chapter 12");
+        assert_eq!(chapter.pages, vec![b"first".to_vec(), b"second".to_vec()]);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+use crate::models::Release;
+use crate::utils::escape_html;
+use chrono::{DateTime, Duration, Utc};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// The period covered by a digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    /// The duration of the period.
+    pub fn duration(&self) -> Duration {
+        match self {
+            DigestPeriod::Daily => Duration::days(1),
+            DigestPeriod::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+impl FromStr for DigestPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "daily" => Ok(DigestPeriod::Daily),
+            "weekly" => Ok(DigestPeriod::Weekly),
+            other => Err(format!(
+                "Unknown period {}. Available periods are: daily, weekly.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DigestPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DigestPeriod::Daily => write!(f, "daily"),
+            DigestPeriod::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually on port 587.
+    #[default]
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// No encryption at all. Only meant for local servers.
+    None,
+}
+
+/// The SMTP settings, from the `[email]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// An email listing the new chapters of a period, in plain text and HTML.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    pub chapters: usize,
+}
+
+/// Builds the digest of the releases detected during the period.
+/// The chapters are grouped by manga, in the order the mangas got their first new chapter.
+/// # Arguments:
+/// * `releases`: the releases recorded for the library.
+/// * `period`: the period to cover, ending now.
+/// * `now`: the end of the period.
+/// # Returns:
+/// The digest, or None if no chapters have been released during the period.
+pub fn build_digest(
+    releases: &[Release],
+    period: DigestPeriod,
+    now: DateTime<Utc>,
+) -> Option<Digest> {
+    let since = now - period.duration();
+    let recent: Vec<&Release> = releases
+        .iter()
+        .filter(|release| release.detected_at > since && release.detected_at <= now)
+        .collect();
+    if recent.is_empty() {
+        return None;
+    }
+    let mut mangas: Vec<(&str, &str, Vec<&Release>)> = Vec::new();
+    for release in &recent {
+        match mangas
+            .iter_mut()
+            .find(|(url, _, _)| *url == release.manga_url)
+        {
+            Some((_, _, chapters)) => chapters.push(release),
+            None => mangas.push((&release.manga_url, &release.manga_title, vec![release])),
+        }
+    }
+
+    let subject = format!(
+        "Manga Updater: {} new chapter{} ({} digest)",
+        recent.len(),
+        if recent.len() > 1 { "s" } else { "" },
+        period
+    );
+    let mut text = format!(
+        "New chapters since {}:\n",
+        since.format("%Y-%m-%d %H:%M UTC")
+    );
+    let mut html = format!(
+        "<html><body><h1>{}</h1>",
+        escape_html(&format!(
+            "New chapters since {}",
+            since.format("%Y-%m-%d %H:%M UTC")
+        ))
+    );
+    for (url, title, chapters) in &mut mangas {
+        chapters.sort_by(|a, b| a.chapter_num.total_cmp(&b.chapter_num));
+        text.push_str(&format!("\n{} ({})\n", title, url));
+        html.push_str(&format!(
+            "<h2><a href=\"{}\">{}</a></h2><ul>",
+            escape_html(url),
+            escape_html(title)
+        ));
+        for chapter in chapters.iter() {
+            text.push_str(&format!(
+                "  - {}: {}\n",
+                chapter.chapter_title, chapter.chapter_url
+            ));
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                escape_html(&chapter.chapter_url),
+                escape_html(&chapter.chapter_title)
+            ));
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("</body></html>");
+    Some(Digest {
+        subject,
+        text,
+        html,
+        chapters: recent.len(),
+    })
+}
+
+/// Sends the digest to every recipient of the config.
+/// # Arguments:
+/// * `config`: the SMTP settings.
+/// * `digest`: the digest to send.
+/// # Returns:
+/// An error message if the email couldn't be built or sent.
+pub async fn send_digest(config: &EmailConfig, digest: &Digest) -> Result<(), String> {
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| format!("Invalid sender {}: {}", config.from, e))?;
+    let mut builder = Message::builder().from(from).subject(&digest.subject);
+    if config.to.is_empty() {
+        return Err("No recipients are configured.".to_owned());
+    }
+    for recipient in &config.to {
+        let to: Mailbox = recipient
+            .parse()
+            .map_err(|e| format!("Invalid recipient {}: {}", recipient, e))?;
+        builder = builder.to(to);
+    }
+    let email = builder
+        .multipart(MultiPart::alternative_plain_html(
+            digest.text.clone(),
+            digest.html.clone(),
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let transport = match config.security {
+        SmtpSecurity::Starttls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+        }
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            &config.host,
+        )),
+    }
+    .map_err(|e| e.to_string())?;
+    let mut transport = match config.port {
+        Some(port) => transport.port(port),
+        None => transport,
+    };
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport
+        .build()
+        .send(email)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("The email couldn't be sent: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    /// A release whose title must be escaped.
+    fn release(url: &str, chapter: f32, hours_ago: i64) -> Release {
+        Release {
+            manga_title: format!("Title <{}>", url),
+            ..test_utils::release(url, chapter, Utc::now() - Duration::hours(hours_ago))
+        }
+    }
+
+    /// Starts a local SMTP sink accepting a single email, without encryption nor authentication.
+    /// # Returns:
+    /// The port of the server, and a receiver for the DATA it got.
+    async fn smtp_sink() -> (u16, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = oneshot::channel();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write
+                .write_all(b"220 localhost ESMTP sink\r\n")
+                .await
+                .unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Ok(Some(line)) = lines.next_line().await {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        write.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let command = line.to_uppercase();
+                let answer: &[u8] = if command.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if command.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if command.starts_with("QUIT") {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                write.write_all(answer).await.unwrap();
+            }
+            let _ = sender.send(data);
+        });
+        (port, receiver)
+    }
+
+    #[test]
+    fn digest_groups_recent_chapters() {
+        let releases = vec![
+            release("url1", 2.0, 30),
+            release("url1", 4.0, 2),
+            release("url2", 7.0, 3),
+            release("url1", 3.0, 1),
+        ];
+        let digest = build_digest(&releases, DigestPeriod::Daily, Utc::now()).unwrap();
+        assert_eq!(digest.chapters, 3);
+        assert_eq!(
+            digest.subject,
+            "Manga Updater: 3 new chapters (daily digest)"
+        );
+        let url1 = digest.text.find("Title <url1>").unwrap();
+        let url2 = digest.text.find("Title <url2>").unwrap();
+        assert!(url1 < url2);
+        assert!(digest.text.find("Chapter 3").unwrap() < digest.text.find("Chapter 4").unwrap());
+        assert!(!digest.text.contains("Chapter 2"));
+        assert!(digest.html.contains("Title &lt;url1&gt;"));
+        assert!(digest
+            .html
+            .contains("<a href=\"url2/chapter-7\">Chapter 7</a>"));
+
+        let weekly = build_digest(&releases, DigestPeriod::Weekly, Utc::now()).unwrap();
+        assert_eq!(weekly.chapters, 4);
+    }
+
+    #[test]
+    fn no_digest_without_chapters() {
+        let releases = vec![release("url1", 2.0, 30)];
+        assert!(build_digest(&releases, DigestPeriod::Daily, Utc::now()).is_none());
+    }
+
+    #[tokio::test]
+    async fn digest_is_sent_to_the_sink() {
+        let (port, data) = smtp_sink().await;
+        let config = EmailConfig {
+            host: "127.0.0.1".to_owned(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "Manga Updater <bot@localhost>".to_owned(),
+            to: vec!["reader@localhost".to_owned()],
+        };
+        let digest =
+            build_digest(&[release("url1", 2.0, 1)], DigestPeriod::Daily, Utc::now()).unwrap();
+        send_digest(&config, &digest).await.unwrap();
+        let data = data.await.unwrap();
+        assert!(data.contains("Subject: Manga Updater: 1 new chapter (daily digest)"));
+        assert!(data.contains("To: reader@localhost"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("text/html"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::exporters::{group_by_status, status_title};
+use crate::output::EntryReport;
+use crate::scraper::{concurrency, download_image};
+use crate::utils::escape_html;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use reqwest::Client;
+
+/// The page, whose placeholders are replaced by the entries.
+const PAGE: &str = include_str!("page.html");
+
+/// Writes the entries as an HTML page, with a section per status.
+/// The page has no external stylesheet or script, so it can be opened or shared as a single file.
+/// Once [`embed_covers`] has been called, the covers are embedded too.
+/// # Argument:
+/// * `entries`: the entries to export, with their last chapter online if it has been fetched.
+/// # Returns:
+/// The HTML of the page.
+pub fn write_html(entries: &[EntryReport]) -> String {
+    let mut sections = String::new();
+    for (status, group) in group_by_status(entries) {
+        sections.push_str(&format!(
+            "<h2>{} ({})</h2>\n<ul class=\"mangas\">\n",
+            status_title(status),
+            group.len()
+        ));
+        for entry in group {
+            sections.push_str(&manga_card(entry));
+        }
+        sections.push_str("</ul>\n");
+    }
+    PAGE.replace("{{count}}", &entries.len().to_string())
+        .replace("{{date}}", &Utc::now().format("%Y-%m-%d").to_string())
+        .replace("{{sections}}", &sections)
+}
+
+/// Writes the card of a manga: its cover, its title linking to its page, the progress and the link to the next chapter.
+fn manga_card(entry: &EntryReport) -> String {
+    let mut card = format!(
+        "<li class=\"manga{}\">\n",
+        if entry.has_new { " new" } else { "" }
+    );
+    if let Some(cover) = &entry.cover {
+        card.push_str(&format!(
+            "    <img src=\"{}\" alt=\"\">\n",
+            escape_html(cover)
+        ));
+    }
+    card.push_str(&format!(
+        "    <div>\n        <h3><a href=\"{}\">{}</a></h3>\n",
+        escape_html(&entry.url),
+        escape_html(&entry.title)
+    ));
+    let mut progress = format!("Read: #{}", entry.last_chapter_num);
+    if let Some(latest) = entry.latest_chapter_num {
+        progress.push_str(&format!(" / Latest: #{}", latest));
+    }
+    if entry.has_new {
+        progress.push_str(" <span class=\"new-badge\">New!</span>");
+    }
+    card.push_str(&format!("        <p>{}</p>\n", progress));
+    if let Some(score) = entry.score {
+        card.push_str(&format!("        <p>Score: {}/10</p>\n", score));
+    }
+    if let Some(next) = &entry.next_chapter_url {
+        card.push_str(&format!(
+            "        <p><a href=\"{}\">Open next chapter</a></p>\n",
+            escape_html(next)
+        ));
+    }
+    card.push_str("    </div>\n</li>\n");
+    card
+}
+
+/// Downloads the covers of the entries, and replaces their URLs by `data:` URLs so that the page can be read offline.
+/// A cover that can't be downloaded keeps its URL.
+/// # Arguments:
+/// * `entries`: the entries whose covers to embed.
+/// * `client`: the client to use to make requests.
+/// * `verbose`: if true, the covers that couldn't be downloaded are printed.
+pub async fn embed_covers(entries: &mut [EntryReport], client: &Client, verbose: bool) {
+    let downloads: Vec<_> = stream::iter(entries.iter())
+        .map(|entry| async move {
+            match &entry.cover {
+                Some(cover) => Some(download_image(cover, &entry.url, client).await),
+                None => None,
+            }
+        })
+        .buffered(concurrency())
+        .collect()
+        .await;
+    for (entry, download) in entries.iter_mut().zip(downloads) {
+        match download {
+            Some(Ok((bytes, content_type))) => {
+                entry.cover = Some(format!(
+                    "data:{};base64,{}",
+                    content_type,
+                    STANDARD.encode(bytes)
+                ))
+            }
+            Some(Err(e)) if verbose => eprintln!("{}", e.reason),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CSVLine;
+    use crate::test_utils::line;
+
+    #[test]
+    fn page_has_a_card_per_manga() {
+        let mut entry = EntryReport::from_line(
+            1,
+            &CSVLine {
+                title: "<Title>".to_owned(),
+                score: Some(7.0),
+                ..line("http://localhost/manga", 3.0)
+            },
+        );
+        entry.cover = Some("data:image/png;base64,AAAA".to_owned());
+        entry.latest_chapter_num = Some(4.0);
+        entry.next_chapter_url = Some("http://localhost/manga/chapter-4".to_owned());
+        entry.has_new = true;
+        let page = write_html(&[entry]);
+        assert!(page.contains("<p>1 manga(s), exported on "));
+        assert!(page.contains("<h2>Reading (1)</h2>"));
+        assert!(page.contains("<li class=\"manga new\">"));
+        assert!(page.contains("<img src=\"data:image/png;base64,AAAA\" alt=\"\">"));
+        assert!(page.contains("<a href=\"http://localhost/manga\">&lt;Title&gt;</a>"));
+        assert!(page.contains("Read: #3 / Latest: #4 <span class=\"new-badge\">New!</span>"));
+        assert!(page.contains("<a href=\"http://localhost/manga/chapter-4\">Open next chapter</a>"));
+        assert!(!page.contains("{{"));
+    }
+}
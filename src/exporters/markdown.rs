@@ -0,0 +1,81 @@
+use crate::exporters::{group_by_status, status_title};
+use crate::output::EntryReport;
+
+/// Writes the entries as a Markdown reading list, with a section per status.
+/// Each manga is a link to its page, followed by the last chapter read and the score.
+/// # Argument:
+/// * `entries`: the entries to export.
+/// # Returns:
+/// The Markdown of the list.
+pub fn write_markdown(entries: &[EntryReport]) -> String {
+    let mut markdown = String::from("# Reading list\n");
+    for (status, group) in group_by_status(entries) {
+        markdown.push_str(&format!(
+            "\n## {} ({})\n\n",
+            status_title(status),
+            group.len()
+        ));
+        for entry in group {
+            markdown.push_str(&format!(
+                "- [{}](<{}>): chapter {}",
+                escape_markdown(&entry.title),
+                entry.url,
+                entry.last_chapter_num
+            ));
+            if let Some(score) = entry.score {
+                markdown.push_str(&format!(", {}/10", score));
+            }
+            markdown.push('\n');
+        }
+    }
+    markdown
+}
+
+/// Escapes the characters that would be read as Markdown in a link text.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CSVLine, ReadingStatus};
+    use crate::test_utils::line;
+
+    #[test]
+    fn list_is_grouped_by_status() {
+        let entries: Vec<EntryReport> = [
+            ("Berserk", ReadingStatus::Completed, Some(9.5)),
+            ("[Oshi no Ko]", ReadingStatus::Reading, None),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(index, (title, status, score))| {
+            EntryReport::from_line(
+                index + 1,
+                &CSVLine {
+                    title: title.to_string(),
+                    status: *status,
+                    score: *score,
+                    ..line(&format!("http://localhost/{}", index), 12.5)
+                },
+            )
+        })
+        .collect();
+        assert_eq!(
+            write_markdown(&entries),
+            "# Reading list\n\
+            \n## Reading (1)\n\n\
+            - [\\[Oshi no Ko\\]](<http://localhost/1>): chapter 12.5\n\
+            \n## Completed (1)\n\n\
+            - [Berserk](<http://localhost/0>): chapter 12.5, 9.5/10\n"
+        );
+    }
+}
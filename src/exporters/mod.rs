@@ -0,0 +1,173 @@
+/// Self-contained HTML pages
+pub mod html;
+/// Markdown reading lists
+pub mod markdown;
+/// OPML outlines
+pub mod opml;
+
+use crate::models::{CSVLine, ReadingStatus};
+use crate::output::EntryReport;
+use std::fmt;
+use std::str::FromStr;
+
+/// The formats the library can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// A copy of the CSV file.
+    #[default]
+    Csv,
+    /// The entries of the library, as a JSON array.
+    Json,
+    /// A reading list to share.
+    Markdown,
+    /// A page to open in a browser, with the covers.
+    Html,
+    /// An outline of the library, grouped by status.
+    Opml,
+    /// A MyAnimeList export.
+    Mal,
+    /// An AniList list.
+    Anilist,
+}
+
+impl ExportFormat {
+    /// The extension of the exported file, written alongside the CSV by default.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Opml => "opml",
+            ExportFormat::Mal => "mal.xml",
+            ExportFormat::Anilist => "anilist.json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            "opml" => Ok(ExportFormat::Opml),
+            "mal" | "myanimelist" => Ok(ExportFormat::Mal),
+            "anilist" => Ok(ExportFormat::Anilist),
+            other => Err(format!(
+                "Unknown export format {}. Available formats are: csv, json, markdown, html, opml, mal, anilist.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Html => "html",
+            ExportFormat::Opml => "opml",
+            ExportFormat::Mal => "mal",
+            ExportFormat::Anilist => "anilist",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Keeps the lines having one of the statuses, along with their positions in the library.
+/// # Arguments:
+/// * `lines`: the lines of the library.
+/// * `statuses`: the statuses to keep. If empty, every line is kept.
+/// # Returns:
+/// The position of each kept line, starting from 1, and the line.
+pub fn filter_lines(lines: Vec<CSVLine>, statuses: &[ReadingStatus]) -> Vec<(usize, CSVLine)> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| statuses.is_empty() || statuses.contains(&line.status))
+        .collect()
+}
+
+/// Writes the entries as a JSON array, in the shape of the `entries` of the JSON output.
+/// # Argument:
+/// * `entries`: the entries to export.
+/// # Returns:
+/// The JSON of the entries.
+pub fn write_json(entries: &[EntryReport]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(entries)
+}
+
+/// Groups the entries by status, in the order of [`ReadingStatus::ALL`]. The empty groups are left out.
+fn group_by_status(entries: &[EntryReport]) -> Vec<(ReadingStatus, Vec<&EntryReport>)> {
+    ReadingStatus::ALL
+        .iter()
+        .map(|status| {
+            (
+                *status,
+                entries
+                    .iter()
+                    .filter(|entry| entry.status == *status)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(_, group)| !group.is_empty())
+        .collect()
+}
+
+/// Names a status for a reader, e.g. `Plan to read`.
+fn status_title(status: ReadingStatus) -> &'static str {
+    match status {
+        ReadingStatus::Reading => "Reading",
+        ReadingStatus::PlanToRead => "Plan to read",
+        ReadingStatus::OnHold => "On hold",
+        ReadingStatus::Completed => "Completed",
+        ReadingStatus::Dropped => "Dropped",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    fn line(title: &str, status: ReadingStatus) -> CSVLine {
+        CSVLine {
+            title: title.to_owned(),
+            status,
+            ..test_utils::line(&format!("http://localhost/{}", title), 1.0)
+        }
+    }
+
+    #[test]
+    fn lines_are_filtered_by_status() {
+        let lines = vec![
+            line("a", ReadingStatus::Reading),
+            line("b", ReadingStatus::Dropped),
+            line("c", ReadingStatus::Completed),
+        ];
+        assert_eq!(filter_lines(lines.clone(), &[]).len(), 3);
+        let kept = filter_lines(lines, &[ReadingStatus::Completed, ReadingStatus::Reading]);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].0, 1);
+        assert_eq!(kept[1].0, 3);
+        assert_eq!(kept[1].1.title, "c");
+        for format in [
+            ExportFormat::Csv,
+            ExportFormat::Json,
+            ExportFormat::Markdown,
+            ExportFormat::Html,
+            ExportFormat::Opml,
+            ExportFormat::Mal,
+            ExportFormat::Anilist,
+        ] {
+            assert_eq!(format.to_string().parse::<ExportFormat>(), Ok(format));
+        }
+    }
+}
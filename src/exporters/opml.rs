@@ -0,0 +1,62 @@
+use crate::exporters::{group_by_status, status_title};
+use crate::output::EntryReport;
+use crate::utils::escape_html;
+use chrono::Utc;
+
+/// Writes the entries as an OPML 2.0 outline, with an outline per status holding a link per manga.
+/// # Argument:
+/// * `entries`: the entries to export.
+/// # Returns:
+/// The XML of the outline.
+pub fn write_opml(entries: &[EntryReport]) -> String {
+    let mut xml =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    xml.push_str("  <head>\n");
+    xml.push_str("    <title>Manga Updater</title>\n");
+    xml.push_str(&format!(
+        "    <dateCreated>{}</dateCreated>\n",
+        Utc::now().to_rfc2822()
+    ));
+    xml.push_str("  </head>\n  <body>\n");
+    for (status, group) in group_by_status(entries) {
+        xml.push_str(&format!(
+            "    <outline text=\"{}\">\n",
+            status_title(status)
+        ));
+        for entry in group {
+            xml.push_str(&format!(
+                "      <outline type=\"link\" text=\"{}\" url=\"{}\" lastChapter=\"{}\"/>\n",
+                escape_html(&entry.title),
+                escape_html(&entry.url),
+                entry.last_chapter_num
+            ));
+        }
+        xml.push_str("    </outline>\n");
+    }
+    xml.push_str("  </body>\n</opml>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CSVLine;
+    use crate::test_utils::line;
+
+    #[test]
+    fn outline_has_a_link_per_manga() {
+        let entry = EntryReport::from_line(
+            1,
+            &CSVLine {
+                title: "Tom & Jerry".to_owned(),
+                ..line("http://localhost/manga?a=1&b=2", 3.0)
+            },
+        );
+        let opml = write_opml(&[entry]);
+        assert!(opml.contains("<outline text=\"Reading\">"));
+        assert!(opml.contains(
+            "<outline type=\"link\" text=\"Tom &amp; Jerry\" url=\"http://localhost/manga?a=1&amp;b=2\" lastChapter=\"3\"/>"
+        ));
+        assert!(opml.ends_with("</opml>\n"));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::models::Release;
+use crate::utils::escape_html;
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// The maximum number of chapters in a feed. Only the latest releases are kept.
+pub const FEED_SIZE: usize = 100;
+
+/// The title of the feeds.
+const FEED_TITLE: &str = "Manga Updater: new chapters";
+
+/// The format of the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    /// The MIME type of the feed, used when it is served over HTTP.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+
+    /// The extension of the feed file, written alongside the CSV by default.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "atom.xml",
+            FeedFormat::Rss => "rss.xml",
+        }
+    }
+}
+
+impl FromStr for FeedFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "atom" => Ok(FeedFormat::Atom),
+            "rss" => Ok(FeedFormat::Rss),
+            other => Err(format!(
+                "Unknown feed format {}. Available formats are: atom, rss.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FeedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeedFormat::Atom => write!(f, "atom"),
+            FeedFormat::Rss => write!(f, "rss"),
+        }
+    }
+}
+
+/// Builds the feed of the latest releases, the most recent first.
+/// # Arguments:
+/// * `releases`: the releases recorded for the library, in the order they've been detected.
+/// * `format`: the format of the feed.
+/// * `link`: the address the feed is published at, if known. Atom feeds use it as their id.
+/// # Returns:
+/// The XML document of the feed.
+pub fn build_feed(releases: &[Release], format: FeedFormat, link: Option<&str>) -> String {
+    let latest: Vec<&Release> = releases.iter().rev().take(FEED_SIZE).collect();
+    // The feed is updated when its latest chapter is detected, so that it doesn't change for nothing.
+    let updated = latest
+        .first()
+        .map(|release| release.detected_at)
+        .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+    match format {
+        FeedFormat::Atom => build_atom(&latest, updated, link),
+        FeedFormat::Rss => build_rss(&latest, updated, link),
+    }
+}
+
+/// Builds an Atom 1.0 feed.
+fn build_atom(releases: &[&Release], updated: DateTime<Utc>, link: Option<&str>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", FEED_TITLE));
+    match link {
+        Some(link) => {
+            xml.push_str(&format!("  <id>{}</id>\n", escape_html(link)));
+            xml.push_str(&format!(
+                "  <link rel=\"self\" href=\"{}\"/>\n",
+                escape_html(link)
+            ));
+        }
+        None => xml.push_str("  <id>urn:manga-updater:releases</id>\n"),
+    }
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str("  <author><name>Manga Updater</name></author>\n");
+    for release in releases {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_html(&entry_title(release))
+        ));
+        xml.push_str(&format!(
+            "    <id>{}</id>\n",
+            escape_html(&release.chapter_url)
+        ));
+        xml.push_str(&format!(
+            "    <link href=\"{}\"/>\n",
+            escape_html(&release.chapter_url)
+        ));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            release.detected_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape_html(&entry_summary(release))
+        ));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Builds an RSS 2.0 feed.
+fn build_rss(releases: &[&Release], updated: DateTime<Utc>, link: Option<&str>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&format!("  <title>{}</title>\n", FEED_TITLE));
+    xml.push_str(&format!(
+        "  <link>{}</link>\n",
+        escape_html(link.unwrap_or("https://manganelo.com"))
+    ));
+    xml.push_str("  <description>The new chapters of the mangas followed with Manga Updater.</description>\n");
+    xml.push_str(&format!(
+        "  <lastBuildDate>{}</lastBuildDate>\n",
+        updated.to_rfc2822()
+    ));
+    for release in releases {
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_html(&entry_title(release))
+        ));
+        xml.push_str(&format!(
+            "    <link>{}</link>\n",
+            escape_html(&release.chapter_url)
+        ));
+        xml.push_str(&format!(
+            "    <guid isPermaLink=\"true\">{}</guid>\n",
+            escape_html(&release.chapter_url)
+        ));
+        xml.push_str(&format!(
+            "    <pubDate>{}</pubDate>\n",
+            release.detected_at.to_rfc2822()
+        ));
+        xml.push_str(&format!(
+            "    <description>{}</description>\n",
+            escape_html(&entry_summary(release))
+        ));
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// The title of a feed entry: the manga, then the chapter.
+fn entry_title(release: &Release) -> String {
+    format!("{}: {}", release.manga_title, release.chapter_title)
+}
+
+/// The summary of a feed entry.
+fn entry_summary(release: &Release) -> String {
+    format!(
+        "Chapter {} of {} ({}).",
+        release.chapter_num, release.manga_title, release.manga_url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::release;
+    use chrono::Duration;
+
+    fn releases() -> Vec<Release> {
+        (1..=3)
+            .map(|i| Release {
+                manga_title: "Tom & Jerry".to_owned(),
+                ..release(
+                    "http://localhost/manga",
+                    i as f32,
+                    Utc::now() - Duration::hours(10 - i),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn atom_feed_lists_latest_first() {
+        let releases = releases();
+        let feed = build_feed(&releases, FeedFormat::Atom, None);
+        assert!(feed.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(feed.contains("<title>Tom &amp; Jerry: Chapter 3</title>"));
+        assert!(feed.contains(&format!(
+            "<updated>{}</updated>",
+            releases[2].detected_at.to_rfc3339()
+        )));
+        assert!(feed.find("chapter-3").unwrap() < feed.find("chapter-1").unwrap());
+        assert_eq!(feed.matches("<entry>").count(), 3);
+    }
+
+    #[test]
+    fn rss_feed_has_items() {
+        let feed = build_feed(
+            &releases(),
+            FeedFormat::Rss,
+            Some("http://localhost:8080/feed"),
+        );
+        assert!(feed.contains("<rss version=\"2.0\">"));
+        assert!(feed.contains("<link>http://localhost:8080/feed</link>"));
+        assert!(feed.contains("<guid isPermaLink=\"true\">http://localhost/manga/chapter-2</guid>"));
+        assert_eq!(feed.matches("<item>").count(), 3);
+    }
+
+    #[test]
+    fn empty_feed_is_valid() {
+        let feed = build_feed(&[], FeedFormat::Atom, None);
+        assert!(feed.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+        assert!(feed.ends_with("</feed>\n"));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::file_ops::extract_path_or_default;
+use crate::models::ReadingEvent;
+use crate::output::{ChangeAction, LibraryChange};
+use chrono::{DateTime, Utc};
+use csv::Writer;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// Finds the reading history associated to the CSV file.
+/// It is stored alongside the CSV, with the `.csv` extension replaced by `.history.csv`.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the history file.
+pub fn extract_history_path(file_path: &Option<PathBuf>) -> PathBuf {
+    extract_path_or_default(file_path).with_extension("history.csv")
+}
+
+/// Reads the reading history of the CSV file.
+/// If the history file doesn't exist yet, nothing has been read.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The progress made, in the order it has been recorded.
+pub fn read_history(file_path: &Option<PathBuf>) -> Result<Vec<ReadingEvent>, io::Error> {
+    let path = extract_history_path(file_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut events: Vec<ReadingEvent> = Vec::new();
+    let parse_num = |value: Option<&str>| {
+        value
+            .unwrap_or("")
+            .parse::<f32>()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    };
+    for record in reader.records() {
+        let rec = record?;
+        events.push(ReadingEvent {
+            read_at: DateTime::parse_from_rfc3339(rec.get(0).unwrap_or(""))
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+                .with_timezone(&Utc),
+            url: rec.get(1).unwrap_or("").to_owned(),
+            title: rec.get(2).unwrap_or("").to_owned(),
+            previous_chapter_num: parse_num(rec.get(3))?,
+            chapter_num: parse_num(rec.get(4))?,
+        });
+    }
+    Ok(events)
+}
+
+/// Records the progress made by changes to the library in the history file.
+/// Only the chapters going forward are recorded: the additions, removals and chapters set back aren't reading.
+/// The file is created along with its headers if needed.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `changes`: the changes made to the library.
+/// # Returns:
+/// The progress recorded.
+pub fn record_reading(
+    file_path: &Option<PathBuf>,
+    changes: &[LibraryChange],
+) -> Result<Vec<ReadingEvent>, io::Error> {
+    let now = Utc::now();
+    let events: Vec<ReadingEvent> = changes
+        .iter()
+        .filter(|change| change.action == ChangeAction::Updated)
+        .filter_map(
+            |change| match (change.previous_chapter_num, change.chapter_num) {
+                (Some(previous), Some(chapter)) if chapter > previous => Some(ReadingEvent {
+                    read_at: now,
+                    url: change.url.clone(),
+                    title: change.title.clone(),
+                    previous_chapter_num: previous,
+                    chapter_num: chapter,
+                }),
+                _ => None,
+            },
+        )
+        .collect();
+    if events.is_empty() {
+        return Ok(events);
+    }
+    let path = extract_history_path(file_path);
+    let exists = path.exists();
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = Writer::from_writer(file);
+    if !exists {
+        writer.write_record(["Read at", "URL", "Title", "Previous chapter", "Chapter"])?;
+    }
+    for event in &events {
+        writer.write_record([
+            event.read_at.to_rfc3339().as_str(),
+            &event.url,
+            &event.title,
+            &event.previous_chapter_num.to_string(),
+            &event.chapter_num.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(events)
+}
+
+/// Warns that the history couldn't be written, as the library itself has been.
+pub fn warn_history(result: Result<Vec<ReadingEvent>, io::Error>) {
+    if let Err(e) = result {
+        eprintln!(
+            "The chapters read couldn't be recorded in the history: {}",
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use std::fs;
+
+    fn change(action: ChangeAction, previous: Option<f32>, chapter: Option<f32>) -> LibraryChange {
+        LibraryChange {
+            action,
+            url: "url1".to_owned(),
+            title: "title1".to_owned(),
+            previous_chapter_num: previous,
+            chapter_num: chapter,
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn only_the_progress_is_recorded() -> Result<(), io::Error> {
+        let path = Some(PathBuf::from("history_test.csv"));
+        let changes = vec![
+            change(ChangeAction::Updated, Some(3.0), Some(7.0)),
+            change(ChangeAction::Updated, Some(7.0), Some(6.0)),
+            change(ChangeAction::Added, None, Some(10.0)),
+            change(ChangeAction::Removed, Some(2.0), None),
+        ];
+        assert_eq!(record_reading(&path, &changes)?.len(), 1);
+        record_reading(
+            &path,
+            &[change(ChangeAction::Updated, Some(7.0), Some(7.5))],
+        )?;
+        let history = read_history(&path)?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].chapters_read(), 4);
+        assert_eq!(history[1].chapters_read(), 1);
+        assert_eq!(history[1].previous_chapter_num, 7.0);
+        fs::remove_file(extract_history_path(&path))?;
+        Ok(())
+    }
+}
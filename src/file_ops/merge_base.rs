@@ -0,0 +1,39 @@
+use crate::file_ops::extract_path_or_default;
+use crate::file_ops::read_csv;
+use crate::file_ops::write_file::write_lines;
+use crate::models::CSVLine;
+use std::io;
+use std::path::PathBuf;
+
+/// Finds the base of the merges associated to the CSV file, i.e. the imported file as it was at the last merge.
+/// It is stored alongside the CSV, with the `.csv` extension replaced by `.merge-base.csv`.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the base.
+pub fn extract_merge_base_path(file_path: &Option<PathBuf>) -> PathBuf {
+    extract_path_or_default(file_path).with_extension("merge-base.csv")
+}
+
+/// Reads the base of the merges.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The lines of the imported file at the last merge, or None if nothing has been merged yet.
+pub fn read_merge_base(file_path: &Option<PathBuf>) -> Result<Option<Vec<CSVLine>>, io::Error> {
+    let path = extract_merge_base_path(file_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_csv(&Some(path), &false).map(Some)
+}
+
+/// Replaces the base of the merges by the lines that have just been merged.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `lines`: the lines of the imported file.
+/// # Returns:
+/// Ok if everything went well.
+pub fn write_merge_base(file_path: &Option<PathBuf>, lines: Vec<CSVLine>) -> Result<(), io::Error> {
+    write_lines(&Some(extract_merge_base_path(file_path)), lines)
+}
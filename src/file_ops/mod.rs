@@ -1,44 +1,35 @@
+pub mod history;
+pub mod merge_base;
+pub mod queue;
+pub mod releases;
+pub mod review;
 pub mod save;
 pub mod write_file;
 
 use crate::models::CSVLine;
-use std::env::current_exe;
+use crate::paths::default_library_path;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 
 /// Checks if the optional path is defined, and if so, returns it.
 /// If None, the default path will be returned instead.
-/// The default path is the executable's folder, with the name `manga.csv`.
-/// This means that by default, the CSV file containing the mangas will be stored alongside the executable.
+/// The default path is `mangas.csv` in the data folder (See [`crate::paths::default_library_path`]).
 /// # Argument:
 /// * `file_path`: the optional file path, if a custom CSV location is used.
 /// # Returns:
 /// The path to the CSV file, be it custom or default.
-fn extract_path_or_default(file_path: &Option<PathBuf>) -> PathBuf {
+pub fn extract_path_or_default(file_path: &Option<PathBuf>) -> PathBuf {
     if file_path.is_some() {
         file_path.clone().unwrap()
     } else {
-        let mut exe = current_exe().unwrap();
-        exe.pop();
-        exe.push("mangas.csv");
-        exe
-    }
-}
-
-fn extract_restore_path_or_default(file_path: &Option<PathBuf>) -> PathBuf {
-    if file_path.is_some() {
-        file_path.clone().unwrap()
-    } else {
-        let mut exe = current_exe().unwrap();
-        exe.pop();
-        exe.push("mangas.csv.bak");
-        exe
+        default_library_path()
     }
 }
 
 /// Reads the CSV file and returns the lines stored inside.
-/// If the headers don't correspond to the normal ones, a panic is raised.
+/// If the headers don't correspond to the normal ones, or if a line can't be read, an error is returned (See [`parse_csv`]).
+/// The status and score columns are optional, so that the files written by the previous versions can still be read.
 /// This is meant as a protection against strange CSV files.
 /// # Arguments:
 /// * `file_path`: the optional file path, if a custom CSV location is used.
@@ -50,49 +41,70 @@ pub fn read_csv(file_path: &Option<PathBuf>, verbose: &bool) -> Result<Vec<CSVLi
         println!("Beginning processing the CSV at {:?}", file_path);
     }
     let path = extract_path_or_default(file_path);
-    let mut reader = csv::Reader::from_path(path)?;
+    let lines = parse_csv(fs::File::open(path)?)?;
+    if *verbose {
+        println!("Found {} lines in the CSV.", lines.len());
+    }
+    Ok(lines)
+}
+
+/// Reads the lines of a CSV, e.g. a version of the library stored by git.
+/// The unknown statuses and scores are left to their defaults, but the other malformed values are errors: `doctor` tells how to fix them (See [`crate::doctor::read_rows`]).
+/// # Argument:
+/// * `contents`: the reader of the CSV.
+/// # Returns:
+/// A Vec containing the lines stored in the CSV.
+/// # Errors:
+/// * `io::InvalidData` if the headers aren't the expected ones, if a line lacks the URL, the last chapter or the title, or if the last chapter isn't a number.
+pub fn parse_csv<R: io::Read>(contents: R) -> Result<Vec<CSVLine>, io::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents);
     let mut lines: Vec<CSVLine> = Vec::new();
     {
         let headers = reader.headers()?;
-        assert!(headers.get(0).unwrap_or("").eq("URL"));
-        assert!(headers.get(1).unwrap_or("").eq("Last chapter"));
-        assert!(headers.get(2).unwrap_or("").eq("Title"));
+        if headers.get(0) != Some("URL") || headers.get(1) != Some("Last chapter") || headers.get(2) != Some("Title") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The headers of the CSV should be URL,Last chapter,Title,Status,Score. Run the doctor command for more details.",
+            ));
+        }
     }
 
-    for record in reader.records() {
+    for (index, record) in reader.records().enumerate() {
         let rec = record?;
+        let invalid = |problem: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Line {} of the CSV {}. Run the doctor command for more details.", index + 1, problem),
+            )
+        };
+        let (Some(url), Some(chapter), Some(title)) = (rec.get(0), rec.get(1), rec.get(2)) else {
+            return Err(invalid("lacks the URL, the last chapter or the title"));
+        };
         lines.push(CSVLine {
-            url: rec.get(0).unwrap().to_owned(),
-            last_chapter_num: rec.get(1).unwrap().parse().unwrap(),
-            title: rec.get(2).unwrap().to_owned(),
+            url: url.to_owned(),
+            last_chapter_num: chapter.parse().map_err(|_| invalid("has a last chapter that isn't a number"))?,
+            title: title.to_owned(),
+            status: rec
+                .get(3)
+                .and_then(|status| status.parse().ok())
+                .unwrap_or_default(),
+            score: rec.get(4).and_then(|score| score.parse().ok()),
         });
     }
-    if *verbose {
-        println!("Found {} lines in the CSV.", lines.len());
-    }
     Ok(lines)
 }
 
-/// Checks if the URL of a manga is already stored into the CSV.
-/// It doesn't need to check line by line: instead, it opens the file as a string and checks if any part of it matches.
-/// # Arguments:
-/// * `file_path`: the optional file path, if a custom CSV location is used.
-/// * `url`: The URl to check and match.
-/// # Returns:
-/// True if the URl has been found in the file's content, false otherwise.
-pub fn is_url_present(file_path: Option<PathBuf>, url: &str) -> Result<bool, io::Error> {
-    let path = extract_path_or_default(&file_path);
-    let contents = fs::read_to_string(path)?;
-    Ok(contents.contains(url))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::ReadingStatus;
     use serial_test::serial;
 
     #[test]
     #[serial]
+    #[allow(clippy::vec_init_then_push, clippy::get_first)]
     fn test_read_csv() -> Result<(), io::Error> {
         let path = PathBuf::from("mangas.csv");
         write_file::create_file(&Some(path.clone()))?;
@@ -101,6 +113,8 @@ mod tests {
             url: "url1".to_owned(),
             last_chapter_num: 0.0,
             title: "title".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         write_file::update_csv(&Some(path.clone()), to_insert)?;
         let inserted = read_csv(&Some(path), &true)?;
@@ -111,23 +125,4 @@ mod tests {
         fs::remove_file("mangas.csv")?;
         Ok(())
     }
-
-    #[test]
-    #[serial]
-    fn test_is_url_present() -> Result<(), io::Error> {
-        let path = PathBuf::from("mangas.csv");
-        write_file::create_file(&Some(path.clone()))?;
-        let mut new_lines: Vec<CSVLine> = Vec::new();
-        new_lines.push(CSVLine {
-            url: "url1".to_owned(),
-            last_chapter_num: 0.0,
-            title: "title".to_owned(),
-        });
-        write_file::update_csv(&Some(path.clone()), new_lines)?;
-        assert!(path.exists());
-        let is_url_present = is_url_present(Some(path), "url1")?;
-        assert!(is_url_present);
-        fs::remove_file("mangas.csv")?;
-        Ok(())
-    }
 }
@@ -0,0 +1,91 @@
+use crate::file_ops::extract_path_or_default;
+use crate::queue::Skip;
+use csv::Writer;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// Finds the file of the chapters skipped in the reading queue, associated to the CSV file.
+/// It is stored alongside the CSV, with the `.csv` extension replaced by `.queue.csv`.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the skipped chapters.
+pub fn extract_skips_path(file_path: &Option<PathBuf>) -> PathBuf {
+    extract_path_or_default(file_path).with_extension("queue.csv")
+}
+
+/// Reads the chapters skipped in the reading queue.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The skipped chapters, from the first one skipped to the last one. It is empty if nothing has been skipped yet.
+pub fn read_skips(file_path: &Option<PathBuf>) -> Result<Vec<Skip>, io::Error> {
+    let path = extract_skips_path(file_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut skips: Vec<Skip> = Vec::new();
+    for record in reader.records() {
+        let rec = record?;
+        skips.push(Skip {
+            url: rec.get(0).unwrap_or("").to_owned(),
+            chapter_num: rec
+                .get(1)
+                .unwrap_or("")
+                .parse()
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+        });
+    }
+    Ok(skips)
+}
+
+/// Replaces the chapters skipped in the reading queue. The file is removed if none are left.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `skips`: the skipped chapters, from the first one skipped to the last one.
+pub fn write_skips(file_path: &Option<PathBuf>, skips: &[Skip]) -> Result<(), io::Error> {
+    let path = extract_skips_path(file_path);
+    if skips.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let mut writer = Writer::from_path(path)?;
+    writer.write_record(["URL", "Chapter"])?;
+    for skip in skips {
+        writer.write_record([skip.url.as_str(), &skip.chapter_num.to_string()])?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn skips_are_written_and_read() -> Result<(), io::Error> {
+        let path = Some(PathBuf::from("queue_test.csv"));
+        assert_eq!(read_skips(&path)?, Vec::new());
+        let skips = vec![
+            Skip {
+                url: "url1".to_owned(),
+                chapter_num: 4.5,
+            },
+            Skip {
+                url: "url2".to_owned(),
+                chapter_num: 1.0,
+            },
+        ];
+        write_skips(&path, &skips)?;
+        assert_eq!(read_skips(&path)?, skips);
+        write_skips(&path, &[])?;
+        assert!(!extract_skips_path(&path).exists());
+        Ok(())
+    }
+}
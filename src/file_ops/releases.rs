@@ -0,0 +1,135 @@
+use crate::file_ops::extract_path_or_default;
+use crate::models::Release;
+use chrono::{DateTime, Utc};
+use csv::Writer;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// Finds the releases file associated to the CSV file.
+/// It is stored alongside the CSV, with the `.csv` extension replaced by `.releases.csv`.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the releases file.
+pub fn extract_releases_path(file_path: &Option<PathBuf>) -> PathBuf {
+    extract_path_or_default(file_path).with_extension("releases.csv")
+}
+
+/// Reads the releases recorded for the CSV file.
+/// If the releases file doesn't exist yet, no releases are returned.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The releases, in the order they've been detected.
+pub fn read_releases(file_path: &Option<PathBuf>) -> Result<Vec<Release>, io::Error> {
+    let path = extract_releases_path(file_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut releases: Vec<Release> = Vec::new();
+    for record in reader.records() {
+        let rec = record?;
+        let detected_at = DateTime::parse_from_rfc3339(rec.get(0).unwrap_or(""))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+            .with_timezone(&Utc);
+        releases.push(Release {
+            detected_at,
+            manga_url: rec.get(1).unwrap_or("").to_owned(),
+            manga_title: rec.get(2).unwrap_or("").to_owned(),
+            chapter_num: rec
+                .get(3)
+                .unwrap_or("")
+                .parse()
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            chapter_title: rec.get(4).unwrap_or("").to_owned(),
+            chapter_url: rec.get(5).unwrap_or("").to_owned(),
+        });
+    }
+    Ok(releases)
+}
+
+/// Appends the releases that haven't been recorded yet to the releases file.
+/// A release is identified by its manga URL and its chapter number, so that a chapter is recorded only once.
+/// The file is created along with its headers if needed.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `releases`: the releases to record.
+/// # Returns:
+/// The releases that were actually new, and have been written.
+pub fn record_releases(
+    file_path: &Option<PathBuf>,
+    releases: Vec<Release>,
+) -> Result<Vec<Release>, io::Error> {
+    let known = read_releases(file_path)?;
+    let new_releases: Vec<Release> = releases
+        .into_iter()
+        .filter(|release| !is_recorded(&known, release))
+        .collect();
+    if new_releases.is_empty() {
+        return Ok(new_releases);
+    }
+    let path = extract_releases_path(file_path);
+    let exists = path.exists();
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = Writer::from_writer(file);
+    if !exists {
+        writer.write_record([
+            "Detected at",
+            "URL",
+            "Title",
+            "Chapter",
+            "Chapter title",
+            "Chapter URL",
+        ])?;
+    }
+    for release in &new_releases {
+        writer.write_record([
+            release.detected_at.to_rfc3339().as_str(),
+            &release.manga_url,
+            &release.manga_title,
+            &release.chapter_num.to_string(),
+            &release.chapter_title,
+            &release.chapter_url,
+        ])?;
+    }
+    writer.flush()?;
+    Ok(new_releases)
+}
+
+/// Tells if a release is already present in the known ones.
+fn is_recorded(known: &[Release], release: &Release) -> bool {
+    known
+        .iter()
+        .any(|k| k.manga_url == release.manga_url && k.chapter_num == release.chapter_num)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use serial_test::serial;
+    use std::fs;
+
+    fn release(url: &str, chapter: f32) -> Release {
+        test_utils::release(url, chapter, Utc::now())
+    }
+
+    #[test]
+    #[serial]
+    fn test_record_releases_once() -> Result<(), io::Error> {
+        let path = Some(PathBuf::from("mangas.csv"));
+        let recorded = record_releases(&path, vec![release("url1", 1.0), release("url2", 3.0)])?;
+        assert_eq!(recorded.len(), 2);
+        let recorded = record_releases(&path, vec![release("url1", 1.0), release("url1", 2.0)])?;
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].chapter_num, 2.0);
+        let releases = read_releases(&path)?;
+        assert_eq!(releases.len(), 3);
+        assert_eq!(releases[2].chapter_url, "url1/chapter-2");
+        fs::remove_file("mangas.releases.csv")?;
+        Ok(())
+    }
+}
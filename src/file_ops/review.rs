@@ -0,0 +1,110 @@
+use crate::file_ops::extract_path_or_default;
+use crate::models::ReviewItem;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// Finds the review queue associated to the CSV file.
+/// It is stored alongside the CSV, with the `.csv` extension replaced by `.review.json`.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the review queue.
+pub fn extract_review_path(file_path: &Option<PathBuf>) -> PathBuf {
+    extract_path_or_default(file_path).with_extension("review.json")
+}
+
+/// Reads the imported mangas waiting to be reviewed.
+/// If the queue doesn't exist, there is nothing to review.
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The mangas to review, in the order they've been imported.
+pub fn read_review_queue(file_path: &Option<PathBuf>) -> Result<Vec<ReviewItem>, io::Error> {
+    let path = extract_review_path(file_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Replaces the review queue. The file is removed once the queue is empty.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `items`: the mangas left to review.
+/// # Returns:
+/// Ok if everything went well.
+pub fn write_review_queue(
+    file_path: &Option<PathBuf>,
+    items: &[ReviewItem],
+) -> Result<(), io::Error> {
+    let path = extract_review_path(file_path);
+    if items.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let json = serde_json::to_string_pretty(items)?;
+    fs::write(path, json)
+}
+
+/// Adds mangas to the review queue. A manga already waiting, with the same title, is replaced by the new one.
+/// # Arguments:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// * `items`: the mangas to add.
+/// # Returns:
+/// The number of mangas waiting in the queue.
+pub fn queue_for_review(
+    file_path: &Option<PathBuf>,
+    items: Vec<ReviewItem>,
+) -> Result<usize, io::Error> {
+    let mut queue = read_review_queue(file_path)?;
+    queue.retain(|queued| {
+        !items
+            .iter()
+            .any(|item| item.entry.title == queued.entry.title)
+    });
+    queue.extend(items);
+    write_review_queue(file_path, &queue)?;
+    Ok(queue.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TrackerEntry;
+
+    fn item(title: &str, read_chapters: f32) -> ReviewItem {
+        ReviewItem {
+            entry: TrackerEntry {
+                title: title.to_owned(),
+                read_chapters,
+                status: Default::default(),
+                score: None,
+            },
+            candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn queue_is_kept_until_empty() -> Result<(), io::Error> {
+        let path = Some(std::env::temp_dir().join("manga_updater_review_test.csv"));
+        write_review_queue(&path, &[])?;
+        assert_eq!(
+            queue_for_review(&path, vec![item("A", 1.0), item("B", 2.0)])?,
+            2
+        );
+        assert_eq!(queue_for_review(&path, vec![item("A", 3.0)])?, 2);
+        let queue = read_review_queue(&path)?;
+        assert_eq!(queue[0], item("B", 2.0));
+        assert_eq!(queue[1], item("A", 3.0));
+
+        write_review_queue(&path, &[])?;
+        assert!(!extract_review_path(&path).exists());
+        assert!(read_review_queue(&path)?.is_empty());
+        Ok(())
+    }
+}
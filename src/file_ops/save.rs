@@ -1,8 +1,9 @@
-use crate::file_ops::{extract_path_or_default, extract_restore_path_or_default};
+use crate::file_ops::extract_path_or_default;
+use crate::sync::commit_change;
 use std::fs;
 use std::io;
 use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Backups the file in the same location with a .bak extension added to it.
 /// The origin path must be the csv file.
@@ -12,38 +13,48 @@ use std::path::PathBuf;
 /// An empty success if the backup was successful, an error from IO::errror otherwise.
 pub fn backup_file(origin_path: Option<PathBuf>) -> Result<(), io::Error> {
     let path = extract_path_or_default(&origin_path);
-    match path.to_str() {
-        Some(p) => {
-            let copy_path = p.to_owned() + ".bak";
-            fs::copy(path, copy_path)?;
-            Ok(())
-        }
-        None => Err(Error::new(
-            ErrorKind::NotFound,
-            "The input file could not be found in this scope.",
-        )),
-    }
+    fs::copy(&path, backup_path(&path))?;
+    Ok(())
+}
+
+/// Gives the path of the backup of a library: the library with a .bak extension added, e.g. `mangas.csv.bak`.
+/// The backup is only located here, so that writing and restoring it can't disagree on where it is.
+pub fn backup_path(library: &Path) -> PathBuf {
+    let mut backup = library.to_path_buf().into_os_string();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Finds the backup of the CSV file, to give to [`restore_file`].
+/// # Argument:
+/// * `file_path`: the optional file path, if a custom CSV location is used.
+/// # Returns:
+/// The path to the backup, alongside the custom CSV with a .bak extension added. None if the default CSV is used, as its backup is the default one.
+pub fn extract_backup_path(file_path: &Option<PathBuf>) -> Option<PathBuf> {
+    file_path.as_deref().map(backup_path)
 }
 
 /// Restores the file from the backup.
-/// The origin path must point to the save file.
+/// The origin path must point to the save file, as given by [`extract_backup_path`]. If the library is synced, the restored version is committed.
 /// # Prerequisites:
-/// The path given in argument must end with `.csv.bak`
+/// The path given in argument must end with `.bak`
 /// # Arguments:
-/// * `restore_path`: The path to restore from.
+/// * `restore_path`: The path to restore from. If None, the backup of the default library is used.
 /// # Returns:
 /// An empty success if the restore operation was successful, io::Error otherwise.
 /// # Errors:
 /// * `io::Unsupported` if the path isn't a file. Will be changed to ErrorKind::IsADirectory in the future.
-/// * `io::InvalidInput` if the path doesn't end with .csv.bak or if an error happened while converting the path to str.
+/// * `io::InvalidInput` if the path doesn't end with .bak or if an error happened while converting the path to str.
 pub fn restore_file(restore_path: &Option<PathBuf>, verbose: &bool) -> Result<(), io::Error> {
-    let path = extract_restore_path_or_default(restore_path);
+    let path = restore_path
+        .clone()
+        .unwrap_or_else(|| backup_path(&extract_path_or_default(&None)));
     match path.clone().to_str() {
         Some(p) => {
-            if !p.ends_with(".csv.bak") {
+            if !p.ends_with(".bak") {
                 return Err(Error::new(
                     ErrorKind::InvalidInput,
-                    "The supplied path is incorrect. The extensions should be .csv.bak.",
+                    "The supplied path is incorrect. The extension should be .bak.",
                 ));
             }
             if !path.is_file() {
@@ -54,6 +65,7 @@ pub fn restore_file(restore_path: &Option<PathBuf>, verbose: &bool) -> Result<()
             }
             let copy_path = &p[0..p.len() - 4];
             fs::copy(path, copy_path)?;
+            commit_change(Path::new(copy_path), "Restore the library from its backup");
             if *verbose {
                 println!("Restored CSV from {}", p);
             }
@@ -71,11 +83,12 @@ mod tests {
     use super::*;
     use crate::file_ops::write_file::create_file;
     use crate::file_ops::write_file::update_csv;
-    use crate::models::CSVLine;
+    use crate::models::{CSVLine, ReadingStatus};
     use serial_test::serial;
 
     #[test]
     #[serial]
+    #[allow(clippy::vec_init_then_push)]
     fn test_backup() -> Result<(), io::Error> {
         let path = PathBuf::from("mangas.csv");
         create_file(&Some(path.clone()))?;
@@ -84,6 +97,8 @@ mod tests {
             url: "url1".to_owned(),
             last_chapter_num: 0.0,
             title: "title".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         update_csv(&Some(path.clone()), new_lines)?;
         assert!(path.exists());
@@ -99,6 +114,7 @@ mod tests {
 
     #[test]
     #[serial]
+    #[allow(clippy::vec_init_then_push)]
     fn test_restore() -> Result<(), io::Error> {
         let path = PathBuf::from("mangas.csv");
         create_file(&Some(path.clone()))?;
@@ -107,6 +123,8 @@ mod tests {
             url: "url1".to_owned(),
             last_chapter_num: 0.0,
             title: "title".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         update_csv(&Some(path.clone()), new_lines)?;
         assert!(path.exists());
@@ -1,6 +1,8 @@
-use crate::file_ops::extract_path_or_default;
+use crate::file_ops::{extract_path_or_default, read_csv};
 use crate::file_ops::save::backup_file;
 use crate::models::CSVLine;
+use crate::output::LibraryChange;
+use crate::sync::{commit_change, describe_changes, is_synced};
 use csv::Writer;
 use std::fs;
 use std::fs::OpenOptions;
@@ -9,6 +11,7 @@ use std::path::PathBuf;
 
 /// Updates the CSV file. I effectively overwrites it wih the new data given in parameter.
 /// It's important to make sure the current lines are in the new data, as they will be overwritten!
+/// If the library is synced, the changes are committed (See [`crate::sync::commit_change`]).
 ///# Arguments:
 /// * `file_path`: the optional file path, if a custom CSV location is used.
 /// * `values`: the lines to write in the new CSV.
@@ -16,51 +19,57 @@ use std::path::PathBuf;
 /// Ok if everything went well.
 pub fn update_csv(file_path: &Option<PathBuf>, values: Vec<CSVLine>) -> Result<(), io::Error> {
     let path = extract_path_or_default(file_path);
+    let message = is_synced(&path).then(|| {
+        let before = read_csv(file_path, &false).unwrap_or_default();
+        describe_changes(&LibraryChange::diff(&before, &values))
+    });
     backup_file(Some(path.clone()))?;
-    create_file(file_path)?;
-    let file = OpenOptions::new().append(true).open(path)?;
-    let mut writer = Writer::from_writer(file);
-    for line in values {
-        writer.write_record(&[line.url, line.last_chapter_num.to_string(), line.title])?;
+    write_lines(file_path, values)?;
+    if let Some(message) = message {
+        commit_change(&path, &message);
     }
-    writer.flush()?;
     Ok(())
 }
 
-/// Appends a new line to the end of the CSV file.
-/// Does not check if the line already exists (please refer to [`crate::file_ops::is_url_present]`)
-///  # Arguments:
-/// * `file_path`: the optional file path, if a custom CSV location is used.
-/// * `url`: The URl to insert (first column).
-/// * `last_chapter`: The chapter to insert (second column)
-/// * `title`: The manga title (thrid column)
+/// Writes the lines to a new CSV file, along with the headers.
+/// Unlike [`update_csv`], there is no backup, so it is meant for the files that aren't the library.
+///# Arguments:
+/// * `file_path`: the optional file path. If None, the default library is used.
+/// * `values`: the lines to write in the new CSV.
 /// # Returns:
 /// Ok if everything went well.
-pub fn append_to_file(
-    file_path: Option<PathBuf>,
-    url: &str,
-    last_chapter: f32,
-    title: &str,
-) -> Result<(), io::Error> {
-    let path = extract_path_or_default(&file_path);
-    let file = OpenOptions::new().append(true).open(path.clone())?;
+pub fn write_lines(file_path: &Option<PathBuf>, values: Vec<CSVLine>) -> Result<(), io::Error> {
+    let path = extract_path_or_default(file_path);
+    create_file(file_path)?;
+    let file = OpenOptions::new().append(true).open(path)?;
     let mut writer = Writer::from_writer(file);
-    backup_file(Some(path))?;
-    writer.write_record(&[url, &last_chapter.to_string(), title])?;
+    for line in values {
+        writer.write_record(&[
+            line.url,
+            line.last_chapter_num.to_string(),
+            line.title,
+            line.status.to_string(),
+            line.score.map(|score| score.to_string()).unwrap_or_default(),
+        ])?;
+    }
     writer.flush()?;
     Ok(())
 }
 
 /// Creates a new CSV file, along with the headers.
 /// The CSv is not customized in terms of separation and line endings.
+/// The folder of the file is created if needed, as the default data folder might not exist yet.
 /// # Argument:
 /// * `file_path`: the optional file path, if a custom CSV location is used.
 /// # Returns:
 /// Ok if everything went well.
 pub fn create_file(file_path: &Option<PathBuf>) -> Result<(), io::Error> {
     let path = extract_path_or_default(file_path);
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
     let mut wtr = Writer::from_path(path)?;
-    wtr.write_record(&["URL", "Last chapter", "Title"])?;
+    wtr.write_record(["URL", "Last chapter", "Title", "Status", "Score"])?;
     wtr.flush()?;
     Ok(())
 }
@@ -89,23 +98,9 @@ mod tests {
 
     use super::*;
     use crate::file_ops::read_csv;
+    use crate::models::ReadingStatus;
     use serial_test::serial;
 
-    #[test]
-    #[serial]
-    fn test_append_to_file() -> Result<(), io::Error> {
-        let path = PathBuf::from("mangas.csv");
-        create_file(&Some(path.clone()))?;
-        append_to_file(Some(path.clone()), "url1", 0.0, "title")?;
-        let contents = read_csv(&Some(path), &true)?;
-        assert_eq!(contents.len(), 1);
-        assert_eq!(contents.get(0).unwrap().url, "url1");
-        assert_eq!(contents.get(0).unwrap().last_chapter_num, 0.0, "title");
-        fs::remove_file("mangas.csv")?;
-        fs::remove_file("mangas.csv.bak")?;
-        Ok(())
-    }
-
     #[test]
     #[serial]
     fn test_create_file() -> Result<(), io::Error> {
@@ -127,6 +122,7 @@ mod tests {
 
     #[test]
     #[serial]
+    #[allow(clippy::vec_init_then_push, clippy::get_first)]
     fn test_export_file() -> Result<(), io::Error> {
         remove_test_dir()?;
         let path = PathBuf::from("mangas.csv");
@@ -136,6 +132,8 @@ mod tests {
             url: "url1".to_owned(),
             last_chapter_num: 0.0,
             title: "title".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         });
         update_csv(&Some(path.clone()), new_lines)?;
         assert!(path.exists());
@@ -0,0 +1,250 @@
+use crate::models::{CSVLine, ReadingStatus, TrackerEntry};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// A response of the AniList API to a `MediaListCollection` query, as saved by the export tools.
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistResponse {
+    data: AnilistData,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistData {
+    #[serde(rename = "MediaListCollection")]
+    media_list_collection: AnilistCollection,
+}
+
+/// The lists of the user. Some tools save it without the `data` wrapper.
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistCollection {
+    lists: Vec<AnilistList>,
+}
+
+/// A list of the user, either a status list or a custom one.
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistList {
+    #[serde(default)]
+    name: String,
+    entries: Vec<AnilistEntry>,
+}
+
+/// A manga of a list.
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistEntry {
+    status: String,
+    /// Either out of 10 or out of 100, depending on the user's settings. 0 when the manga hasn't been scored.
+    #[serde(default)]
+    score: Option<f32>,
+    /// The number of chapters read.
+    #[serde(default)]
+    progress: Option<f32>,
+    media: AnilistMedia,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AnilistMedia {
+    title: AnilistTitle,
+}
+
+/// The titles of a manga. The one preferred by the user is only there if it was requested.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnilistTitle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_preferred: Option<String>,
+    #[serde(default)]
+    english: Option<String>,
+    #[serde(default)]
+    romaji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    native: Option<String>,
+}
+
+impl AnilistTitle {
+    /// Gives the title to search for: the user's preferred one, then the English, romaji and native ones.
+    fn best(self) -> Option<String> {
+        [self.user_preferred, self.english, self.romaji, self.native]
+            .into_iter()
+            .flatten()
+            .map(|title| title.trim().to_owned())
+            .find(|title| !title.is_empty())
+    }
+}
+
+/// The accepted shapes of the file: the whole response, or only the collection.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AnilistFile {
+    Response(AnilistResponse),
+    Collection(AnilistCollection),
+}
+
+/// Reads an AniList list, saved as the JSON response of a `MediaListCollection` query.
+/// A manga in several lists is only read once.
+/// # Argument:
+/// * `path`: the path to the JSON file.
+/// # Returns:
+/// The mangas of the lists.
+/// # Errors:
+/// Any IO error while reading the file, or `io::InvalidData` if it isn't an AniList list.
+pub fn read_list(path: &Path) -> Result<Vec<TrackerEntry>, io::Error> {
+    parse_list(&fs::read_to_string(path)?).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} isn't a valid AniList list: {}", path.display(), e),
+        )
+    })
+}
+
+/// Parses the JSON of an AniList list.
+fn parse_list(json: &str) -> Result<Vec<TrackerEntry>, serde_json::Error> {
+    let collection = match serde_json::from_str(json)? {
+        AnilistFile::Response(response) => response.data.media_list_collection,
+        AnilistFile::Collection(collection) => collection,
+    };
+    let mut entries: Vec<TrackerEntry> = Vec::new();
+    for entry in collection.lists.into_iter().flat_map(|list| list.entries) {
+        let Some(title) = entry.media.title.best() else {
+            continue;
+        };
+        if entries.iter().any(|known| known.title == title) {
+            continue;
+        }
+        entries.push(TrackerEntry {
+            title,
+            read_chapters: entry.progress.unwrap_or(0.0),
+            status: parse_status(&entry.status),
+            score: entry.score.filter(|score| *score > 0.0).map(|score| {
+                if score > 10.0 {
+                    score / 10.0
+                } else {
+                    score
+                }
+            }),
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads the status of a manga. A manga read again is considered as being read.
+fn parse_status(status: &str) -> ReadingStatus {
+    match status {
+        "PLANNING" => ReadingStatus::PlanToRead,
+        "PAUSED" => ReadingStatus::OnHold,
+        "COMPLETED" => ReadingStatus::Completed,
+        "DROPPED" => ReadingStatus::Dropped,
+        _ => ReadingStatus::Reading,
+    }
+}
+
+/// Names a status the way AniList does, along with the name of its list.
+fn status_names(status: ReadingStatus) -> (&'static str, &'static str) {
+    match status {
+        ReadingStatus::Reading => ("CURRENT", "Reading"),
+        ReadingStatus::PlanToRead => ("PLANNING", "Planning"),
+        ReadingStatus::OnHold => ("PAUSED", "Paused"),
+        ReadingStatus::Completed => ("COMPLETED", "Completed"),
+        ReadingStatus::Dropped => ("DROPPED", "Dropped"),
+    }
+}
+
+/// Writes the library as an AniList list, in the shape of the response of a `MediaListCollection` query.
+/// There is one list per status, and the scores are out of 10. As AniList only counts whole chapters, the chapters are rounded down.
+/// # Argument:
+/// * `lines`: the lines of the library.
+/// # Returns:
+/// The JSON of the list.
+pub fn write_list(lines: &[CSVLine]) -> Result<String, serde_json::Error> {
+    let mut lists: Vec<AnilistList> = Vec::new();
+    for status in ReadingStatus::ALL {
+        let (status_name, list_name) = status_names(status);
+        let entries: Vec<AnilistEntry> = lines
+            .iter()
+            .filter(|line| line.status == status)
+            .map(|line| AnilistEntry {
+                status: status_name.to_owned(),
+                score: Some(line.score.unwrap_or(0.0)),
+                progress: Some(line.last_chapter_num.max(0.0).floor()),
+                media: AnilistMedia {
+                    title: AnilistTitle {
+                        romaji: Some(line.title.clone()),
+                        ..Default::default()
+                    },
+                },
+            })
+            .collect();
+        if !entries.is_empty() {
+            lists.push(AnilistList {
+                name: list_name.to_owned(),
+                entries,
+            });
+        }
+    }
+    serde_json::to_string_pretty(&AnilistResponse {
+        data: AnilistData {
+            media_list_collection: AnilistCollection { lists },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_is_read_and_written() -> Result<(), serde_json::Error> {
+        let json = r#"{"data": {"MediaListCollection": {"lists": [
+            {"name": "Reading", "entries": [
+                {"status": "CURRENT", "score": 85, "progress": 120,
+                 "media": {"title": {"romaji": "Shingeki no Kyojin", "english": "Attack on Titan"}}},
+                {"status": "REPEATING", "score": 0, "progress": 3,
+                 "media": {"title": {"romaji": "Yotsuba to!", "english": null}}}
+            ]},
+            {"name": "Favourites", "entries": [
+                {"status": "CURRENT", "score": 85, "progress": 120,
+                 "media": {"title": {"romaji": "Shingeki no Kyojin", "english": "Attack on Titan"}}}
+            ]},
+            {"name": "Paused", "entries": [
+                {"status": "PAUSED", "score": 7.5, "progress": 10,
+                 "media": {"title": {"userPreferred": "Berserk", "romaji": "Berserk"}}}
+            ]}
+        ]}}}"#;
+        let entries = parse_list(json)?;
+        assert_eq!(
+            entries,
+            vec![
+                TrackerEntry {
+                    title: "Attack on Titan".to_owned(),
+                    read_chapters: 120.0,
+                    status: ReadingStatus::Reading,
+                    score: Some(8.5),
+                },
+                TrackerEntry {
+                    title: "Yotsuba to!".to_owned(),
+                    read_chapters: 3.0,
+                    status: ReadingStatus::Reading,
+                    score: None,
+                },
+                TrackerEntry {
+                    title: "Berserk".to_owned(),
+                    read_chapters: 10.0,
+                    status: ReadingStatus::OnHold,
+                    score: Some(7.5),
+                },
+            ]
+        );
+
+        let lines: Vec<CSVLine> = entries
+            .into_iter()
+            .map(|entry| entry.into_line("http://localhost/manga".to_owned(), None))
+            .collect();
+        let written = write_list(&lines)?;
+        let collection = r#"{"lists": [{"entries": [{"status": "DROPPED", "media": {"title": {"romaji": "Claymore"}}}]}]}"#;
+        assert_eq!(parse_list(&written)?.len(), 3);
+        assert_eq!(parse_list(collection)?[0].status, ReadingStatus::Dropped);
+        Ok(())
+    }
+}
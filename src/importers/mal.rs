@@ -0,0 +1,190 @@
+use crate::models::{CSVLine, ReadingStatus, TrackerEntry};
+use crate::utils::escape_html;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::fs::File;
+use std::io;
+use std::io::{Error, ErrorKind, Read};
+use std::path::Path;
+
+/// The root of a MyAnimeList export. Only the mangas are read, the animes and the user's info are ignored.
+#[derive(Debug, Deserialize)]
+struct MalExport {
+    #[serde(default)]
+    manga: Vec<MalManga>,
+}
+
+/// A manga of a MyAnimeList export. Only the fields needed by the import are read.
+#[derive(Debug, Deserialize)]
+struct MalManga {
+    manga_title: String,
+    #[serde(default)]
+    my_read_chapters: f32,
+    /// From 1 to 10, 0 when the manga hasn't been scored.
+    #[serde(default)]
+    my_score: f32,
+    #[serde(default)]
+    my_status: String,
+}
+
+/// Reads a MyAnimeList export, either the `.xml.gz` file given by the website or the extracted `.xml` file.
+/// # Argument:
+/// * `path`: the path to the export.
+/// # Returns:
+/// The mangas of the list.
+/// # Errors:
+/// Any IO error while reading the file, or `io::InvalidData` if it isn't a MyAnimeList export.
+pub fn read_list(path: &Path) -> Result<Vec<TrackerEntry>, io::Error> {
+    let mut xml = String::new();
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        GzDecoder::new(file).read_to_string(&mut xml)?;
+    } else {
+        let mut file = file;
+        file.read_to_string(&mut xml)?;
+    }
+    parse_list(&xml).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} isn't a valid MyAnimeList export: {}", path.display(), e),
+        )
+    })
+}
+
+/// Parses the XML of a MyAnimeList export.
+fn parse_list(xml: &str) -> Result<Vec<TrackerEntry>, quick_xml::DeError> {
+    let export: MalExport = quick_xml::de::from_str(xml)?;
+    Ok(export
+        .manga
+        .into_iter()
+        .map(|manga| TrackerEntry {
+            title: manga.manga_title.trim().to_owned(),
+            read_chapters: manga.my_read_chapters,
+            status: parse_status(&manga.my_status),
+            score: Some(manga.my_score).filter(|score| *score > 0.0),
+        })
+        .collect())
+}
+
+/// Reads the status of a manga. The older exports use numbers instead of names.
+fn parse_status(status: &str) -> ReadingStatus {
+    match status.trim() {
+        "Completed" | "2" => ReadingStatus::Completed,
+        "On-Hold" | "3" => ReadingStatus::OnHold,
+        "Dropped" | "4" => ReadingStatus::Dropped,
+        "Plan to Read" | "6" => ReadingStatus::PlanToRead,
+        _ => ReadingStatus::Reading,
+    }
+}
+
+/// Names a status the way MyAnimeList does.
+fn status_name(status: ReadingStatus) -> &'static str {
+    match status {
+        ReadingStatus::Reading => "Reading",
+        ReadingStatus::PlanToRead => "Plan to Read",
+        ReadingStatus::OnHold => "On-Hold",
+        ReadingStatus::Completed => "Completed",
+        ReadingStatus::Dropped => "Dropped",
+    }
+}
+
+/// Writes the library as a MyAnimeList export.
+/// The mangas have no MyAnimeList ID, so the list must be imported by a tool matching the titles.
+/// As MyAnimeList only counts whole chapters, the chapters are rounded down.
+/// # Argument:
+/// * `lines`: the lines of the library.
+/// # Returns:
+/// The XML of the export.
+pub fn write_list(lines: &[CSVLine]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<myanimelist>\n");
+    xml.push_str("  <myinfo>\n");
+    xml.push_str(&format!(
+        "    <user_total_manga>{}</user_total_manga>\n",
+        lines.len()
+    ));
+    xml.push_str("    <user_export_type>2</user_export_type>\n");
+    xml.push_str("  </myinfo>\n");
+    for line in lines {
+        xml.push_str("  <manga>\n");
+        xml.push_str("    <manga_mangadb_id>0</manga_mangadb_id>\n");
+        xml.push_str(&format!(
+            "    <manga_title>{}</manga_title>\n",
+            escape_html(&line.title)
+        ));
+        xml.push_str(&format!(
+            "    <my_read_chapters>{}</my_read_chapters>\n",
+            line.last_chapter_num.max(0.0).floor()
+        ));
+        xml.push_str(&format!(
+            "    <my_score>{}</my_score>\n",
+            line.score.map(|score| score.round()).unwrap_or(0.0)
+        ));
+        xml.push_str(&format!(
+            "    <my_status>{}</my_status>\n",
+            status_name(line.status)
+        ));
+        xml.push_str("    <update_on_import>1</update_on_import>\n");
+        xml.push_str("  </manga>\n");
+    }
+    xml.push_str("</myanimelist>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_is_read_and_written() -> Result<(), quick_xml::DeError> {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<myanimelist>
+  <myinfo>
+    <user_name>reader</user_name>
+    <user_export_type>2</user_export_type>
+  </myinfo>
+  <manga>
+    <manga_mangadb_id>2</manga_mangadb_id>
+    <manga_title><![CDATA[Berserk]]></manga_title>
+    <manga_chapters>0</manga_chapters>
+    <my_read_chapters>380</my_read_chapters>
+    <my_score>9</my_score>
+    <my_status>On-Hold</my_status>
+    <my_comments><![CDATA[]]></my_comments>
+  </manga>
+  <manga>
+    <manga_mangadb_id>11</manga_mangadb_id>
+    <manga_title><![CDATA[Naruto & Co]]></manga_title>
+    <my_read_chapters>0</my_read_chapters>
+    <my_score>0</my_score>
+    <my_status>Plan to Read</my_status>
+  </manga>
+</myanimelist>"#;
+        let entries = parse_list(xml)?;
+        assert_eq!(
+            entries,
+            vec![
+                TrackerEntry {
+                    title: "Berserk".to_owned(),
+                    read_chapters: 380.0,
+                    status: ReadingStatus::OnHold,
+                    score: Some(9.0),
+                },
+                TrackerEntry {
+                    title: "Naruto & Co".to_owned(),
+                    read_chapters: 0.0,
+                    status: ReadingStatus::PlanToRead,
+                    score: None,
+                },
+            ]
+        );
+
+        let lines: Vec<CSVLine> = entries
+            .into_iter()
+            .map(|entry| entry.into_line("http://localhost/manga".to_owned(), None))
+            .collect();
+        let written = write_list(&lines);
+        assert!(written.contains("<manga_title>Naruto &amp; Co</manga_title>"));
+        assert_eq!(parse_list(&written)?.len(), 2);
+        Ok(())
+    }
+}
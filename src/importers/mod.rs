@@ -0,0 +1,225 @@
+/// AniList lists
+pub mod anilist;
+/// MyAnimeList exports
+pub mod mal;
+/// Tachiyomi and Mihon backups
+pub mod tachiyomi;
+
+use crate::file_ops::read_csv;
+use crate::models::{CSVLine, ReviewItem, SearchResult, TrackerEntry};
+use crate::scraper::{concurrency, create_client, search_titles};
+use futures::{stream, StreamExt};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The kinds of files the import command can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A CSV file written by this program.
+    Csv,
+    /// A Tachiyomi or Mihon backup, i.e. a gzipped protobuf.
+    Tachiyomi,
+    /// A MyAnimeList export, i.e. an XML file, gzipped or not.
+    Mal,
+    /// An AniList list, i.e. a JSON file.
+    Anilist,
+}
+
+impl ImportFormat {
+    /// Guesses the kind of the file from its name.
+    /// The `.tachibk` and `.proto.gz` files are backups, the `.xml` and `.xml.gz` files MyAnimeList exports, and the `.json` files AniList lists.
+    /// Anything else is expected to be a CSV file.
+    pub fn detect(path: &Path) -> ImportFormat {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".tachibk") || name.ends_with(".proto.gz") {
+            ImportFormat::Tachiyomi
+        } else if name.ends_with(".xml") || name.ends_with(".xml.gz") {
+            ImportFormat::Mal
+        } else if name.ends_with(".json") {
+            ImportFormat::Anilist
+        } else {
+            ImportFormat::Csv
+        }
+    }
+}
+
+/// A manga of the imported file that couldn't be turned into a library entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedManga {
+    pub title: String,
+    pub url: Option<String>,
+    pub reason: String,
+}
+
+/// The lines read from the imported file, along with the mangas that couldn't be imported.
+#[derive(Debug, Default)]
+pub struct ImportedLines {
+    pub lines: Vec<CSVLine>,
+    pub skipped: Vec<SkippedManga>,
+    /// The mangas of a tracker's list, whose URL must be found (See [`resolve_entries`]).
+    pub unresolved: Vec<TrackerEntry>,
+}
+
+/// Reads the lines of a file to import, whatever its kind.
+/// # Arguments:
+/// * `path`: the file to import.
+/// * `verbose`: if true, more messages will be shown.
+/// # Returns:
+/// The lines to merge into the library, and the mangas that were left aside.
+/// # Errors:
+/// Any IO error while reading the file, or `io::InvalidData` if it can't be decoded.
+pub fn read_imported_lines(path: &PathBuf, verbose: bool) -> Result<ImportedLines, io::Error> {
+    match ImportFormat::detect(path) {
+        ImportFormat::Csv => Ok(ImportedLines {
+            lines: read_csv(&Some(path.clone()), &verbose)?,
+            ..Default::default()
+        }),
+        ImportFormat::Tachiyomi => {
+            let backup = tachiyomi::read_backup(path)?;
+            if verbose {
+                println!("Found {} mangas in the backup.", backup.backup_manga.len());
+            }
+            Ok(tachiyomi::backup_lines(&backup))
+        }
+        ImportFormat::Mal => Ok(tracker_lines(mal::read_list(path)?, verbose)),
+        ImportFormat::Anilist => Ok(tracker_lines(anilist::read_list(path)?, verbose)),
+    }
+}
+
+/// Keeps the mangas of a tracker's list aside, as their URLs must be found before they can be imported.
+fn tracker_lines(entries: Vec<TrackerEntry>, verbose: bool) -> ImportedLines {
+    if verbose {
+        println!("Found {} mangas in the list.", entries.len());
+    }
+    ImportedLines {
+        unresolved: entries,
+        ..Default::default()
+    }
+}
+
+/// Simplifies a title to compare it with another: only the lowercase letters and digits are kept.
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Finds the search result whose title is the one of the entry, ignoring the case and the punctuation.
+/// # Arguments:
+/// * `entry`: the manga of the tracker's list.
+/// * `candidates`: the search results for its title.
+/// # Returns:
+/// The line of the library for the entry, or None if no result matches exactly.
+pub fn match_entry(entry: &TrackerEntry, candidates: &[SearchResult]) -> Option<CSVLine> {
+    let title = normalize_title(&entry.title);
+    candidates
+        .iter()
+        .find(|candidate| normalize_title(&candidate.title) == title)
+        .map(|candidate| {
+            entry
+                .clone()
+                .into_line(candidate.url.clone(), Some(candidate.title.clone()))
+        })
+}
+
+/// Finds the URLs of the mangas of a tracker's list, by searching their titles on the website.
+/// A manga is resolved when a search result has exactly its title; otherwise it is left for the user to review, along with the results.
+/// # Arguments:
+/// * `entries`: the mangas of the list.
+/// * `verbose`: if true, the searches that failed are printed.
+/// # Returns:
+/// The lines of the resolved mangas, and the mangas to review.
+pub async fn resolve_entries(
+    entries: Vec<TrackerEntry>,
+    verbose: bool,
+) -> (Vec<CSVLine>, Vec<ReviewItem>) {
+    let client = create_client().ok();
+    let searches: Vec<_> = stream::iter(entries)
+        .map(|entry| {
+            let client = client.clone();
+            async move {
+                let candidates = match search_titles(&entry.title, client.as_ref()).await {
+                    Ok(candidates) => candidates,
+                    Err(e) => {
+                        if verbose {
+                            eprintln!("{}", e.reason);
+                        }
+                        Vec::new()
+                    }
+                };
+                (entry, candidates)
+            }
+        })
+        .buffered(concurrency())
+        .collect()
+        .await;
+    let mut lines = Vec::new();
+    let mut review = Vec::new();
+    for (entry, candidates) in searches {
+        match match_entry(&entry, &candidates) {
+            Some(line) => lines.push(line),
+            None => review.push(ReviewItem { entry, candidates }),
+        }
+    }
+    (lines, review)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_detected() {
+        assert_eq!(
+            ImportFormat::detect(Path::new("/tmp/mihon_2024-01-01.tachibk")),
+            ImportFormat::Tachiyomi
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("tachiyomi_2022-03-04.proto.gz")),
+            ImportFormat::Tachiyomi
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("mangas.csv")),
+            ImportFormat::Csv
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("animelist_1700000000_-_123.xml.gz")),
+            ImportFormat::Mal
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("anilist.JSON")),
+            ImportFormat::Anilist
+        );
+    }
+
+    #[test]
+    fn entries_match_exact_titles() {
+        let entry = TrackerEntry {
+            title: "Mushoku Tensei: Isekai Ittara Honki Dasu".to_owned(),
+            read_chapters: 12.0,
+            status: Default::default(),
+            score: Some(8.0),
+        };
+        let candidates = vec![
+            SearchResult {
+                title: "Mushoku Tensei - Roxy Is Serious".to_owned(),
+                url: "https://readmanganato.com/manga-hx986305".to_owned(),
+            },
+            SearchResult {
+                title: "Mushoku Tensei - Isekai Ittara Honki Dasu".to_owned(),
+                url: "https://readmanganato.com/manga-qm951521".to_owned(),
+            },
+        ];
+        let line = match_entry(&entry, &candidates).unwrap();
+        assert_eq!(line.url, "https://readmanganato.com/manga-qm951521");
+        assert_eq!(line.title, "Mushoku Tensei - Isekai Ittara Honki Dasu");
+        assert_eq!(line.last_chapter_num, 12.0);
+        assert_eq!(line.score, Some(8.0));
+        assert!(match_entry(&entry, &candidates[..1]).is_none());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::importers::{ImportedLines, SkippedManga};
+use crate::models::CSVLine;
+use flate2::read::GzDecoder;
+use prost::Message;
+use std::fs::File;
+use std::io;
+use std::io::{Error, ErrorKind, Read};
+use std::path::PathBuf;
+
+/// The sources whose pages can be read by the scraper, by lowercase name, along with their website.
+/// The URLs of the backups are relative to the website of their source.
+const SUPPORTED_SOURCES: [(&str, &str); 3] = [
+    ("manganato", "https://manganato.com"),
+    ("manganelo", "https://manganelo.com"),
+    ("readmanganato", "https://readmanganato.com"),
+];
+
+/// A Tachiyomi or Mihon backup. Only the fields needed by the import are decoded, the others are skipped.
+#[derive(Clone, PartialEq, Message)]
+pub struct Backup {
+    #[prost(message, repeated, tag = "1")]
+    pub backup_manga: Vec<BackupManga>,
+    #[prost(message, repeated, tag = "101")]
+    pub backup_sources: Vec<BackupSource>,
+}
+
+/// A manga of the backup.
+#[derive(Clone, PartialEq, Message)]
+pub struct BackupManga {
+    #[prost(int64, tag = "1")]
+    pub source: i64,
+    #[prost(string, tag = "2")]
+    pub url: String,
+    #[prost(string, tag = "3")]
+    pub title: String,
+    #[prost(message, repeated, tag = "16")]
+    pub chapters: Vec<BackupChapter>,
+    /// Missing when the manga is in the library, as it is the default value.
+    #[prost(bool, optional, tag = "100")]
+    pub favorite: Option<bool>,
+}
+
+/// A chapter of a manga of the backup.
+#[derive(Clone, PartialEq, Message)]
+pub struct BackupChapter {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(bool, tag = "4")]
+    pub read: bool,
+    /// Negative when the source couldn't tell the number of the chapter.
+    #[prost(float, tag = "9")]
+    pub chapter_number: f32,
+}
+
+/// A source of the backup, i.e. an extension of the reader.
+#[derive(Clone, PartialEq, Message)]
+pub struct BackupSource {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(int64, tag = "2")]
+    pub source_id: i64,
+}
+
+/// Reads a `.tachibk` or `.proto.gz` backup.
+/// # Argument:
+/// * `path`: the path to the backup.
+/// # Returns:
+/// The decoded backup.
+/// # Errors:
+/// Any IO error while reading the file, or `io::InvalidData` if it isn't a gzipped backup.
+pub fn read_backup(path: &PathBuf) -> Result<Backup, io::Error> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(File::open(path)?).read_to_end(&mut bytes)?;
+    Backup::decode(bytes.as_slice()).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} isn't a valid backup: {}", path.display(), e),
+        )
+    })
+}
+
+/// Turns the mangas of the library of the backup into lines.
+/// The last chapter of a line is the highest chapter marked as read, or 0 if none has been read.
+/// The mangas from a source the scraper can't read are skipped, as well as the ones that were removed from the library.
+/// # Argument:
+/// * `backup`: the decoded backup.
+/// # Returns:
+/// The lines, and the mangas whose source isn't supported.
+pub fn backup_lines(backup: &Backup) -> ImportedLines {
+    let mut imported = ImportedLines::default();
+    for manga in &backup.backup_manga {
+        if !manga.favorite.unwrap_or(true) {
+            continue;
+        }
+        let source = source_name(backup, manga.source);
+        match manga_url(&source, &manga.url) {
+            Some(url) => imported.lines.push(CSVLine {
+                title: if manga.title.is_empty() {
+                    url.clone()
+                } else {
+                    manga.title.clone()
+                },
+                url,
+                last_chapter_num: last_read_chapter(manga),
+                status: Default::default(),
+                score: None,
+            }),
+            None => imported.skipped.push(SkippedManga {
+                title: manga.title.clone(),
+                url: Some(manga.url.clone()),
+                reason: format!("The source {} isn't supported.", source),
+            }),
+        }
+    }
+    imported
+}
+
+/// Finds the name of the source from the list of the backup.
+fn source_name(backup: &Backup, id: i64) -> String {
+    backup
+        .backup_sources
+        .iter()
+        .find(|source| source.source_id == id)
+        .map(|source| source.name.clone())
+        .unwrap_or_else(|| format!("#{}", id))
+}
+
+/// Builds the full URL of a manga, if its source is supported.
+fn manga_url(source: &str, url: &str) -> Option<String> {
+    let (_, website) = SUPPORTED_SOURCES
+        .iter()
+        .find(|(name, _)| source.eq_ignore_ascii_case(name))?;
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Some(url.to_owned())
+    } else {
+        Some(format!("{}/{}", website, url.trim_start_matches('/')))
+    }
+}
+
+/// Finds the highest chapter marked as read.
+fn last_read_chapter(manga: &BackupManga) -> f32 {
+    manga
+        .chapters
+        .iter()
+        .filter(|chapter| chapter.read && chapter.chapter_number >= 0.0)
+        .map(|chapter| chapter.chapter_number)
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::io::Write;
+
+    fn chapter(num: f32, read: bool) -> BackupChapter {
+        BackupChapter {
+            url: format!("/chapter-{}", num),
+            name: format!("Chapter {}", num),
+            read,
+            chapter_number: num,
+        }
+    }
+
+    #[test]
+    fn backup_is_decoded_and_mapped() -> Result<(), io::Error> {
+        let backup = Backup {
+            backup_manga: vec![
+                BackupManga {
+                    source: 1,
+                    url: "/manga-ab123".to_owned(),
+                    title: "Supported".to_owned(),
+                    chapters: vec![chapter(1.0, true), chapter(2.5, true), chapter(3.0, false)],
+                    favorite: None,
+                },
+                BackupManga {
+                    source: 2,
+                    url: "/title/xyz".to_owned(),
+                    title: "Unsupported".to_owned(),
+                    chapters: vec![chapter(1.0, true)],
+                    favorite: None,
+                },
+                BackupManga {
+                    source: 1,
+                    url: "https://manganato.com/manga-removed".to_owned(),
+                    title: "Removed".to_owned(),
+                    chapters: Vec::new(),
+                    favorite: Some(false),
+                },
+            ],
+            backup_sources: vec![
+                BackupSource {
+                    name: "Manganato".to_owned(),
+                    source_id: 1,
+                },
+                BackupSource {
+                    name: "MangaDex".to_owned(),
+                    source_id: 2,
+                },
+            ],
+        };
+        let path = std::env::temp_dir().join("manga_updater_test.tachibk");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&backup.encode_to_vec())?;
+        fs::write(&path, encoder.finish()?)?;
+
+        let decoded = read_backup(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(decoded, backup);
+
+        let imported = backup_lines(&decoded);
+        assert_eq!(imported.lines.len(), 1);
+        assert_eq!(imported.lines[0].url, "https://manganato.com/manga-ab123");
+        assert_eq!(imported.lines[0].title, "Supported");
+        assert_eq!(imported.lines[0].last_chapter_num, 2.5);
+        assert_eq!(imported.skipped.len(), 1);
+        assert_eq!(imported.skipped[0].title, "Unsupported");
+        assert_eq!(
+            imported.skipped[0].reason,
+            "The source MangaDex isn't supported."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_backup_is_rejected() -> Result<(), io::Error> {
+        let path = std::env::temp_dir().join("manga_updater_invalid.tachibk");
+        fs::write(&path, b"not gzipped")?;
+        assert!(read_backup(&path).is_err());
+        fs::remove_file(&path)
+    }
+}
@@ -1,74 +1,501 @@
+mod cadence;
 mod commands;
+mod config;
+mod doctor;
+mod downloads;
+mod email;
+mod exporters;
+mod feed;
 mod file_ops;
+mod importers;
+mod merge;
 mod models;
+mod notifiers;
+mod output;
+mod paths;
+mod queue;
 mod scraper;
+mod server;
+mod stats;
+mod sync;
+/// The builders shared by the tests
+#[cfg(test)]
+mod test_utils;
+mod tui;
+mod urls;
 mod utils;
 
 use structopt::StructOpt;
+use structopt::clap::{AppSettings, Shell};
+use owo_colors::set_override;
 use std::path::PathBuf;
-use crate::commands::{list, init, add, update, export, import, remove, open, unread, undo};
+use std::process;
+use std::time::Duration;
+use crate::commands::{list, init, add, update, export, import, review, remove, download, package, doctor, info, open, queue, stats, unread, undo, watch, email_digest, feed, serve, tui, migrate, profile_create, profile_list, profile_delete, profile_copy, sync_init, sync, merge_driver, completions, complete_entries, usage_error, ImportMode, QueueAction};
+use crate::downloads::epub::Screen;
+use crate::email::DigestPeriod;
+use crate::feed::FeedFormat;
+use crate::exporters::ExportFormat;
+use crate::merge::MergeStrategy;
+use crate::models::ReadingStatus;
+use crate::utils::parse_duration;
+use crate::config::{load_config, Colours};
+use crate::notifiers::NotifierConfig;
+use crate::output::OutputFormat;
+use crate::queue::QueueOrder;
+use crate::paths::{check_profile_name, legacy_files, profile_library_path, DEFAULT_PROFILE};
+use crate::scraper::{configure_client, ClientSettings, DEFAULT_CONCURRENCY};
 
-/// The CLI struct to store the different commands and parameters used by the app.
+/// The CLI struct to store the options shared by every command, and the command to run.
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Manga updater", about = "A CLI tool to show updated manga chapters.")]
 struct Cli {
-    //The command can be list, add [url], remove [url], update [url/all] (coming soon)
+    //A path is optional (used mainly for debug purposes), and indicates the file containing the URLs.
+    #[structopt(short = "p", long = "path", parse(from_os_str), global = true, env = "MANGA_UPDATER_LIBRARY",
+    help="The path to the CSV file to use. Overrides the library of the config file, and mangas.csv in the data folder.")]
+    path: Option<PathBuf>,
+
+    //A profile has its own library and settings, and is used instead of the default library.
+    #[structopt(long = "profile", global = true, env = "MANGA_UPDATER_PROFILE",
+    help="The profile to use, with its own library and settings. Defaults to the default library.")]
+    profile: Option<String>,
+
+    #[structopt(short="v", long="verbose", global = true, help="Be more verbose about the process.")]
+    verbose: bool,
+
+    //The config file, used mainly to configure the notifiers.
+    #[structopt(short = "c", long = "config", parse(from_os_str), global = true, env = "MANGA_UPDATER_CONFIG",
+    help="The path to the TOML config file. Defaults to config.toml in the config folder.")]
+    config: Option<PathBuf>,
+
+    //The format of the output. Everything but table is meant to be read by other programs.
+    #[structopt(short = "f", long = "format", possible_values = &["table", "json", "ndjson"], global = true,
+    help="The output format. json and ndjson print the structured results of the command, and disable the verbose messages. Defaults to table.")]
+    format: Option<OutputFormat>,
+
     //By default, it takes nothing to return the last chapters of the stored mangas.
-    #[structopt(default_value="list",
-    help="Available commands: list, add [url], remove [url], export [-e path], import [-e path], update [url/all], open [url/line number], restore. For more info, refer to the doc.")]
-    command: String,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
 
-    //The URL to the manga to add / remove. Can be [all] in the case of update.
-    #[structopt(help="The URL to the manga page on manganelo. Can be all for update, or a line number.")]
-    argument: Option<String>,
+// The commands of the app, each with its own arguments. Not a doc comment, as it would replace the about of the app.
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(about = "Lists the mangas and tells which ones have new chapters. This is the default command.")]
+    List {
+        //If set, displays only new chapters in the output.
+        #[structopt(short="n", long="new", help="Display only new chapters.")]
+        new: bool,
 
-    //A path is optional (used mainly for debug purposes), and indicates the file containing the URLs.
-    #[structopt(short = "p", long = "path", parse(from_os_str),
-    help="The path to the CSV file to use. Overrides default.")]
-    path: Option<PathBuf>,
+        #[structopt(short = "u", long="no-update", help="Will not update the opened manga.")]
+        no_update: bool,
 
-    //The path to export the CSV file to, or import from.
-    #[structopt(short = "e", long = "external", parse(from_os_str),
-    help="The path to export/import a CSV file from the application.")]
-    external_file: Option<PathBuf>,
+        //If set, the list command won't ask for a manga to open. It is also the case when stdin isn't a terminal.
+        #[structopt(long="no-prompt", help="Do not ask for a manga to open after listing. Implied when stdin is not a terminal.")]
+        no_prompt: bool,
 
-    //If set, opens the last chapter directly.
-    #[structopt(short="d", long="direct", help="Open the last chapter directly.")]
-    direct: bool,
+        #[structopt(long = "on-new", help = ON_NEW_HELP)]
+        on_new: Option<String>,
+    },
 
-    //If set, displays only new chapters in the output.
-    #[structopt(short="n", long="new", help="Display only new chapters.")]
-    new: bool,
+    #[structopt(about = "Creates the CSV file to store the mangas.")]
+    Init,
 
-    #[structopt(short="o", long="overwrite",
-    help="Specifies if the current database must be overwritten. Usable only with import command.")]
-    overwrite: bool,
+    #[structopt(about = "Adds a manga, along with its latest chapter, or every manga whose URL is found in a text.")]
+    Add {
+        #[structopt(help="The URL to the manga page, or - to add the URLs found in the text read from stdin.")]
+        url: Option<String>,
 
-    #[structopt(short="v", long="verbose", help="Be more verbose about the process.")]
-    verbose: bool,
+        #[structopt(long = "from-file", parse(from_os_str),
+        help="Adds the URLs found in the file instead, e.g. exported bookmarks or pasted text.")]
+        from_file: Option<PathBuf>,
+    },
+
+    #[structopt(about = "Updates the mangas to their latest chapters.")]
+    Update {
+        #[structopt(help="The URL or the line number of the manga to update. Defaults to all.")]
+        target: Option<String>,
+    },
+
+    #[structopt(about = "Copies the CSV file to a folder, or writes the library as JSON, Markdown, HTML, OPML or a MyAnimeList/AniList list.")]
+    Export {
+        #[structopt(short = "e", long = "external", parse(from_os_str),
+        help="The folder to export the CSV file to. With the other formats, the file to write, as --output.")]
+        to: Option<PathBuf>,
+
+        #[structopt(short = "o", long = "output", parse(from_os_str),
+        help="The file to write. Defaults to a file next to the CSV file, e.g. mangas.html, except for csv.")]
+        output: Option<PathBuf>,
+
+        //Not --format, which is the output format of every command.
+        #[structopt(long = "as", possible_values = &["csv", "json", "markdown", "html", "opml", "mal", "anilist"],
+        help="The format of the exported file. html is a page with the covers, markdown a reading list to share. Defaults to csv, a copy of the CSV file. Not --format, which is the output format of the command.")]
+        export_format: Option<ExportFormat>,
+
+        #[structopt(short = "s", long = "status", possible_values = &["reading", "plan_to_read", "on_hold", "completed", "dropped"],
+        help="Only export the mangas with this status. Can be given several times.")]
+        statuses: Vec<ReadingStatus>,
+    },
+
+    #[structopt(about = "Imports a CSV file, a Tachiyomi/Mihon backup or a MyAnimeList/AniList list into the library.")]
+    Import {
+        #[structopt(short = "e", long = "external", parse(from_os_str),
+        help="The CSV file, the .tachibk/.proto.gz backup, the MyAnimeList .xml/.xml.gz export or the AniList .json list to import.")]
+        from: Option<PathBuf>,
+
+        #[structopt(short="o", long="overwrite", conflicts_with = "merge", help="Replace the current library instead of adding the missing mangas.")]
+        overwrite: bool,
+
+        //The changes made on both sides since the last merge are kept, and the strategy resolves the conflicts.
+        #[structopt(short="m", long="merge", possible_values = &["prefer-highest-chapter", "prefer-newest-edit", "prefer-source", "prefer-target", "interactive"],
+        help="Merge the changes of the file and of the library since the last merge. The strategy chooses the version of the entries changed on both sides.")]
+        merge: Option<MergeStrategy>,
+
+        #[structopt(long="dry-run", help="Show the changes without writing them.")]
+        dry_run: bool,
+
+        #[structopt(short="y", long="yes", help="Write a merge without asking for a confirmation after its preview.")]
+        yes: bool,
+    },
+
+    #[structopt(about = "Picks the URLs of the imported MyAnimeList/AniList mangas that couldn't be found.")]
+    Review,
+
+    #[structopt(about = "Removes a manga from the library.")]
+    Remove {
+        #[structopt(help="The URL or the line number of the manga to remove.")]
+        target: String,
+    },
+
+    #[structopt(about = "Downloads chapters of a manga as CBZ archives, to read them offline. Defaults to the unread chapters.")]
+    Download {
+        #[structopt(help="The URL or the line number of the manga, as shown by list.")]
+        target: String,
+
+        #[structopt(long="from", help="The first chapter to download.")]
+        from: Option<f32>,
+
+        #[structopt(long="to", help="The last chapter to download.")]
+        to: Option<f32>,
+    },
+
+    #[structopt(about = "Packages downloaded chapters of a manga into a fixed-layout EPUB, to read them on an e-reader. Defaults to every downloaded chapter.")]
+    Package {
+        #[structopt(help="The URL or the line number of the manga, as shown by list.")]
+        target: String,
+
+        #[structopt(long="epub", help="Package the chapters as an EPUB3, the only format for now.")]
+        epub: bool,
+
+        #[structopt(long="from", help="The first chapter to package.")]
+        from: Option<f32>,
+
+        #[structopt(long="to", help="The last chapter to package.")]
+        to: Option<f32>,
+
+        #[structopt(long="downscale", help="Downscale the larger pages to the screen of an e-reader: kindle, kobo or a size such as 1072x1448.")]
+        downscale: Option<Screen>,
+    },
+
+    #[structopt(about = "Checks the library for problems: malformed lines, duplicates, dead links, changed layouts, chapters ahead of the website and stale backups.")]
+    Doctor {
+        #[structopt(long="fix", help="Apply the fixes that lose nothing: merge the duplicates, reset the unknown statuses and scores, and remove the stale backup.")]
+        fix: bool,
+    },
+
+    #[structopt(about = "Shows the details of a manga, with its release cadence and its predicted next release.")]
+    Info {
+        #[structopt(help="The URL or the line number of the manga, as shown by list.")]
+        target: String,
+    },
+
+    #[structopt(about = "Opens the page of a manga in the browser.")]
+    Open {
+        #[structopt(help="The URL or the line number of the manga to open, as shown by list.")]
+        target: String,
+
+        //If set, opens the last chapter directly.
+        #[structopt(short="d", long="direct", help="Open the last chapter directly.")]
+        direct: bool,
+
+        //If set, opens the first unread chapter, and records it as read.
+        #[structopt(short="n", long="next", conflicts_with="direct", help="Open the first unread chapter, and record it as the last chapter read.")]
+        next: bool,
+    },
+
+    #[structopt(about = "Lists every unread chapter of the mangas being read, in the reading order.")]
+    Queue {
+        #[structopt(long="sort", default_value="release", possible_values = &["release", "priority", "behind"], help="How the series are ordered: by release date, by score, or the ones behind for the longest time first.")]
+        sort: QueueOrder,
+
+        #[structopt(subcommand)]
+        action: Option<QueueCommand>,
+    },
+
+    #[structopt(about = "Prints statistics about the library: chapters read, backlog, and the release cadence of the mangas.")]
+    Stats {
+        #[structopt(long="stale-months", default_value="6", help="The number of months without release after which a manga is listed as stale.")]
+        stale_months: u32,
+
+        #[structopt(long, help="Draw the chapters read per week and per month as sparklines.")]
+        sparkline: bool,
+    },
 
-    #[structopt(short = "u", long="no-update", help="Will not update the opened manga.")]
-    no_update: bool
+    #[structopt(about = "Sets the last chapter read of a manga back one chapter.")]
+    Unread {
+        #[structopt(help="The line number of the manga, as shown by list.")]
+        line: String,
+    },
+
+    #[structopt(about = "Undoes the last change made to the library.")]
+    Undo,
+
+    #[structopt(about = "Checks the mangas periodically until stopped, and records the new chapters.")]
+    Watch {
+        //The delay between two checks.
+        #[structopt(short = "i", long = "interval", parse(try_from_str = parse_duration),
+        help="The delay between two checks, e.g. 2h, 30m or 1h30m. Defaults to 2h.")]
+        interval: Option<Duration>,
+
+        //The maximum random delay added to the interval, so that the checks don't happen at fixed times.
+        #[structopt(long = "jitter", parse(try_from_str = parse_duration),
+        help="The maximum random delay added to the interval. Defaults to a tenth of the interval.")]
+        jitter: Option<Duration>,
+
+        //Checks each manga at its own pace, from its release cadence.
+        #[structopt(long = "adaptive",
+        help="Check each manga at its own pace, from its release cadence: the weekly and monthly ones less often until their next release is close.")]
+        adaptive: bool,
+
+        #[structopt(long = "on-new", help = ON_NEW_HELP)]
+        on_new: Option<String>,
+
+        #[structopt(long = "digest", possible_values = &["daily", "weekly"],
+        help="Send an email digest of the new chapters at each period: daily or weekly.")]
+        digest: Option<DigestPeriod>,
+    },
+
+    #[structopt(about = "Sends an email listing the new chapters of the period.")]
+    EmailDigest {
+        #[structopt(long = "digest", possible_values = &["daily", "weekly"],
+        help="The period of the digest: daily or weekly. Defaults to daily.")]
+        digest: Option<DigestPeriod>,
+    },
+
+    #[structopt(about = "Writes the feed of the latest chapters, or serves it over HTTP.")]
+    Feed {
+        #[structopt(possible_values = &["atom", "rss"], help="The format of the feed. Defaults to atom.")]
+        feed_format: Option<FeedFormat>,
+
+        #[structopt(short = "e", long = "external", parse(from_os_str),
+        help="The file to write the feed to. Defaults to mangas.atom.xml or mangas.rss.xml next to the CSV file.")]
+        to: Option<PathBuf>,
+
+        //If set, the feed is served on this address instead of being written.
+        #[structopt(long = "bind", help="The address to serve the feed on, e.g. 127.0.0.1:8080, instead of writing it.")]
+        bind: Option<String>,
+    },
+
+    #[structopt(about = "Serves the library over HTTP, with a JSON API and a dashboard.")]
+    Serve {
+        #[structopt(long = "bind", help="The address to listen to. Defaults to 127.0.0.1:8080.")]
+        bind: Option<String>,
+
+        #[structopt(long = "on-new", help = ON_NEW_HELP)]
+        on_new: Option<String>,
+    },
+
+    #[structopt(about = "Shows the library in a full-screen terminal UI.")]
+    Tui,
+
+    #[structopt(about = "Manages the profiles, each with its own library and settings.")]
+    Profile(ProfileCommand),
+
+    #[structopt(about = "Moves the library and the config file from the executable's folder to the data and config folders.")]
+    Migrate,
+
+    #[structopt(about = "Syncs the library with a git remote, e.g. to share it between computers. Set it up first with sync init.")]
+    Sync {
+        #[structopt(subcommand)]
+        action: Option<SyncCommand>,
+    },
+
+    #[structopt(about = "Prints the completion script of a shell, e.g. source <(manga_updater completions bash).")]
+    Completions {
+        #[structopt(possible_values = &["bash", "zsh", "fish"], help="The shell to complete.")]
+        shell: Shell,
+    },
+
+    //Used by the completion scripts to complete the entries of the library.
+    #[structopt(setting = AppSettings::Hidden)]
+    CompleteEntries,
+
+    //Used by git to merge two versions of a synced library.
+    #[structopt(setting = AppSettings::Hidden)]
+    MergeDriver {
+        #[structopt(parse(from_os_str))]
+        base: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        ours: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        theirs: PathBuf,
+    },
 }
 
+// The actions on the reading queue.
+#[derive(Debug, StructOpt)]
+enum QueueCommand {
+    #[structopt(about = "Opens the first chapter of the queue, and marks it as read.")]
+    Next,
+
+    #[structopt(about = "Marks the first chapter of the queue as read, without opening it.")]
+    Pop,
+
+    #[structopt(about = "Puts the series of the first chapter at the back of the queue, until this chapter is read.")]
+    Skip,
+}
+
+// The commands setting up the sync.
+#[derive(Debug, StructOpt)]
+enum SyncCommand {
+    #[structopt(about = "Keeps the library in a git repository pushed to the remote. If the remote already has a library, both are merged.")]
+    Init {
+        #[structopt(help="The URL or the path of the remote repository.")]
+        remote: String,
+
+        #[structopt(long = "force", help="Replaces the remote the library is already synced with.")]
+        force: bool,
+    },
+}
+
+// The commands managing the profiles.
+#[derive(Debug, StructOpt)]
+enum ProfileCommand {
+    #[structopt(about = "Creates a profile, with an empty library.")]
+    Create {
+        #[structopt(help="The name of the profile. Only letters, digits, - and _ are allowed.")]
+        name: String,
+    },
+
+    #[structopt(about = "Lists the profiles, along with the number of mangas in their libraries.")]
+    List,
+
+    #[structopt(about = "Deletes a profile, with its library and its settings.")]
+    Delete {
+        #[structopt(help="The name of the profile.")]
+        name: String,
+
+        #[structopt(short="y", long="yes", help="Do not ask for a confirmation.")]
+        yes: bool,
+    },
+
+    #[structopt(about = "Copies a profile, with its library and its settings, to a new profile.")]
+    Copy {
+        #[structopt(help="The profile to copy. Use default for the library used without a profile.")]
+        from: String,
+
+        #[structopt(help="The name of the new profile.")]
+        to: String,
+    },
+}
+
+/// The help of the --on-new option, shared by the commands announcing new chapters.
+const ON_NEW_HELP: &str = "A shell command to run for each new chapter. The chapter is given in the MANGA_TITLE, MANGA_URL, CHAPTER_NUM, CHAPTER_TITLE, CHAPTER_URL and PREVIOUS_CHAPTER_NUM environment variables.";
+
 /// Entry point of the application.
-/// Matches the argument given at the start, and redirect to the correct command.
+/// Matches the command given at the start, and redirect to the correct function.
+/// The program exits with the code returned by the command (See [`crate::output::EXIT_SUCCESS`] and the following constants).
 #[tokio::main]
 async fn main() {
     let args = Cli::from_args();
-    match args.command.as_str() {
-        "list" => list(args.path, args.new, args.no_update, args.verbose).await,
-        "init" => init(args.path),
-        "add" => add(args.path, args.argument, args.verbose).await,
-        "update" => update(args.path, args.argument, args.verbose).await,
-        "export" => export(args.path, args.external_file),
-        "import" => import(args.external_file, args.path, args.overwrite, args.verbose),
-        "remove" => remove(args.path, args.argument, args.verbose),
-        "open" => open(args.path, args.argument, args.direct, args.verbose).await,
-        "unread" => unread(args.path, args.argument, args.verbose),
-        "undo" => undo(args.path, args.verbose),
-        _ => println!("Argument out of range. Try running --h or -h.")
+    let profile = args.profile.filter(|name| name != DEFAULT_PROFILE);
+    let mut config = match load_config(&args.config, profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => process::exit(usage_error("config", &e.to_string(), args.format.unwrap_or_default())),
+    };
+    let format = args.format.or(config.defaults.format).unwrap_or_default();
+    // The verbose messages are printed to stdout, and would break the machine-readable output.
+    let verbose = (args.verbose || config.defaults.verbose) && format.is_table();
+    match config.colours {
+        Colours::Auto => {}
+        Colours::Always => set_override(true),
+        Colours::Never => set_override(false),
     }
-    
-
+    configure_client(ClientSettings {
+        user_agent: config.user_agent.clone(),
+        concurrency: config.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+        search_url: config.search_url.clone(),
+        rate_limit: config.rate_limit,
+    });
+    let command = args.command.unwrap_or(Command::List { new: false, no_update: false, no_prompt: false, on_new: None });
+    let profile_library = match &profile {
+        Some(name) if !matches!(command, Command::Profile(_)) => match check_profile_name(name) {
+            Ok(()) if profile_library_path(name).exists() => Some(profile_library_path(name)),
+            Ok(()) => process::exit(usage_error("profile", &format!("The profile {} doesn't exist. Create it with: profile create {}", name, name), format)),
+            Err(e) => process::exit(usage_error("profile", &e.to_string(), format)),
+        },
+        _ => None,
+    };
+    let path = args.path.or(profile_library).or_else(|| config.library.clone());
+    if path.is_none() && format.is_table() && !matches!(command, Command::Migrate | Command::CompleteEntries | Command::MergeDriver { .. }) && legacy_files().is_ok_and(|files| !files.is_empty()) {
+        eprintln!("The files of a previous version are still next to the executable. Run the migrate command to move them to the data and config folders.");
+    }
+    if let Command::List { on_new: Some(command), .. }
+    | Command::Watch { on_new: Some(command), .. }
+    | Command::Serve { on_new: Some(command), .. } = &command {
+        config.notifiers.push(NotifierConfig::Shell { command: command.clone() });
+    }
+    let defaults = &config.defaults;
+    let code = match command {
+        Command::List { new, no_update, no_prompt, .. } => list(path, new || defaults.new, no_update, no_prompt || defaults.no_prompt, config.notifiers, verbose, format).await,
+        Command::Init => init(path, format),
+        Command::Add { url, from_file } => add(path, url, from_file, verbose, format).await,
+        Command::Update { target } => update(path, target, verbose, format).await,
+        Command::Export { to, output, export_format, statuses } => export(path, to, output, export_format, statuses, verbose, format).await,
+        Command::Import { from, overwrite, merge, dry_run, yes } => {
+            let mode = match merge {
+                Some(strategy) => ImportMode::Merge(strategy),
+                None if overwrite => ImportMode::Overwrite,
+                None => ImportMode::Append,
+            };
+            import(from, path, mode, dry_run, yes, verbose, format).await
+        }
+        Command::Review => review(path, verbose, format).await,
+        Command::Remove { target } => remove(path, target, verbose, format),
+        Command::Download { target, from, to } => download(path, target, from, to, config.download_dir.clone(), verbose, format).await,
+        Command::Package { target, epub, from, to, downscale } => package(path, target, from, to, epub, downscale, config.download_dir.clone(), verbose, format).await,
+        Command::Doctor { fix } => doctor(path, fix, verbose, format).await,
+        Command::Info { target } => info(path, target, verbose, format).await,
+        Command::Open { target, direct, next } => open(path, target, direct || defaults.direct, next, verbose, format).await,
+        Command::Queue { sort, action } => {
+            let action = match action {
+                Some(QueueCommand::Next) => QueueAction::Next,
+                Some(QueueCommand::Pop) => QueueAction::Pop,
+                Some(QueueCommand::Skip) => QueueAction::Skip,
+                None => QueueAction::Show,
+            };
+            queue(path, sort, action, verbose, format).await
+        }
+        Command::Stats { stale_months, sparkline } => stats(path, stale_months, sparkline, verbose, format).await,
+        Command::Unread { line } => unread(path, line, verbose, format),
+        Command::Undo => undo(path, verbose, format),
+        Command::Watch { interval, jitter, adaptive, digest, .. } => watch(path, interval, jitter, adaptive, config, digest, verbose, format).await,
+        Command::EmailDigest { digest } => email_digest(path, config.email, digest, format).await,
+        Command::Feed { feed_format, to, bind } => feed(path, feed_format, to, bind, format).await,
+        Command::Serve { bind, .. } => serve(path, bind, config.notifiers, verbose, format).await,
+        Command::Tui => tui(path, format).await,
+        Command::Profile(ProfileCommand::Create { name }) => profile_create(name, format),
+        Command::Profile(ProfileCommand::List) => profile_list(profile, format),
+        Command::Profile(ProfileCommand::Delete { name, yes }) => profile_delete(name, yes, format),
+        Command::Profile(ProfileCommand::Copy { from, to }) => profile_copy(from, to, format),
+        Command::Migrate => migrate(verbose, format),
+        Command::Sync { action: Some(SyncCommand::Init { remote, force }) } => sync_init(path, remote, force, verbose, format),
+        Command::Sync { action: None } => sync(path, verbose, format),
+        Command::MergeDriver { base, ours, theirs } => merge_driver(base, ours, theirs),
+        Command::Completions { shell } => completions(Cli::clap(), shell),
+        Command::CompleteEntries => complete_entries(path),
+    };
+    process::exit(code)
 }
@@ -0,0 +1,299 @@
+use crate::models::CSVLine;
+use crate::urls::same_manga;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the conflicts of a merge are resolved, i.e. the entries changed on both sides since the last merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep the version with the highest chapter read. A removed entry loses against a changed one.
+    HighestChapter,
+    /// Keep the version of the file modified last.
+    NewestEdit,
+    /// Keep the version of the imported file.
+    Source,
+    /// Keep the version of the library.
+    Target,
+    /// Ask the user for each conflict.
+    Interactive,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "prefer-highest-chapter" => Ok(MergeStrategy::HighestChapter),
+            "prefer-newest-edit" => Ok(MergeStrategy::NewestEdit),
+            "prefer-source" => Ok(MergeStrategy::Source),
+            "prefer-target" => Ok(MergeStrategy::Target),
+            "interactive" => Ok(MergeStrategy::Interactive),
+            other => Err(format!(
+                "Unknown merge strategy {}. Available strategies are: prefer-highest-chapter, prefer-newest-edit, prefer-source, prefer-target, interactive.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MergeStrategy::HighestChapter => "prefer-highest-chapter",
+            MergeStrategy::NewestEdit => "prefer-newest-edit",
+            MergeStrategy::Source => "prefer-source",
+            MergeStrategy::Target => "prefer-target",
+            MergeStrategy::Interactive => "interactive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The side of a merge whose version of an entry is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    /// The imported file.
+    Source,
+    /// The library.
+    Target,
+}
+
+/// An entry changed on both sides since the last merge, in different ways.
+/// A missing version means the entry has been removed on that side.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub url: String,
+    pub title: String,
+    pub base: Option<CSVLine>,
+    pub source: Option<CSVLine>,
+    pub target: Option<CSVLine>,
+    /// The side kept once the conflict has been resolved.
+    pub kept: Side,
+}
+
+impl Conflict {
+    /// Gives the version of the entry kept by the merge, None if it is removed.
+    fn kept_line(&self) -> Option<CSVLine> {
+        match self.kept {
+            Side::Source => self.source.clone(),
+            Side::Target => self.target.clone(),
+        }
+    }
+}
+
+/// The result of a merge.
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    /// The lines of the merged library.
+    pub lines: Vec<CSVLine>,
+    /// The conflicts, along with the side kept for each of them.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merges the imported lines into the library, entry by entry, using the base to tell which side changed an entry.
+/// The base is the imported file as it was at the last merge: an entry that is still the same in the imported file hasn't changed there, so the library's version is kept, and the other way around.
+/// Without a base, nothing can be told removed, so the entries of both sides are kept, and every entry that differs is a conflict.
+/// The entries are matched by the canonical form of their URL (See [`crate::urls::canonical_url`]).
+/// The lines of the library keep their order, and the new ones are appended in the order of the imported file.
+/// # Arguments:
+/// * `base`: the lines of the imported file at the last merge, if any.
+/// * `source`: the lines of the imported file.
+/// * `target`: the lines of the library.
+/// * `resolve`: picks the side to keep for a conflict.
+/// # Returns:
+/// The merged lines, and the conflicts that were resolved.
+pub fn merge_lines(
+    base: Option<&[CSVLine]>,
+    source: &[CSVLine],
+    target: &[CSVLine],
+    mut resolve: impl FnMut(&Conflict) -> Side,
+) -> MergeResult {
+    let find = |lines: &[CSVLine], url: &str| {
+        lines
+            .iter()
+            .find(|line| same_manga(&line.url, url))
+            .cloned()
+    };
+    let mut urls: Vec<&str> = target.iter().map(|line| line.url.as_str()).collect();
+    urls.extend(
+        source
+            .iter()
+            .map(|line| line.url.as_str())
+            .filter(|url| !target.iter().any(|line| same_manga(&line.url, url))),
+    );
+    let mut result = MergeResult::default();
+    for url in urls {
+        let target_line = find(target, url);
+        let source_line = find(source, url);
+        let merged = match base {
+            Some(base) => merge_entry(find(base, url), source_line, target_line),
+            None => match (source_line, target_line) {
+                (Some(source), Some(target)) if source != target => {
+                    EntryMerge::Conflict(None, Some(source), Some(target))
+                }
+                (source, target) => EntryMerge::Merged(target.or(source)),
+            },
+        };
+        match merged {
+            EntryMerge::Merged(line) => result.lines.extend(line),
+            EntryMerge::Conflict(base, source, target) => {
+                let mut conflict = Conflict {
+                    url: url.to_owned(),
+                    title: target
+                        .as_ref()
+                        .or(source.as_ref())
+                        .map(|line| line.title.clone())
+                        .unwrap_or_default(),
+                    base,
+                    source,
+                    target,
+                    kept: Side::Target,
+                };
+                conflict.kept = resolve(&conflict);
+                result.lines.extend(conflict.kept_line());
+                result.conflicts.push(conflict);
+            }
+        }
+    }
+    result
+}
+
+/// The outcome of the merge of an entry.
+enum EntryMerge {
+    /// The merged entry, None if it is removed.
+    Merged(Option<CSVLine>),
+    /// Both sides changed the entry: the base, the source and the target.
+    Conflict(Option<CSVLine>, Option<CSVLine>, Option<CSVLine>),
+}
+
+/// Merges the versions of an entry.
+fn merge_entry(
+    base: Option<CSVLine>,
+    source: Option<CSVLine>,
+    target: Option<CSVLine>,
+) -> EntryMerge {
+    if source == target || source == base {
+        EntryMerge::Merged(target)
+    } else if target == base {
+        EntryMerge::Merged(source)
+    } else {
+        EntryMerge::Conflict(base, source, target)
+    }
+}
+
+/// Resolves a conflict without asking the user.
+/// # Arguments:
+/// * `strategy`: the strategy of the merge. The interactive one keeps the library's version.
+/// * `conflict`: the conflict to resolve.
+/// * `source_is_newer`: whether the imported file has been modified after the library, for [`MergeStrategy::NewestEdit`].
+/// # Returns:
+/// The side to keep.
+pub fn resolve_conflict(
+    strategy: MergeStrategy,
+    conflict: &Conflict,
+    source_is_newer: bool,
+) -> Side {
+    match strategy {
+        MergeStrategy::HighestChapter => {
+            let chapter = |line: &Option<CSVLine>| line.as_ref().map(|line| line.last_chapter_num);
+            match (chapter(&conflict.source), chapter(&conflict.target)) {
+                (Some(source), Some(target)) if source > target => Side::Source,
+                (Some(_), None) => Side::Source,
+                _ => Side::Target,
+            }
+        }
+        MergeStrategy::NewestEdit if source_is_newer => Side::Source,
+        MergeStrategy::Source => Side::Source,
+        _ => Side::Target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::line;
+
+    #[test]
+    fn changes_of_both_sides_are_merged() {
+        let base = vec![
+            line("same", 1.0),
+            line("source", 1.0),
+            line("target", 1.0),
+            line("both", 1.0),
+            line("removed", 1.0),
+            line("edited", 1.0),
+        ];
+        let source = vec![
+            line("same", 1.0),
+            line("source", 2.0),
+            line("target", 1.0),
+            line("both", 5.0),
+            line("edited", 3.0),
+            line("new", 1.0),
+        ];
+        let target = vec![
+            line("same", 1.0),
+            line("source", 1.0),
+            line("target", 2.0),
+            line("both", 4.0),
+            line("removed", 1.0),
+            line("local", 1.0),
+        ];
+        let result = merge_lines(Some(&base), &source, &target, |conflict| {
+            resolve_conflict(MergeStrategy::HighestChapter, conflict, false)
+        });
+        assert_eq!(
+            result.lines,
+            vec![
+                line("same", 1.0),
+                line("source", 2.0),
+                line("target", 2.0),
+                line("both", 5.0),
+                line("local", 1.0),
+                line("edited", 3.0),
+                line("new", 1.0)
+            ]
+        );
+        assert_eq!(result.conflicts.len(), 2);
+        assert_eq!(result.conflicts[0].url, "both");
+        assert_eq!(result.conflicts[0].kept, Side::Source);
+        // Removed from the library but changed in the imported file.
+        assert_eq!(result.conflicts[1].url, "edited");
+        assert_eq!(result.conflicts[1].target, None);
+        assert_eq!(result.conflicts[1].kept, Side::Source);
+    }
+
+    #[test]
+    fn without_base_every_difference_is_a_conflict() {
+        let source = vec![line("a", 3.0), line("b", 1.0)];
+        let target = vec![line("a", 2.0), line("c", 1.0)];
+        let result = merge_lines(None, &source, &target, |conflict| {
+            resolve_conflict(MergeStrategy::Target, conflict, true)
+        });
+        assert_eq!(
+            result.lines,
+            vec![line("a", 2.0), line("c", 1.0), line("b", 1.0)]
+        );
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(
+            resolve_conflict(MergeStrategy::NewestEdit, conflict, true),
+            Side::Source
+        );
+        assert_eq!(
+            resolve_conflict(MergeStrategy::NewestEdit, conflict, false),
+            Side::Target
+        );
+        assert_eq!(
+            resolve_conflict(MergeStrategy::Source, conflict, false),
+            Side::Source
+        );
+        assert_eq!(
+            resolve_conflict(MergeStrategy::HighestChapter, conflict, false),
+            Side::Source
+        );
+        assert_eq!("prefer-newest-edit".parse(), Ok(MergeStrategy::NewestEdit));
+    }
+}
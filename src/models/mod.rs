@@ -1,23 +1,217 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
 /// Represents a manga chapter.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MangaChapter {
     pub manga_title: String,
     pub url: String,
     pub chapter_title: String,
     pub num: f32,
+    pub cover: Option<String>,
+}
+
+/// The reading status of a manga, set by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingStatus {
+    #[default]
+    Reading,
+    PlanToRead,
+    OnHold,
+    Completed,
+    Dropped,
+}
+
+impl ReadingStatus {
+    /// All the statuses, in the order they are cycled through.
+    pub const ALL: [ReadingStatus; 5] = [
+        ReadingStatus::Reading,
+        ReadingStatus::PlanToRead,
+        ReadingStatus::OnHold,
+        ReadingStatus::Completed,
+        ReadingStatus::Dropped,
+    ];
+
+    /// The status following this one, going back to the first after the last.
+    pub fn next(&self) -> ReadingStatus {
+        let index = ReadingStatus::ALL
+            .iter()
+            .position(|status| status == self)
+            .unwrap_or(0);
+        ReadingStatus::ALL[(index + 1) % ReadingStatus::ALL.len()]
+    }
+}
+
+impl FromStr for ReadingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "reading" => Ok(ReadingStatus::Reading),
+            "plan_to_read" => Ok(ReadingStatus::PlanToRead),
+            "on_hold" => Ok(ReadingStatus::OnHold),
+            "completed" => Ok(ReadingStatus::Completed),
+            "dropped" => Ok(ReadingStatus::Dropped),
+            other => Err(format!(
+                "Unknown status {}. Available statuses are: reading, plan_to_read, on_hold, completed, dropped.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReadingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadingStatus::Reading => write!(f, "reading"),
+            ReadingStatus::PlanToRead => write!(f, "plan_to_read"),
+            ReadingStatus::OnHold => write!(f, "on_hold"),
+            ReadingStatus::Completed => write!(f, "completed"),
+            ReadingStatus::Dropped => write!(f, "dropped"),
+        }
+    }
 }
 
 /// Represents a line of the CSV used by the program.
-#[derive(Debug, PartialEq, Clone)]
+/// The status and score columns have been added later on: the files without them are read as if every manga was being read, without a score.
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub struct CSVLine {
     pub url: String,
     pub last_chapter_num: f32,
     pub title: String,
+    pub status: ReadingStatus,
+    /// The score given by the user, out of 10.
+    pub score: Option<f32>,
 }
 
 /// Represents a combination of a CSVLine and a MangaChapter, to use where necessary.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LineChapter {
     pub line: CSVLine,
     pub chapter: MangaChapter,
 }
+
+impl LineChapter {
+    /// Finds the link to the first chapter after the last one read.
+    /// The chapter links end with their number (e.g. `.../chapter-74`), so the link is built from the last chapter's one.
+    /// # Returns:
+    /// The link to the next chapter, or None if there are no new chapters.
+    pub fn next_chapter_url(&self) -> Option<String> {
+        if self.chapter.num <= self.line.last_chapter_num {
+            return None;
+        }
+        let next_num = self.line.last_chapter_num.floor() + 1f32;
+        if next_num >= self.chapter.num {
+            return Some(self.chapter.url.clone());
+        }
+        self.chapter
+            .url
+            .rsplit_once('-')
+            .map(|(prefix, _)| format!("{}-{}", prefix, next_num))
+    }
+}
+
+/// Represents a chapter release detected by the program, as stored in the releases file.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Release {
+    pub detected_at: DateTime<Utc>,
+    pub manga_url: String,
+    pub manga_title: String,
+    pub chapter_num: f32,
+    pub chapter_title: String,
+    pub chapter_url: String,
+}
+
+/// A chapter of a manga, as listed in the manga's page.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ChapterLink {
+    pub num: f32,
+    pub title: String,
+    pub url: String,
+}
+
+/// A manga found by searching its title on the website.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+}
+
+/// A manga of a tracker's list (MyAnimeList, AniList), which has a title but no URL.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TrackerEntry {
+    pub title: String,
+    pub read_chapters: f32,
+    pub status: ReadingStatus,
+    /// The score given by the user, out of 10.
+    pub score: Option<f32>,
+}
+
+impl TrackerEntry {
+    /// Turns the entry into a line of the library, once its URL is known.
+    pub fn into_line(self, url: String, title: Option<String>) -> CSVLine {
+        CSVLine {
+            url,
+            last_chapter_num: self.read_chapters,
+            title: title.unwrap_or(self.title),
+            status: self.status,
+            score: self.score,
+        }
+    }
+}
+
+/// A tracker's entry whose URL couldn't be found, waiting for the user to pick it among the search results.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub entry: TrackerEntry,
+    pub candidates: Vec<SearchResult>,
+}
+
+/// Represents a reading progress made on a manga, as stored in the history file.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ReadingEvent {
+    pub read_at: DateTime<Utc>,
+    pub url: String,
+    pub title: String,
+    pub previous_chapter_num: f32,
+    pub chapter_num: f32,
+}
+
+impl ReadingEvent {
+    /// Counts the chapters read, the half chapters counting for one.
+    pub fn chapters_read(&self) -> usize {
+        if self.chapter_num <= self.previous_chapter_num {
+            return 0;
+        }
+        (self.chapter_num.floor() - self.previous_chapter_num.floor()).max(1.0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{line, manga_chapter};
+
+    fn line_chapter(last_read: f32, latest: f32) -> LineChapter {
+        LineChapter {
+            line: line("http://localhost/manga", last_read),
+            chapter: manga_chapter("http://localhost/manga", latest),
+        }
+    }
+
+    #[test]
+    fn next_chapter_follows_the_last_read() {
+        assert_eq!(
+            line_chapter(70f32, 74f32).next_chapter_url().as_deref(),
+            Some("http://localhost/manga/chapter-71")
+        );
+        assert_eq!(
+            line_chapter(73.5, 74f32).next_chapter_url().as_deref(),
+            Some("http://localhost/manga/chapter-74")
+        );
+        assert_eq!(line_chapter(74f32, 74f32).next_chapter_url(), None);
+    }
+}
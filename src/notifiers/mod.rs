@@ -0,0 +1,414 @@
+/// Shell command notifier
+mod shell;
+
+use crate::file_ops::releases::record_releases;
+use crate::models::Release;
+use crate::notifiers::shell::run_command;
+use crate::output::{ErrorReport, NewChapterReport};
+use chrono::Utc;
+use futures::future::join_all;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// The message sent by the notifiers when none is configured.
+const DEFAULT_MESSAGE: &str = "New chapter of {manga_title}: {chapter_title} ({chapter_url})";
+
+/// A notifier, as configured in the `[[notifiers]]` sections of the config file.
+/// The `message` and `body` settings are templates, in which `{manga_title}`, `{manga_url}`, `{chapter_title}`, `{chapter_url}`, `{chapter_num}` and `{previous_num}` are replaced by the chapter data.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NotifierConfig {
+    /// Posts a JSON body to any URL. Without a body template, the chapter is sent as is.
+    /// The values are escaped to be used inside JSON strings of the template.
+    Webhook {
+        url: String,
+        body: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Posts a message to a Discord webhook.
+    Discord {
+        url: String,
+        message: Option<String>,
+    },
+    /// Posts a message to a Slack-compatible incoming webhook (Slack, Mattermost, Rocket.Chat...).
+    Slack {
+        url: String,
+        message: Option<String>,
+    },
+    /// Publishes a message to a ntfy topic.
+    Ntfy {
+        #[serde(default = "default_ntfy_server")]
+        server: String,
+        topic: String,
+        token: Option<String>,
+        message: Option<String>,
+    },
+    /// Pushes a message to a Gotify server.
+    Gotify {
+        server: String,
+        token: String,
+        priority: Option<u8>,
+        message: Option<String>,
+    },
+    /// Runs a shell command, with the chapter data in environment variables (See [`shell::run_command`]).
+    Shell { command: String },
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_owned()
+}
+
+/// Records the new chapters in the releases file, and calls every notifier for the ones that have never been seen before.
+/// As the releases file is used to remember the chapters, each chapter is announced only once, whatever the command finding it.
+/// # Arguments:
+/// * `path`: the optional path to the CSV file, next to which the releases are stored.
+/// * `notifiers`: the notifiers to call.
+/// * `client`: the client to send the requests with.
+/// * `found`: the chapters newer than the ones stored in the CSV.
+/// # Returns:
+/// The chapters that have been announced, along with the notifiers errors.
+/// An I/O Error is returned if the releases couldn't be recorded, in which case nobody is notified.
+pub async fn announce_new_chapters(
+    path: &Option<PathBuf>,
+    notifiers: &[NotifierConfig],
+    client: &Client,
+    found: Vec<NewChapterReport>,
+) -> Result<(Vec<NewChapterReport>, Vec<ErrorReport>), io::Error> {
+    let now = Utc::now();
+    let releases: Vec<Release> = found
+        .iter()
+        .map(|chapter| Release {
+            detected_at: now,
+            manga_url: chapter.manga_url.clone(),
+            manga_title: chapter.manga_title.clone(),
+            chapter_num: chapter.num,
+            chapter_title: chapter.chapter_title.clone(),
+            chapter_url: chapter.chapter_url.clone(),
+        })
+        .collect();
+    let recorded = record_releases(path, releases)?;
+    let announced: Vec<NewChapterReport> = found
+        .into_iter()
+        .filter(|chapter| {
+            recorded
+                .iter()
+                .any(|r| r.manga_url == chapter.manga_url && r.chapter_num == chapter.num)
+        })
+        .collect();
+
+    let notifications: Vec<_> = announced
+        .iter()
+        .flat_map(|chapter| {
+            notifiers
+                .iter()
+                .map(move |notifier| notify(notifier, client, chapter))
+        })
+        .collect();
+    let errors: Vec<ErrorReport> = join_all(notifications)
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+    Ok((announced, errors))
+}
+
+/// Sends a notification about a chapter.
+/// # Arguments:
+/// * `notifier`: the notifier to use.
+/// * `client`: the client to send the requests with.
+/// * `chapter`: the new chapter.
+/// # Returns:
+/// An error concerning the manga if the notification couldn't be sent.
+async fn notify(
+    notifier: &NotifierConfig,
+    client: &Client,
+    chapter: &NewChapterReport,
+) -> Result<(), ErrorReport> {
+    let message = |template: &Option<String>| {
+        render(
+            template.as_deref().unwrap_or(DEFAULT_MESSAGE),
+            chapter,
+            false,
+        )
+    };
+    let result = match notifier {
+        NotifierConfig::Webhook { url, body, headers } => {
+            let body = match body {
+                Some(template) => render(template, chapter, true),
+                None => serde_json::to_string(chapter).unwrap_or_default(),
+            };
+            let mut request = client.post(url).header(CONTENT_TYPE, "application/json");
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            send(request.body(body)).await
+        }
+        NotifierConfig::Discord { url, message: msg } => {
+            post_json(client, url, json!({ "content": message(msg) })).await
+        }
+        NotifierConfig::Slack { url, message: msg } => {
+            post_json(client, url, json!({ "text": message(msg) })).await
+        }
+        NotifierConfig::Ntfy {
+            server,
+            topic,
+            token,
+            message: msg,
+        } => {
+            let body = json!({
+                "topic": topic,
+                "title": chapter.manga_title,
+                "message": message(msg),
+                "click": chapter.chapter_url,
+            });
+            let mut request = client
+                .post(server.trim_end_matches('/'))
+                .header(CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            send(request.body(body.to_string())).await
+        }
+        NotifierConfig::Gotify {
+            server,
+            token,
+            priority,
+            message: msg,
+        } => {
+            let url = format!("{}/message", server.trim_end_matches('/'));
+            let body = json!({
+                "title": chapter.manga_title,
+                "message": message(msg),
+                "priority": priority.unwrap_or(5),
+                "extras": { "client::notification": { "click": { "url": chapter.chapter_url } } },
+            });
+            let request = client
+                .post(url)
+                .header("X-Gotify-Key", token)
+                .header(CONTENT_TYPE, "application/json");
+            send(request.body(body.to_string())).await
+        }
+        NotifierConfig::Shell { command } => run_command(command, chapter).await,
+    };
+    result.map_err(|reason| {
+        ErrorReport::new(
+            Some(&chapter.manga_url),
+            format!("Notification failed: {}", reason),
+        )
+    })
+}
+
+/// Posts a JSON value to the URL.
+async fn post_json(client: &Client, url: &str, body: serde_json::Value) -> Result<(), String> {
+    let request = client
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    send(request).await
+}
+
+/// Sends the request, and checks that the server accepted it.
+async fn send(request: reqwest::RequestBuilder) -> Result<(), String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("the server answered {}", response.status()))
+    }
+}
+
+/// Replaces the placeholders of the template with the chapter data.
+/// # Arguments:
+/// * `template`: the template to fill.
+/// * `chapter`: the chapter data.
+/// * `escape_json`: if true, the values are escaped to be put inside JSON strings.
+/// # Returns:
+/// The filled template.
+fn render(template: &str, chapter: &NewChapterReport, escape_json: bool) -> String {
+    let escape = |value: &str| {
+        if escape_json {
+            let quoted = serde_json::to_string(value).unwrap_or_default();
+            quoted[1..quoted.len() - 1].to_owned()
+        } else {
+            value.to_owned()
+        }
+    };
+    template
+        .replace("{manga_title}", &escape(&chapter.manga_title))
+        .replace("{manga_url}", &escape(&chapter.manga_url))
+        .replace("{chapter_title}", &escape(&chapter.chapter_title))
+        .replace("{chapter_url}", &escape(&chapter.chapter_url))
+        .replace("{chapter_num}", &chapter.num.to_string())
+        .replace("{previous_num}", &chapter.previous_num.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_ops::releases::extract_releases_path;
+    use crate::test_utils::new_chapter;
+    use serial_test::serial;
+    use std::fs;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    /// A chapter whose title must be escaped.
+    fn chapter() -> NewChapterReport {
+        new_chapter("The \"Title\"")
+    }
+
+    /// Starts a local HTTP stand-in answering a single request with the given status.
+    /// # Returns:
+    /// The URL of the server, and a receiver for the raw request it got.
+    async fn stand_in(status: &'static str) -> (String, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request: Vec<u8> = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|l| {
+                            l.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(str::to_owned)
+                        })
+                        .and_then(|l| l.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || read == 0 {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let _ = sender.send(String::from_utf8_lossy(&request).to_string());
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn render_escapes_json() {
+        let rendered = render(
+            r#"{"text": "{manga_title} #{chapter_num}"}"#,
+            &chapter(),
+            true,
+        );
+        assert_eq!(rendered, r#"{"text": "The \"Title\" #2"}"#);
+        let rendered = render(DEFAULT_MESSAGE, &chapter(), false);
+        assert_eq!(
+            rendered,
+            "New chapter of The \"Title\": Chapter 2 (http://localhost/manga-1/chapter-2)"
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_sends_templated_body() {
+        let (url, request) = stand_in("200 OK").await;
+        let mut headers = HashMap::new();
+        headers.insert("X-Token".to_owned(), "secret".to_owned());
+        let notifier = NotifierConfig::Webhook {
+            url: format!("{}/hook", url),
+            body: Some(r#"{"chapter": {chapter_num}}"#.to_owned()),
+            headers,
+        };
+        notify(&notifier, &Client::new(), &chapter()).await.unwrap();
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.to_lowercase().contains("x-token: secret"));
+        assert!(request.ends_with(r#"{"chapter": 2}"#));
+    }
+
+    #[tokio::test]
+    async fn discord_and_slack_payloads() {
+        let (url, request) = stand_in("204 No Content").await;
+        let notifier = NotifierConfig::Discord {
+            url,
+            message: Some("{manga_title}".to_owned()),
+        };
+        notify(&notifier, &Client::new(), &chapter()).await.unwrap();
+        assert!(request
+            .await
+            .unwrap()
+            .ends_with(r#"{"content":"The \"Title\""}"#));
+
+        let (url, request) = stand_in("200 OK").await;
+        let notifier = NotifierConfig::Slack {
+            url,
+            message: Some("#{chapter_num}".to_owned()),
+        };
+        notify(&notifier, &Client::new(), &chapter()).await.unwrap();
+        assert!(request.await.unwrap().ends_with(r##"{"text":"#2"}"##));
+    }
+
+    #[tokio::test]
+    async fn ntfy_and_gotify_payloads() {
+        let (url, request) = stand_in("200 OK").await;
+        let notifier = NotifierConfig::Ntfy {
+            server: url,
+            topic: "mangas".to_owned(),
+            token: Some("tk".to_owned()),
+            message: None,
+        };
+        notify(&notifier, &Client::new(), &chapter()).await.unwrap();
+        let request = request.await.unwrap();
+        assert!(request.to_lowercase().contains("authorization: bearer tk"));
+        assert!(request.contains(r#""topic":"mangas""#));
+
+        let (url, request) = stand_in("200 OK").await;
+        let notifier = NotifierConfig::Gotify {
+            server: format!("{}/", url),
+            token: "app".to_owned(),
+            priority: None,
+            message: None,
+        };
+        notify(&notifier, &Client::new(), &chapter()).await.unwrap();
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /message"));
+        assert!(request.to_lowercase().contains("x-gotify-key: app"));
+        assert!(request.contains(r#""priority":5"#));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_reported() {
+        let (url, _request) = stand_in("500 Internal Server Error").await;
+        let notifier = NotifierConfig::Slack { url, message: None };
+        let error = notify(&notifier, &Client::new(), &chapter())
+            .await
+            .unwrap_err();
+        assert_eq!(error.url.as_deref(), Some("http://localhost/manga-1"));
+        assert!(error.reason.contains("500"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn chapters_are_announced_once() -> Result<(), io::Error> {
+        let path = Some(PathBuf::from("mangas.csv"));
+        let (announced, errors) =
+            announce_new_chapters(&path, &[], &Client::new(), vec![chapter()]).await?;
+        assert_eq!(announced.len(), 1);
+        assert!(errors.is_empty());
+        let (announced, _) =
+            announce_new_chapters(&path, &[], &Client::new(), vec![chapter()]).await?;
+        assert!(announced.is_empty());
+        fs::remove_file(extract_releases_path(&path))?;
+        Ok(())
+    }
+}
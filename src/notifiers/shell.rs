@@ -0,0 +1,61 @@
+use crate::output::NewChapterReport;
+use std::io;
+use tokio::process::Command;
+
+/// Runs a shell command for a new chapter.
+/// The chapter data is given through the environment variables `MANGA_TITLE`, `MANGA_URL`, `CHAPTER_NUM`, `CHAPTER_TITLE`, `CHAPTER_URL` and `PREVIOUS_CHAPTER_NUM`.
+/// The output of the command is redirected to stderr, so that it doesn't mix with the JSON output.
+/// # Arguments:
+/// * `command`: the command to run in a shell.
+/// * `chapter`: the new chapter.
+/// # Returns:
+/// An error message if the command couldn't be run or failed.
+pub async fn run_command(command: &str, chapter: &NewChapterReport) -> Result<(), String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .stdout(io::stderr())
+        .env("MANGA_TITLE", &chapter.manga_title)
+        .env("MANGA_URL", &chapter.manga_url)
+        .env("CHAPTER_NUM", chapter.num.to_string())
+        .env("CHAPTER_TITLE", &chapter.chapter_title)
+        .env("CHAPTER_URL", &chapter.chapter_url)
+        .env("PREVIOUS_CHAPTER_NUM", chapter.previous_num.to_string())
+        .status()
+        .await
+        .map_err(|e| format!("The command couldn't be run: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("The command failed with {}", status))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_utils::new_chapter;
+
+    #[tokio::test]
+    async fn command_gets_the_chapter() {
+        let result = run_command(
+            r#"test "$MANGA_TITLE" = "Title" && test "$CHAPTER_NUM" = "2""#,
+            &new_chapter("Title"),
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn failing_command_is_an_error() {
+        assert!(run_command("exit 3", &new_chapter("Title")).await.is_err());
+    }
+}
@@ -0,0 +1,519 @@
+use crate::cadence::ReleaseEstimate;
+use crate::doctor::Issue;
+use crate::merge::Conflict;
+use crate::queue::QueueItem;
+use crate::stats::LibraryStats;
+use crate::models::{CSVLine, LineChapter, ReadingStatus};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// The command went well. For `list`, it also means that there are no new chapters.
+pub const EXIT_SUCCESS: i32 = 0;
+/// The command couldn't be completed, e.g. the CSV couldn't be read or an argument is missing.
+pub const EXIT_FATAL: i32 = 1;
+/// Some mangas couldn't be fetched, but the others have been processed.
+pub const EXIT_PARTIAL_FAILURE: i32 = 2;
+/// The `list` command found new chapters.
+pub const EXIT_NEW_CHAPTERS: i32 = 10;
+
+/// The different formats the program can print its results in.
+/// `Table` is the default, human-readable and coloured output.
+/// `Json` prints a single JSON document once the command is over, while `Ndjson` prints one JSON object per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Tells if the output is meant for humans. If false, nothing but the report should be printed to stdout.
+    pub fn is_table(&self) -> bool {
+        *self == OutputFormat::Table
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            other => Err(format!(
+                "Unknown format {}. Available formats are: json, ndjson, table.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// A manga of the library, along with the last chapter found online if it could be fetched.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryReport {
+    pub position: usize,
+    pub url: String,
+    pub title: String,
+    pub last_chapter_num: f32,
+    pub status: ReadingStatus,
+    pub score: Option<f32>,
+    pub latest_chapter_num: Option<f32>,
+    pub latest_chapter_title: Option<String>,
+    pub latest_chapter_url: Option<String>,
+    pub next_chapter_url: Option<String>,
+    pub cover: Option<String>,
+    pub has_new: bool,
+    /// The release cadence of the manga, if enough releases have been recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseEstimate>,
+}
+
+impl EntryReport {
+    /// Creates a report for a line whose page hasn't been fetched.
+    pub fn from_line(position: usize, line: &CSVLine) -> EntryReport {
+        EntryReport {
+            position,
+            url: line.url.clone(),
+            title: line.title.clone(),
+            last_chapter_num: line.last_chapter_num,
+            status: line.status,
+            score: line.score,
+            latest_chapter_num: None,
+            latest_chapter_title: None,
+            latest_chapter_url: None,
+            next_chapter_url: None,
+            cover: None,
+            has_new: false,
+            release: None,
+        }
+    }
+
+    /// Creates a report for a line along with its last chapter online.
+    pub fn from_line_chapter(position: usize, line_chapter: &LineChapter) -> EntryReport {
+        EntryReport {
+            position,
+            url: line_chapter.line.url.clone(),
+            title: line_chapter.chapter.manga_title.clone(),
+            last_chapter_num: line_chapter.line.last_chapter_num,
+            status: line_chapter.line.status,
+            score: line_chapter.line.score,
+            latest_chapter_num: Some(line_chapter.chapter.num),
+            latest_chapter_title: Some(line_chapter.chapter.chapter_title.clone()),
+            latest_chapter_url: Some(line_chapter.chapter.url.clone()),
+            next_chapter_url: line_chapter.next_chapter_url(),
+            cover: line_chapter.chapter.cover.clone(),
+            has_new: line_chapter.chapter.num > line_chapter.line.last_chapter_num,
+            release: None,
+        }
+    }
+}
+
+/// A chapter that has been released since the last one stored in the library.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewChapterReport {
+    pub position: usize,
+    pub manga_url: String,
+    pub manga_title: String,
+    pub chapter_title: String,
+    pub chapter_url: String,
+    pub num: f32,
+    pub previous_num: f32,
+}
+
+impl NewChapterReport {
+    pub fn from_line_chapter(position: usize, line_chapter: &LineChapter) -> NewChapterReport {
+        NewChapterReport {
+            position,
+            manga_url: line_chapter.line.url.clone(),
+            manga_title: line_chapter.chapter.manga_title.clone(),
+            chapter_title: line_chapter.chapter.chapter_title.clone(),
+            chapter_url: line_chapter.chapter.url.clone(),
+            num: line_chapter.chapter.num,
+            previous_num: line_chapter.line.last_chapter_num,
+        }
+    }
+}
+
+/// An error that happened during the command. The URL is set when the error concerns a single manga.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub url: Option<String>,
+    pub reason: String,
+}
+
+impl ErrorReport {
+    pub fn new(url: Option<&str>, reason: impl ToString) -> ErrorReport {
+        ErrorReport {
+            url: url.map(str::to_owned),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// The kind of modification made to a library entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// A modification made to the library by the command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LibraryChange {
+    pub action: ChangeAction,
+    pub url: String,
+    pub title: String,
+    pub previous_chapter_num: Option<f32>,
+    pub chapter_num: Option<f32>,
+}
+
+impl LibraryChange {
+    /// Computes the changes needed to go from the `before` lines to the `after` lines.
+    /// Lines are matched on their URL: a line can be added, removed, or updated if its chapter or its title has changed.
+    /// # Arguments:
+    /// * `before`: the lines of the library before the command.
+    /// * `after`: the lines of the library after the command.
+    /// # Returns:
+    /// The list of changes, the removed lines first, then the updated and added ones in the order of `after`.
+    pub fn diff(before: &[CSVLine], after: &[CSVLine]) -> Vec<LibraryChange> {
+        let mut changes: Vec<LibraryChange> = before
+            .iter()
+            .filter(|old| !after.iter().any(|new| new.url == old.url))
+            .map(|old| LibraryChange {
+                action: ChangeAction::Removed,
+                url: old.url.clone(),
+                title: old.title.clone(),
+                previous_chapter_num: Some(old.last_chapter_num),
+                chapter_num: None,
+            })
+            .collect();
+        for new in after {
+            match before.iter().find(|old| old.url == new.url) {
+                Some(old) if old == new => {}
+                Some(old) => changes.push(LibraryChange {
+                    action: ChangeAction::Updated,
+                    url: new.url.clone(),
+                    title: new.title.clone(),
+                    previous_chapter_num: Some(old.last_chapter_num),
+                    chapter_num: Some(new.last_chapter_num),
+                }),
+                None => changes.push(LibraryChange {
+                    action: ChangeAction::Added,
+                    url: new.url.clone(),
+                    title: new.title.clone(),
+                    previous_chapter_num: None,
+                    chapter_num: Some(new.last_chapter_num),
+                }),
+            }
+        }
+        changes
+    }
+}
+
+/// A profile, with its own library and settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    pub name: String,
+    pub library: String,
+    pub entries: Option<usize>,
+    pub active: bool,
+}
+
+/// A chapter downloaded as an archive.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadReport {
+    pub title: String,
+    pub chapter_num: f32,
+    pub chapter_url: String,
+    pub file: String,
+    pub pages: usize,
+    /// True if the archive was already there, and nothing has been downloaded.
+    pub already_downloaded: bool,
+}
+
+/// Chapters packaged into a book.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageReport {
+    pub title: String,
+    pub file: String,
+    pub chapters: Vec<f32>,
+    pub pages: usize,
+    /// False if the first page is used as the cover, as the one of the manga couldn't be downloaded.
+    pub cover: bool,
+}
+
+/// What happened to a URL given to `add`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddOutcome {
+    Added,
+    /// The manga is already in the library, or has been given before.
+    Duplicate,
+    /// The URL isn't from a supported website.
+    Unsupported,
+    /// The page of the manga couldn't be fetched or scraped.
+    Failed,
+}
+
+impl fmt::Display for AddOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddOutcome::Added => write!(f, "added"),
+            AddOutcome::Duplicate => write!(f, "duplicate"),
+            AddOutcome::Unsupported => write!(f, "unsupported"),
+            AddOutcome::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A URL found in the text given to `add`, along with what happened to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdditionReport {
+    /// The URL as found in the text.
+    pub url: String,
+    pub outcome: AddOutcome,
+    /// The title of the manga, once its page has been fetched.
+    pub title: Option<String>,
+    /// Why the manga couldn't be added, if it failed.
+    pub reason: Option<String>,
+}
+
+/// The structured result of a command, printed when a machine-readable format is requested.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandReport {
+    pub command: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<EntryReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub new_chapters: Vec<NewChapterReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<LibraryChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ProfileReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub downloads: Vec<DownloadReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<PackageReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub queue: Vec<QueueItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<LibraryStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<Issue>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub additions: Vec<AdditionReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A single line of the NDJSON output.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Entry(&'a EntryReport),
+    NewChapter(&'a NewChapterReport),
+    Error(&'a ErrorReport),
+    Change(&'a LibraryChange),
+    Profile(&'a ProfileReport),
+    Conflict(&'a Conflict),
+    Download(&'a DownloadReport),
+    Package(&'a PackageReport),
+    QueueItem(&'a QueueItem),
+    Stats(&'a LibraryStats),
+    Issue(&'a Issue),
+    Addition(&'a AdditionReport),
+    Summary {
+        command: &'a str,
+        entries: usize,
+        new_chapters: usize,
+        errors: usize,
+        changes: usize,
+        message: &'a Option<String>,
+    },
+}
+
+impl CommandReport {
+    pub fn new(command: &str) -> CommandReport {
+        CommandReport {
+            command: command.to_owned(),
+            entries: Vec::new(),
+            new_chapters: Vec::new(),
+            errors: Vec::new(),
+            changes: Vec::new(),
+            profiles: Vec::new(),
+            conflicts: Vec::new(),
+            downloads: Vec::new(),
+            packages: Vec::new(),
+            queue: Vec::new(),
+            stats: None,
+            issues: Vec::new(),
+            additions: Vec::new(),
+            message: None,
+        }
+    }
+
+    /// Adds an error to the report.
+    pub fn error(&mut self, url: Option<&str>, reason: impl ToString) {
+        self.errors.push(ErrorReport::new(url, reason));
+    }
+
+    /// Prints the report in the requested format.
+    /// Nothing is printed for the table format, as the commands print their results as they go.
+    /// Write errors are ignored, so that piping the output to a program that stops reading early (e.g. `head`) doesn't panic.
+    pub fn emit(&self, format: OutputFormat) {
+        let mut stdout = io::stdout().lock();
+        match format {
+            OutputFormat::Table => {}
+            OutputFormat::Json => match serde_json::to_string_pretty(self) {
+                Ok(json) => {
+                    let _ = writeln!(stdout, "{}", json);
+                }
+                Err(e) => eprintln!("Error while serializing the report: {}", e),
+            },
+            OutputFormat::Ndjson => {
+                for record in self.records() {
+                    match serde_json::to_string(&record) {
+                        Ok(json) => {
+                            let _ = writeln!(stdout, "{}", json);
+                        }
+                        Err(e) => eprintln!("Error while serializing the report: {}", e),
+                    }
+                }
+            }
+        }
+        let _ = stdout.flush();
+    }
+
+    /// Computes the exit code of a command that either succeeds or fails as a whole.
+    pub fn exit_code(&self) -> i32 {
+        if self.errors.is_empty() {
+            EXIT_SUCCESS
+        } else {
+            EXIT_FATAL
+        }
+    }
+
+    /// Prints the report for the commands that have nothing more than a message to tell in table mode.
+    /// In table mode, the message is printed to stdout and the errors to stderr.
+    /// Otherwise, the report is emitted in the requested format.
+    pub fn print(&self, format: OutputFormat) {
+        if format.is_table() {
+            if let Some(message) = &self.message {
+                println!("{}", message);
+            }
+            for error in &self.errors {
+                eprintln!("{}", error.reason);
+            }
+        } else {
+            self.emit(format);
+        }
+    }
+
+    /// Flattens the report into NDJSON records, ending with a summary line.
+    fn records(&self) -> Vec<Record<'_>> {
+        let mut records: Vec<Record> = Vec::new();
+        records.extend(self.entries.iter().map(Record::Entry));
+        records.extend(self.new_chapters.iter().map(Record::NewChapter));
+        records.extend(self.changes.iter().map(Record::Change));
+        records.extend(self.profiles.iter().map(Record::Profile));
+        records.extend(self.conflicts.iter().map(Record::Conflict));
+        records.extend(self.downloads.iter().map(Record::Download));
+        records.extend(self.packages.iter().map(Record::Package));
+        records.extend(self.queue.iter().map(Record::QueueItem));
+        records.extend(self.stats.iter().map(Record::Stats));
+        records.extend(self.issues.iter().map(Record::Issue));
+        records.extend(self.additions.iter().map(Record::Addition));
+        records.extend(self.errors.iter().map(Record::Error));
+        records.push(Record::Summary {
+            command: &self.command,
+            entries: self.entries.len(),
+            new_chapters: self.new_chapters.len(),
+            errors: self.errors.len(),
+            changes: self.changes.len(),
+            message: &self.message,
+        });
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::line;
+
+    #[test]
+    fn diff_finds_every_change() {
+        let before = vec![line("url1", 1.0), line("url2", 2.0), line("url3", 3.0)];
+        let after = vec![line("url1", 1.0), line("url3", 4.0), line("url4", 1.0)];
+        let changes = LibraryChange::diff(&before, &after);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].action, ChangeAction::Removed);
+        assert_eq!(changes[0].url, "url2");
+        assert_eq!(changes[1].action, ChangeAction::Updated);
+        assert_eq!(changes[1].previous_chapter_num, Some(3.0));
+        assert_eq!(changes[1].chapter_num, Some(4.0));
+        assert_eq!(changes[2].action, ChangeAction::Added);
+        assert_eq!(changes[2].url, "url4");
+    }
+
+    #[test]
+    fn ndjson_records_end_with_summary() -> Result<(), serde_json::Error> {
+        let mut report = CommandReport::new("list");
+        report
+            .entries
+            .push(EntryReport::from_line(1, &line("url1", 1.0)));
+        report.error(Some("url2"), "unreachable");
+        let records = report.records();
+        assert_eq!(records.len(), 3);
+        let first = serde_json::to_value(&records[0])?;
+        assert_eq!(first["type"], "entry");
+        assert_eq!(first["url"], "url1");
+        let last = serde_json::to_value(&records[2])?;
+        assert_eq!(last["type"], "summary");
+        assert_eq!(last["errors"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn json_only_has_the_fields_of_the_command() -> Result<(), serde_json::Error> {
+        let mut report = CommandReport::new("remove");
+        report.error(Some("url1"), "not found");
+        assert_eq!(
+            serde_json::to_value(&report)?,
+            serde_json::json!({
+                "command": "remove",
+                "errors": [{ "url": "url1", "reason": "not found" }]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("NDJSON".parse::<OutputFormat>(), Ok(OutputFormat::Ndjson));
+        assert_eq!("table".parse::<OutputFormat>(), Ok(OutputFormat::Table));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use std::env;
+use std::env::current_exe;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The name of the folders of the program, in the config and data folders.
+const APP_FOLDER: &str = "manga_updater";
+
+/// The environment variable overriding the folder of the config file.
+pub const CONFIG_DIR_VAR: &str = "MANGA_UPDATER_CONFIG_DIR";
+
+/// The environment variable overriding the folder of the library.
+pub const DATA_DIR_VAR: &str = "MANGA_UPDATER_DATA_DIR";
+
+/// The name of the library file.
+const LIBRARY_FILE: &str = "mangas.csv";
+
+/// The name of the config file.
+const CONFIG_FILE: &str = "config.toml";
+
+/// The name standing for the library used without a profile.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Finds the folder of the config file.
+/// It is `$MANGA_UPDATER_CONFIG_DIR` if set, `$XDG_CONFIG_HOME/manga_updater` otherwise (`~/.config/manga_updater` by default).
+/// On the other systems, the config folder of the platform is used instead of the XDG one.
+pub fn config_dir() -> PathBuf {
+    dir_from_env_or(CONFIG_DIR_VAR, dirs::config_dir())
+}
+
+/// Finds the folder of the library.
+/// It is `$MANGA_UPDATER_DATA_DIR` if set, `$XDG_DATA_HOME/manga_updater` otherwise (`~/.local/share/manga_updater` by default).
+/// On the other systems, the data folder of the platform is used instead of the XDG one.
+pub fn data_dir() -> PathBuf {
+    dir_from_env_or(DATA_DIR_VAR, dirs::data_dir())
+}
+
+/// Reads the folder from the environment variable, or uses the folder of the program in the base folder.
+/// If neither is known, the legacy folder is used, or the current folder if the executable can't be located.
+fn dir_from_env_or(var: &str, base: Option<PathBuf>) -> PathBuf {
+    match env::var_os(var).filter(|value| !value.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => base
+            .map(|base| base.join(APP_FOLDER))
+            .or_else(|| legacy_dir().ok())
+            .unwrap_or_else(|| PathBuf::from(".")),
+    }
+}
+
+/// The folder of the executable, where the files were stored by the previous versions.
+/// # Errors:
+/// `io::NotFound` if the executable can't be located, e.g. when it has been deleted or `/proc` isn't mounted.
+fn legacy_dir() -> Result<PathBuf, io::Error> {
+    let exe = current_exe().map_err(|e| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("The folder of the executable can't be found: {}", e),
+        )
+    })?;
+    exe.parent().map(Path::to_path_buf).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "The folder of the executable can't be found.",
+        )
+    })
+}
+
+/// Finds the default library.
+/// It is `mangas.csv` in the data folder (See [`data_dir`]).
+/// As long as it hasn't been migrated (See [`migrate_legacy_files`]), the library next to the executable is used if there is one.
+pub fn default_library_path() -> PathBuf {
+    prefer_legacy(data_dir().join(LIBRARY_FILE), legacy_dir().ok().map(|dir| dir.join(LIBRARY_FILE)))
+}
+
+/// Finds the default config file.
+/// It is `config.toml` in the config folder (See [`config_dir`]).
+/// As long as it hasn't been migrated (See [`migrate_legacy_files`]), the config file next to the executable is used if there is one.
+pub fn default_config_path() -> PathBuf {
+    prefer_legacy(config_dir().join(CONFIG_FILE), legacy_dir().ok().map(|dir| dir.join(CONFIG_FILE)))
+}
+
+/// Keeps using the legacy file if the new one doesn't exist yet.
+/// The new one is used if the legacy folder is unknown.
+fn prefer_legacy(path: PathBuf, legacy: Option<PathBuf>) -> PathBuf {
+    match legacy {
+        Some(legacy) if !path.exists() && legacy.exists() => legacy,
+        _ => path,
+    }
+}
+
+/// Finds the folder of a profile, holding its library, the backup and the recorded chapters.
+/// It is `profiles/[name]` in the data folder (See [`data_dir`]).
+pub fn profile_dir(name: &str) -> PathBuf {
+    profiles_dir().join(name)
+}
+
+/// Finds the folder holding the profiles.
+pub fn profiles_dir() -> PathBuf {
+    data_dir().join("profiles")
+}
+
+/// Finds the library of a profile.
+pub fn profile_library_path(name: &str) -> PathBuf {
+    profile_dir(name).join(LIBRARY_FILE)
+}
+
+/// Finds the settings of a profile, applied over the ones of the config file.
+/// It is `profiles/[name].toml` in the config folder (See [`config_dir`]).
+pub fn profile_config_path(name: &str) -> PathBuf {
+    config_dir().join("profiles").join(format!("{}.toml", name))
+}
+
+/// Checks that the name can be used for a profile, as it is used as a file name.
+/// # Argument:
+/// * `name`: the name of the profile.
+/// # Errors:
+/// * `io::InvalidInput` if the name is empty, is `default`, or contains something else than letters, digits, `-` and `_`.
+pub fn check_profile_name(name: &str) -> Result<(), io::Error> {
+    if name == DEFAULT_PROFILE {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The default profile is the library used without --profile, and can't be created or deleted.",
+        ));
+    }
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid profile name {}. Only letters, digits, - and _ are allowed.",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Lists the profiles, sorted by name.
+/// # Returns:
+/// The names of the folders of the profiles holding a library.
+pub fn list_profiles() -> Result<Vec<String>, io::Error> {
+    let dir = profiles_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(LIBRARY_FILE).is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Lists the files of the previous versions that are still next to the executable, along with their new locations.
+/// A file is only listed if its new location is still free.
+/// # Returns:
+/// The pairs of the legacy file and of its destination.
+/// # Errors:
+/// `io::NotFound` if the executable can't be located, so its folder can't be searched.
+pub fn legacy_files() -> Result<Vec<(PathBuf, PathBuf)>, io::Error> {
+    let legacy = legacy_dir()?;
+    let library = data_dir();
+    let config = config_dir();
+    let files = vec![
+        (legacy.join(LIBRARY_FILE), library.join(LIBRARY_FILE)),
+        (
+            legacy.join("mangas.csv.bak"),
+            library.join("mangas.csv.bak"),
+        ),
+        (
+            legacy.join("mangas.releases.csv"),
+            library.join("mangas.releases.csv"),
+        ),
+        (legacy.join(CONFIG_FILE), config.join(CONFIG_FILE)),
+    ];
+    Ok(files
+        .into_iter()
+        .filter(|(from, to)| from != to && from.exists() && !to.exists())
+        .collect())
+}
+
+/// Moves the files of the previous versions from the executable's folder to the config and data folders.
+/// This is meant to be done once; afterwards, the files next to the executable are ignored.
+/// # Arguments:
+/// * `files`: the pairs of the legacy file and of its destination (See [`legacy_files`]).
+/// # Returns:
+/// The files that have been moved.
+/// # Errors:
+/// Any IO error that happened while creating the folders or moving the files. The files moved before the error stay moved.
+pub fn migrate_legacy_files(
+    files: Vec<(PathBuf, PathBuf)>,
+) -> Result<Vec<(PathBuf, PathBuf)>, io::Error> {
+    for (from, to) in &files {
+        if to.exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists.", to.display()),
+            ));
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(from, to)?;
+    }
+    Ok(files)
+}
+
+/// Moves a file, copying it if it must go to another file system.
+fn move_file(from: &Path, to: &Path) -> Result<(), io::Error> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names_are_checked() {
+        assert!(check_profile_name("alice").is_ok());
+        assert!(check_profile_name("team_2-shared").is_ok());
+        assert!(check_profile_name("default").is_err());
+        assert!(check_profile_name("").is_err());
+        assert!(check_profile_name("../alice").is_err());
+        assert!(check_profile_name("bob smith").is_err());
+    }
+
+    #[test]
+    fn legacy_file_is_kept_until_migrated() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("manga_updater_paths_test");
+        let _ = fs::remove_dir_all(&dir);
+        let legacy = dir.join("legacy.csv");
+        let path = dir.join("data").join("mangas.csv");
+        fs::create_dir_all(&dir)?;
+        fs::write(&legacy, "URL,Last chapter,Title\n")?;
+
+        assert_eq!(prefer_legacy(path.clone(), Some(legacy.clone())), legacy);
+        assert_eq!(prefer_legacy(path.clone(), None), path);
+        migrate_legacy_files(vec![(legacy.clone(), path.clone())])?;
+        assert!(!legacy.exists());
+        assert_eq!(prefer_legacy(path.clone(), Some(legacy.clone())), path);
+
+        fs::write(&legacy, "")?;
+        let error = migrate_legacy_files(vec![(legacy, path)]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        fs::remove_dir_all(&dir)
+    }
+}
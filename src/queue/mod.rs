@@ -0,0 +1,344 @@
+use crate::models::{CSVLine, ChapterLink, Release};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the series are ordered in the reading queue.
+/// The chapters of a series always stay in order, as reading a chapter marks the previous ones as read too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOrder {
+    /// The chapters of every series, from the oldest release to the newest one.
+    Release,
+    /// The series with the highest score first, each one until it is caught up.
+    Priority,
+    /// The series behind for the longest time first, i.e. whose first unread chapter is the oldest, each one until it is caught up.
+    Behind,
+}
+
+impl FromStr for QueueOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "release" => Ok(QueueOrder::Release),
+            "priority" => Ok(QueueOrder::Priority),
+            "behind" => Ok(QueueOrder::Behind),
+            other => Err(format!(
+                "Unknown order {}. Available orders are: release, priority, behind.",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for QueueOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueueOrder::Release => write!(f, "release"),
+            QueueOrder::Priority => write!(f, "priority"),
+            QueueOrder::Behind => write!(f, "behind"),
+        }
+    }
+}
+
+/// A series skipped in the reading queue: it stays at the back of the queue until the chapter is read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skip {
+    pub url: String,
+    /// The first unread chapter when the series has been skipped.
+    pub chapter_num: f32,
+}
+
+/// An unread chapter in the reading queue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueItem {
+    /// The line number of the manga.
+    pub position: usize,
+    pub url: String,
+    pub title: String,
+    pub chapter_num: f32,
+    pub chapter_title: String,
+    pub chapter_url: String,
+    /// When the chapter has been detected, if it has been recorded in the releases.
+    pub released_at: Option<DateTime<Utc>>,
+    /// True if the series has been skipped, and stays at the back of the queue.
+    pub skipped: bool,
+}
+
+/// The chapters of a manga in the library, as listed in its page.
+#[derive(Debug, Clone)]
+pub struct SeriesChapters {
+    /// The line number of the manga.
+    pub position: usize,
+    pub line: CSVLine,
+    /// The chapters, from the first one to the last one.
+    pub chapters: Vec<ChapterLink>,
+}
+
+/// Builds the reading queue: every unread chapter of the series, i.e. numbered above the last chapter read.
+/// The skipped series come last, from the first one skipped to the last one.
+/// # Arguments:
+/// * `series`: the chapters of each manga.
+/// * `releases`: the recorded releases, to date the chapters.
+/// * `skips`: the skipped series.
+/// * `order`: how the series are ordered.
+/// # Returns:
+/// The unread chapters, in the reading order.
+pub fn build_queue(
+    series: Vec<SeriesChapters>,
+    releases: &[Release],
+    skips: &[Skip],
+    order: QueueOrder,
+) -> Vec<QueueItem> {
+    let mut active: Vec<(Option<f32>, Vec<QueueItem>)> = Vec::new();
+    let mut skipped: Vec<(usize, Vec<QueueItem>)> = Vec::new();
+    for series in series {
+        let items: Vec<QueueItem> = series
+            .chapters
+            .into_iter()
+            .filter(|chapter| chapter.num > series.line.last_chapter_num)
+            .map(|chapter| QueueItem {
+                position: series.position,
+                url: series.line.url.clone(),
+                title: series.line.title.clone(),
+                released_at: releases
+                    .iter()
+                    .find(|release| {
+                        release.manga_url == series.line.url && release.chapter_num == chapter.num
+                    })
+                    .map(|release| release.detected_at),
+                chapter_num: chapter.num,
+                chapter_title: chapter.title,
+                chapter_url: chapter.url,
+                skipped: false,
+            })
+            .collect();
+        let Some(head) = items.first() else {
+            continue;
+        };
+        match skips
+            .iter()
+            .position(|skip| skip.url == head.url && skip.chapter_num == head.chapter_num)
+        {
+            Some(index) => skipped.push((
+                index,
+                items
+                    .into_iter()
+                    .map(|item| QueueItem {
+                        skipped: true,
+                        ..item
+                    })
+                    .collect(),
+            )),
+            None => active.push((series.line.score, items)),
+        }
+    }
+    let mut queue = order_series(active, order);
+    skipped.sort_by_key(|(index, _)| *index);
+    queue.extend(skipped.into_iter().flat_map(|(_, items)| items));
+    queue
+}
+
+/// Orders the chapters of the series, keeping the chapters of each series in order.
+/// # Arguments:
+/// * `series`: the score and the unread chapters of each series, none of them empty.
+/// * `order`: how the series are ordered.
+fn order_series(
+    mut series: Vec<(Option<f32>, Vec<QueueItem>)>,
+    order: QueueOrder,
+) -> Vec<QueueItem> {
+    match order {
+        QueueOrder::Release => {
+            // Picks the oldest of the next chapters of the series, until there are none left.
+            let mut remaining: Vec<std::vec::IntoIter<QueueItem>> = series
+                .into_iter()
+                .map(|(_, items)| items.into_iter())
+                .collect();
+            let mut next: Vec<Option<QueueItem>> =
+                remaining.iter_mut().map(|items| items.next()).collect();
+            let mut queue: Vec<QueueItem> = Vec::new();
+            while let Some(index) = next
+                .iter()
+                .enumerate()
+                .filter_map(|(index, item)| Some((index, item.as_ref()?)))
+                .min_by(|(_, a), (_, b)| by_release(a, b))
+                .map(|(index, _)| index)
+            {
+                queue.extend(next[index].take());
+                next[index] = remaining[index].next();
+            }
+            queue
+        }
+        QueueOrder::Priority => {
+            // The series without a score come last.
+            let score = |score: &Option<f32>| score.unwrap_or(f32::NEG_INFINITY);
+            series.sort_by(|(score_a, a), (score_b, b)| {
+                score(score_b)
+                    .total_cmp(&score(score_a))
+                    .then(a[0].position.cmp(&b[0].position))
+            });
+            series.into_iter().flat_map(|(_, items)| items).collect()
+        }
+        QueueOrder::Behind => {
+            // Among the series behind since before the releases have been recorded, the ones with the most unread chapters first.
+            series.sort_by(|(_, a), (_, b)| {
+                a[0].released_at
+                    .cmp(&b[0].released_at)
+                    .then(b.len().cmp(&a.len()))
+                    .then(a[0].position.cmp(&b[0].position))
+            });
+            series.into_iter().flat_map(|(_, items)| items).collect()
+        }
+    }
+}
+
+/// Compares the releases of two chapters, the oldest first.
+/// The chapters released before the releases have been recorded are the oldest ones, in the order of the library.
+fn by_release(a: &QueueItem, b: &QueueItem) -> Ordering {
+    a.released_at
+        .cmp(&b.released_at)
+        .then(a.position.cmp(&b.position))
+        .then(a.chapter_num.total_cmp(&b.chapter_num))
+}
+
+/// Skips the series at the head of the queue, which goes to the back of it until the chapter is read.
+/// The skips whose chapter has been read since are forgotten.
+/// # Arguments:
+/// * `queue`: the reading queue.
+/// * `skips`: the skipped series.
+/// # Returns:
+/// The skipped series, with the head of the queue last. They are unchanged if the queue is empty.
+pub fn skip_head(queue: &[QueueItem], skips: &[Skip]) -> Vec<Skip> {
+    let Some(head) = queue.first() else {
+        return skips.to_vec();
+    };
+    let mut updated: Vec<Skip> = skips
+        .iter()
+        .filter(|skip| skip.url != head.url)
+        .filter(|skip| {
+            queue.iter().any(|item| {
+                item.skipped && item.url == skip.url && item.chapter_num == skip.chapter_num
+            })
+        })
+        .cloned()
+        .collect();
+    updated.push(Skip {
+        url: head.url.clone(),
+        chapter_num: head.chapter_num,
+    });
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, line};
+    use chrono::TimeZone;
+
+    fn series(position: usize, last: f32, score: Option<f32>, chapters: &[f32]) -> SeriesChapters {
+        SeriesChapters {
+            position,
+            line: CSVLine {
+                score,
+                ..line(&format!("url{}", position), last)
+            },
+            chapters: chapters
+                .iter()
+                .map(|num| ChapterLink {
+                    num: *num,
+                    title: format!("Chapter {}", num),
+                    url: format!("url{}/chapter-{}", position, num),
+                })
+                .collect(),
+        }
+    }
+
+    fn release(position: usize, chapter: f32, day: u32) -> Release {
+        test_utils::release(
+            &format!("url{}", position),
+            chapter,
+            Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+        )
+    }
+
+    fn library() -> Vec<SeriesChapters> {
+        vec![
+            series(1, 2.0, Some(6.0), &[1.0, 2.0, 3.0, 4.0]),
+            series(2, 0.0, Some(9.0), &[1.0, 2.0]),
+            series(3, 5.0, None, &[4.0, 5.0, 6.0]),
+            // Caught up, so not in the queue.
+            series(4, 3.0, Some(10.0), &[1.0, 2.0, 3.0]),
+        ]
+    }
+
+    fn chapters(queue: &[QueueItem]) -> Vec<(usize, f32)> {
+        queue
+            .iter()
+            .map(|item| (item.position, item.chapter_num))
+            .collect()
+    }
+
+    #[test]
+    fn queue_is_ordered_at_chapter_level() {
+        let releases = vec![release(1, 4.0, 3), release(2, 2.0, 1), release(3, 6.0, 2)];
+        let by_release = build_queue(library(), &releases, &[], QueueOrder::Release);
+        // The chapters released before the releases were recorded come first.
+        assert_eq!(
+            chapters(&by_release),
+            vec![(1, 3.0), (2, 1.0), (2, 2.0), (3, 6.0), (1, 4.0)]
+        );
+        assert_eq!(by_release[3].released_at, Some(releases[2].detected_at));
+
+        let by_priority = build_queue(library(), &releases, &[], QueueOrder::Priority);
+        assert_eq!(
+            chapters(&by_priority),
+            vec![(2, 1.0), (2, 2.0), (1, 3.0), (1, 4.0), (3, 6.0)]
+        );
+
+        let by_behind = build_queue(library(), &releases, &[], QueueOrder::Behind);
+        assert_eq!(
+            chapters(&by_behind),
+            vec![(1, 3.0), (1, 4.0), (2, 1.0), (2, 2.0), (3, 6.0)]
+        );
+        assert_eq!("behind".parse(), Ok(QueueOrder::Behind));
+    }
+
+    #[test]
+    fn skipped_series_go_to_the_back() {
+        let queue = build_queue(library(), &[], &[], QueueOrder::Priority);
+        let skips = skip_head(&queue, &[]);
+        assert_eq!(
+            skips,
+            vec![Skip {
+                url: "url2".to_owned(),
+                chapter_num: 1.0
+            }]
+        );
+        let queue = build_queue(library(), &[], &skips, QueueOrder::Priority);
+        assert_eq!(
+            chapters(&queue),
+            vec![(1, 3.0), (1, 4.0), (3, 6.0), (2, 1.0), (2, 2.0)]
+        );
+        assert!(queue[3].skipped && !queue[0].skipped);
+
+        let skips = skip_head(&queue, &skips);
+        assert_eq!(skips.len(), 2);
+        let queue = build_queue(library(), &[], &skips, QueueOrder::Priority);
+        assert_eq!(
+            chapters(&queue),
+            vec![(3, 6.0), (2, 1.0), (2, 2.0), (1, 3.0), (1, 4.0)]
+        );
+
+        // Once its chapter is read, a series is no longer skipped.
+        let mut read = library();
+        read[1].line.last_chapter_num = 1.0;
+        let queue = build_queue(read, &[], &skips, QueueOrder::Priority);
+        assert_eq!(
+            chapters(&queue),
+            vec![(2, 2.0), (3, 6.0), (1, 3.0), (1, 4.0)]
+        );
+        assert_eq!(skip_head(&queue, &skips).len(), 2);
+    }
+}
@@ -1,8 +1,81 @@
-use crate::models::MangaChapter;
+use crate::models::{ChapterLink, MangaChapter, SearchResult};
 use crate::utils::ScraperError;
 use reqwest::{Client, Error};
 use scraper::{ElementRef, Html, Selector};
 use std::error;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// The default maximum number of pages fetched at the same time.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The search page of the website, followed by the query.
+pub const DEFAULT_SEARCH_URL: &str = "https://manganato.com/search/story/";
+
+/// The settings of the requests, set once at startup from the config file.
+static CLIENT_SETTINGS: OnceLock<ClientSettings> = OnceLock::new();
+
+/// The time at which the next request may be sent, shared by every request of the program.
+static NEXT_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// The settings of the requests sent to the manga pages.
+#[derive(Debug, Clone)]
+pub struct ClientSettings {
+    /// The user agent to send. If None, the default one of reqwest is sent.
+    pub user_agent: Option<String>,
+    /// The maximum number of pages fetched at the same time.
+    pub concurrency: usize,
+    /// The search page to use instead of [`DEFAULT_SEARCH_URL`].
+    pub search_url: Option<String>,
+    /// The maximum number of requests sent per second. Unlimited if None.
+    pub rate_limit: Option<f64>,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        ClientSettings {
+            user_agent: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            search_url: None,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Sets the settings used by the clients and the fetches for the rest of the program.
+/// Only the first call has an effect.
+/// # Argument:
+/// * `settings`: the settings, read from the config file.
+pub fn configure_client(settings: ClientSettings) {
+    let _ = CLIENT_SETTINGS.set(settings);
+}
+
+/// Gives the settings of the requests, or the default ones if they haven't been configured.
+fn client_settings() -> &'static ClientSettings {
+    CLIENT_SETTINGS.get_or_init(ClientSettings::default)
+}
+
+/// Gives the maximum number of pages to fetch at the same time. It is never zero.
+pub fn concurrency() -> usize {
+    client_settings().concurrency.max(1)
+}
+
+/// Waits until the rate limit allows another request (See [`ClientSettings::rate_limit`]).
+/// The requests are spaced out evenly, whichever part of the program sends them.
+async fn wait_for_rate_limit() {
+    let Some(rate) = client_settings().rate_limit.filter(|rate| *rate > 0.0) else {
+        return;
+    };
+    let interval = Duration::from_secs_f64(1.0 / rate);
+    let slot = {
+        let mut next = NEXT_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = next.map_or(Instant::now(), |next| next.max(Instant::now()));
+        *next = Some(slot + interval);
+        slot
+    };
+    sleep_until(slot).await;
+}
 
 /// Downloads the HTML contents of the URL given in parameter.
 /// Executes a GET request in async mode.
@@ -17,8 +90,9 @@ async fn download_page(
     url: &str,
     client: Option<&Client>,
 ) -> Result<String, Box<dyn error::Error>> {
+    wait_for_rate_limit().await;
     match client {
-        None => Ok(reqwest::get(url).await?.text().await?),
+        None => Ok(create_client()?.get(url).send().await?.text().await?),
         Some(client) => Ok(client.get(url).send().await?.text().await?),
     }
 }
@@ -39,7 +113,7 @@ fn browse_fragment(
     list_sel: Selector,
     item_sel: Selector,
     link_sel: Selector,
-) -> Result<ElementRef, ScraperError> {
+) -> Result<ElementRef<'_>, ScraperError> {
     fragment
         .select(&list_sel)
         .next()
@@ -69,7 +143,7 @@ fn browse_fragment(
 fn extract_last_chapter_elt_ref(
     fragment: &Html,
     verbose: bool,
-) -> Result<ElementRef, ScraperError> {
+) -> Result<ElementRef<'_>, ScraperError> {
     let list_selector = Selector::parse("ul.row-content-chapter");
     let item_selector = Selector::parse("li");
     let link_selector = Selector::parse("a");
@@ -121,6 +195,7 @@ fn extract_last_chapter_elt_ref(
 /// - Last chapter's name
 /// - Last chapter's number
 /// - Last chapter's link
+/// - Manga's cover, if present
 ///
 /// # Argument:
 /// * `page`: the String containing the page's HTML.
@@ -161,16 +236,23 @@ fn scrape_page_for_last_chapter(
     let link = last_chapter.value().attr("href").unwrap();
     let chapter_number = link
         .split('-')
-        .last()
+        .next_back()
         .unwrap_or("1")
         .parse::<f32>()
         .unwrap_or(1f32);
 
+    let cover = fragment
+        .select(&Selector::parse("span.info-image img").unwrap())
+        .next()
+        .and_then(|image| image.value().attr("src"))
+        .map(str::to_owned);
+
     Ok(MangaChapter {
         manga_title,
         url: link.parse().unwrap(),
         chapter_title,
         num: chapter_number,
+        cover,
     })
 }
 
@@ -187,7 +269,9 @@ pub async fn find_last_chapter(
     match download_page(manga_url, client).await {
         Ok(page) => scrape_page_for_last_chapter(page, manga_url, *verbose),
         Err(e) => {
-            eprintln!("Error processing url {}: reason {:?}", manga_url, e);
+            if *verbose {
+                eprintln!("Error processing url {}: reason {:?}", manga_url, e);
+            }
             Err(ScraperError {
                 reason: e.to_string(),
             })
@@ -195,15 +279,241 @@ pub async fn find_last_chapter(
     }
 }
 
+/// Why the page of a manga couldn't be scraped.
+#[derive(Debug)]
+pub enum PageError {
+    /// The page couldn't be downloaded, or the website answered with an error.
+    Unreachable(String),
+    /// The page has been downloaded, but it doesn't match the selectors anymore.
+    Layout(ScraperError),
+}
+
+/// Fetches the page of a manga and scrapes its last chapter, telling a page that can't be reached from a page whose layout has changed.
+/// Unlike [`find_last_chapter`], the HTTP status is checked, so that an error page isn't taken for a new layout.
+/// # Arguments:
+/// * `manga_url`: the URL of the manga.
+/// * `client`: the client to use to make requests.
+/// # Returns:
+/// The last chapter of the manga.
+pub async fn check_manga_page(manga_url: &str, client: &Client) -> Result<MangaChapter, PageError> {
+    wait_for_rate_limit().await;
+    let response = client
+        .get(manga_url)
+        .send()
+        .await
+        .map_err(|e| PageError::Unreachable(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(PageError::Unreachable(format!("the website answered {}", status)));
+    }
+    let page = response
+        .text()
+        .await
+        .map_err(|e| PageError::Unreachable(e.to_string()))?;
+    scrape_page_for_last_chapter(page, manga_url, false).map_err(PageError::Layout)
+}
+
+/// Scrapes the page of a manga for the list of its chapters.
+/// # Argument:
+/// * `page`: the String containing the page's HTML.
+/// # Returns:
+/// The chapters, from the first one to the last one.
+/// # Errors:
+/// A ScraperError if the chapter list is absent or empty.
+fn scrape_chapter_list(page: String) -> Result<Vec<ChapterLink>, ScraperError> {
+    let fragment = Html::parse_document(page.as_str());
+    let selector = Selector::parse("ul.row-content-chapter li a").unwrap();
+    let mut chapters: Vec<ChapterLink> = fragment
+        .select(&selector)
+        .filter_map(|link| {
+            let url = link.value().attr("href")?;
+            Some(ChapterLink {
+                num: url.split('-').next_back()?.parse::<f32>().ok()?,
+                title: link.text().collect::<String>().trim().to_owned(),
+                url: url.to_owned(),
+            })
+        })
+        .collect();
+    if chapters.is_empty() {
+        return Err(ScraperError {
+            reason: "The chapter list is absent or empty.".to_owned(),
+        });
+    }
+    chapters.reverse();
+    Ok(chapters)
+}
+
+/// Finds the chapters of a manga.
+/// # Arguments:
+/// * `manga_url`: the URL of the manga.
+/// * `client`: the client to use to make requests.
+/// # Returns:
+/// The chapters, from the first one to the last one.
+pub async fn find_chapters(
+    manga_url: &str,
+    client: &Client,
+) -> Result<Vec<ChapterLink>, ScraperError> {
+    let page = download_page(manga_url, Some(client))
+        .await
+        .map_err(|e| ScraperError {
+            reason: e.to_string(),
+        })?;
+    scrape_chapter_list(page)
+}
+
+/// Scrapes the page of a chapter for its images, in the reading order.
+/// Only the images directly in the reader are pages, the others being ads. The images loaded lazily have their URL in `data-src` instead of `src`.
+fn scrape_chapter_images(page: String) -> Vec<String> {
+    let fragment = Html::parse_document(page.as_str());
+    let selector = Selector::parse("div.container-chapter-reader > img").unwrap();
+    fragment
+        .select(&selector)
+        .filter_map(|image| {
+            image
+                .value()
+                .attr("data-src")
+                .or(image.value().attr("src"))
+        })
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+/// Finds the images of a chapter.
+/// # Arguments:
+/// * `chapter_url`: the URL of the chapter.
+/// * `client`: the client to use to make requests.
+/// # Returns:
+/// The URLs of the images, in the reading order.
+/// # Errors:
+/// A ScraperError if the page couldn't be downloaded or has no images.
+pub async fn find_chapter_images(
+    chapter_url: &str,
+    client: &Client,
+) -> Result<Vec<String>, ScraperError> {
+    let page = download_page(chapter_url, Some(client))
+        .await
+        .map_err(|e| ScraperError {
+            reason: e.to_string(),
+        })?;
+    let images = scrape_chapter_images(page);
+    if images.is_empty() {
+        return Err(ScraperError {
+            reason: format!("No images were found in the chapter {}.", chapter_url),
+        });
+    }
+    Ok(images)
+}
+
+/// Downloads an image, such as the cover of a manga.
+/// The websites refuse to serve their images to other websites, so the page showing the image is sent as the referer.
+/// # Arguments:
+/// * `url`: the URL of the image.
+/// * `referer`: the page showing the image.
+/// * `client`: the client to use to make requests.
+/// # Returns:
+/// The bytes of the image, and its MIME type as given by the server (`image/jpeg` if none was given).
+/// # Errors:
+/// A ScraperError if the image couldn't be downloaded.
+pub async fn download_image(
+    url: &str,
+    referer: &str,
+    client: &Client,
+) -> Result<(Vec<u8>, String), ScraperError> {
+    let to_error = |e: Error| ScraperError {
+        reason: format!("Error while downloading the image {}: {}", url, e),
+    };
+    wait_for_rate_limit().await;
+    let response = client
+        .get(url)
+        .header(reqwest::header::REFERER, referer)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(to_error)?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| value.starts_with("image/"))
+        .unwrap_or("image/jpeg")
+        .to_owned();
+    let bytes = response.bytes().await.map_err(to_error)?;
+    Ok((bytes.to_vec(), content_type))
+}
+
+/// Builds the search query of a title: the website expects lowercase words separated by underscores.
+fn search_query(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Scrapes the search page for the mangas found.
+/// # Argument:
+/// * `page`: the String containing the page's HTML.
+/// # Returns:
+/// The title and the URL of each manga found, in the order of the page. It is empty if nothing was found.
+fn scrape_search_results(page: String) -> Vec<SearchResult> {
+    let fragment = Html::parse_document(page.as_str());
+    let item_selector = Selector::parse("div.search-story-item a.item-title").unwrap();
+    fragment
+        .select(&item_selector)
+        .filter_map(|link| {
+            let url = link.value().attr("href")?;
+            Some(SearchResult {
+                title: link.text().collect::<String>().trim().to_owned(),
+                url: url.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Searches a title on the website.
+/// The search page is the one of the settings, or [`DEFAULT_SEARCH_URL`] (See [`configure_client`]).
+/// # Arguments:
+/// * `title`: the title to search for.
+/// * `client`: the client to use to make requests. If None, a new one is created.
+/// # Returns:
+/// The mangas found, which may not match the title exactly.
+/// # Errors:
+/// A ScraperError if the title has no searchable word, or if the page couldn't be downloaded.
+pub async fn search_titles(
+    title: &str,
+    client: Option<&Client>,
+) -> Result<Vec<SearchResult>, ScraperError> {
+    let query = search_query(title);
+    if query.is_empty() {
+        return Err(ScraperError {
+            reason: format!("The title {} can't be searched.", title),
+        });
+    }
+    let search_url = client_settings()
+        .search_url
+        .as_deref()
+        .unwrap_or(DEFAULT_SEARCH_URL);
+    let url = format!("{}{}", search_url, query);
+    download_page(&url, client)
+        .await
+        .map(scrape_search_results)
+        .map_err(|e| ScraperError {
+            reason: format!("Error while searching {}: {}", title, e),
+        })
+}
+
 /// Creates a new Client to send requests using its connection pool for better efficiency.
+/// The user agent of the settings is used, if any (See [`configure_client`]).
 /// # Result:
 /// A Result type containing the client or an error.
 pub fn create_client() -> Result<Client, Error> {
-    let builder = Client::builder();
-    match builder.build() {
-        Ok(client) => Ok(client),
-        Err(e) => Err(e),
+    let mut builder = Client::builder();
+    if let Some(user_agent) = &client_settings().user_agent {
+        builder = builder.user_agent(user_agent);
     }
+    builder.build()
 }
 
 #[cfg(test)]
@@ -213,6 +523,7 @@ mod tests {
     use std::path::PathBuf;
 
     #[test]
+    #[allow(clippy::unnecessary_to_owned)]
     fn scrape_page_for_last_chapter_test() -> Result<(), Box<dyn error::Error>> {
         let mut directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         directory.push("tests_resources/testpage.html");
@@ -229,6 +540,10 @@ mod tests {
                     "Mushoku Tensei - Isekai Ittara Honki Dasu"
                 );
                 assert_eq!(chapter.num, 74f32);
+                assert_eq!(
+                    chapter.cover.as_deref(),
+                    Some("https://avt.mkklcdnv6temp.com/7/r/1-1583463967.jpg")
+                );
                 Ok(())
             }
             Err(_) => panic!("Cannot extract chapter"),
@@ -236,6 +551,57 @@ mod tests {
     }
 
     #[test]
+    fn scrape_search_results_test() -> Result<(), Box<dyn error::Error>> {
+        assert_eq!(
+            search_query("Mushoku Tensei: Isekai Ittara Honki Dasu"),
+            "mushoku_tensei_isekai_ittara_honki_dasu"
+        );
+        let mut directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        directory.push("tests_resources/searchpage.html");
+        let results = scrape_search_results(fs::read_to_string(directory)?);
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0],
+            SearchResult {
+                title: "Mushoku Tensei - Isekai Ittara Honki Dasu".to_owned(),
+                url: "http://localhost:8765/testpage.html".to_owned(),
+            }
+        );
+        assert_eq!(results[1].url, "https://readmanganato.com/manga-hx986305");
+        Ok(())
+    }
+
+    #[test]
+    fn scrape_chapters_test() -> Result<(), Box<dyn error::Error>> {
+        let mut directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        directory.push("tests_resources/testpage.html");
+        let chapters = scrape_chapter_list(fs::read_to_string(directory)?)?;
+        assert_eq!(chapters.len(), 87);
+        assert_eq!(chapters[0].num, 1.0);
+        let last = chapters.last().unwrap();
+        assert_eq!(last.num, 74.0);
+        assert_eq!(last.title, "Chapter 74");
+        assert_eq!(
+            last.url,
+            "https://readmanganato.com/manga-qm951521/chapter-74"
+        );
+
+        let mut directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        directory.push("tests_resources/chapterpage.html");
+        let images = scrape_chapter_images(fs::read_to_string(directory)?);
+        assert_eq!(
+            images,
+            vec![
+                "http://localhost:8765/page1.jpg",
+                "http://localhost:8765/page2.png",
+                "http://localhost:8765/page3.jpg"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::unnecessary_to_owned)]
     fn with_a_wrong_site_throws_error() -> Result<(), Box<dyn error::Error>> {
         let mut directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         directory.push("tests_resources/false_testpage.html");
@@ -0,0 +1,211 @@
+use crate::feed::{build_feed, FeedFormat};
+use crate::file_ops::releases::read_releases;
+use hyper::body::to_bytes;
+use hyper::header::{CONTENT_TYPE, HOST};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+/// The page of the dashboard, served by the serve command.
+pub const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Serves HTTP requests with the handler, until the shutdown future completes.
+/// # Arguments:
+/// * `listener`: the socket to accept the connections from.
+/// * `handler`: the function answering each request.
+/// * `shutdown`: the future telling the server to stop.
+/// # Returns:
+/// An error if the server couldn't be started or failed.
+pub async fn serve<H, F>(
+    listener: TcpListener,
+    handler: H,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), hyper::Error>
+where
+    H: Fn(Request<Body>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response.await) }
+            }))
+        }
+    });
+    Server::from_tcp(listener)?
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+/// Serves the feed of the releases over HTTP, until the shutdown future completes.
+/// The feed is built again at each request, so that it always reflects the releases file.
+/// It is available at `/`, `/feed` and `/feed.xml`.
+/// # Arguments:
+/// * `listener`: the socket to accept the connections from.
+/// * `path`: the optional path to the CSV file, next to which the releases are stored.
+/// * `feed_format`: the format of the feed.
+/// * `shutdown`: the future telling the server to stop.
+/// # Returns:
+/// An error if the server couldn't be started or failed.
+pub async fn serve_feed(
+    listener: TcpListener,
+    path: Option<PathBuf>,
+    feed_format: FeedFormat,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), hyper::Error> {
+    let handler = move |request: Request<Body>| {
+        let response = if matches!(request.uri().path(), "/" | "/feed" | "/feed.xml") {
+            feed_response(&request, &path, feed_format)
+        } else {
+            plain_response(StatusCode::NOT_FOUND, "Not found. The feed is at /feed.")
+        };
+        async move { response }
+    };
+    serve(listener, handler, shutdown).await
+}
+
+/// Answers a request for the feed of the releases.
+/// # Arguments:
+/// * `request`: the request, whose host is used to build the link of the feed.
+/// * `path`: the optional path to the CSV file.
+/// * `feed_format`: the format of the feed.
+/// # Returns:
+/// The feed, or an error response if the releases couldn't be read.
+pub fn feed_response(
+    request: &Request<Body>,
+    path: &Option<PathBuf>,
+    feed_format: FeedFormat,
+) -> Response<Body> {
+    if request.method() != Method::GET {
+        return plain_response(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported.");
+    }
+    match read_releases(path) {
+        Ok(releases) => {
+            let link = request
+                .headers()
+                .get(HOST)
+                .and_then(|host| host.to_str().ok())
+                .map(|host| format!("http://{}{}", host, request.uri().path()));
+            Response::builder()
+                .header(CONTENT_TYPE, feed_format.content_type())
+                .body(Body::from(build_feed(
+                    &releases,
+                    feed_format,
+                    link.as_deref(),
+                )))
+                .unwrap()
+        }
+        Err(e) => plain_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("The releases couldn't be read: {}", e),
+        ),
+    }
+}
+
+/// Builds a JSON response.
+pub fn json_response(status: StatusCode, value: &impl Serialize) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(json) => Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(json))
+            .unwrap(),
+        Err(e) => plain_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Builds an HTML response.
+pub fn html_response(page: &'static str) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(page))
+        .unwrap()
+}
+
+/// Reads the JSON body of a request.
+/// # Returns:
+/// The deserialized body, or a bad request response telling what's wrong with it.
+pub async fn read_json<T: DeserializeOwned>(request: Request<Body>) -> Result<T, Response<Body>> {
+    let bytes = to_bytes(request.into_body()).await.map_err(|e| {
+        plain_response(
+            StatusCode::BAD_REQUEST,
+            &format!("The body couldn't be read: {}", e),
+        )
+    })?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        plain_response(
+            StatusCode::BAD_REQUEST,
+            &format!("The body is invalid: {}", e),
+        )
+    })
+}
+
+/// Builds a plain text response, used for the errors.
+pub fn plain_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(message.to_owned()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_ops::releases::record_releases;
+    use crate::models::Release;
+    use chrono::Utc;
+    use serial_test::serial;
+    use std::fs::remove_file;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    #[serial]
+    async fn feed_is_served() {
+        let path = Some(PathBuf::from("test_feed_server.csv"));
+        let release = Release {
+            detected_at: Utc::now(),
+            manga_url: "http://localhost/manga".to_owned(),
+            manga_title: "Title".to_owned(),
+            chapter_num: 2.0,
+            chapter_title: "Chapter 2".to_owned(),
+            chapter_url: "http://localhost/manga/chapter-2".to_owned(),
+        };
+        record_releases(&path, vec![release]).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_feed(listener, path, FeedFormat::Rss, async {
+            let _ = stopped.await;
+        }));
+
+        let response = reqwest::get(format!("http://{}/feed", address))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[CONTENT_TYPE.as_str()],
+            "application/rss+xml; charset=utf-8"
+        );
+        let body = response.text().await.unwrap();
+        assert!(body.contains(&format!("<link>http://{}/feed</link>", address)));
+        assert!(body.contains("<title>Title: Chapter 2</title>"));
+
+        let missing = reqwest::get(format!("http://{}/other", address))
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        remove_file("test_feed_server.releases.csv").unwrap();
+    }
+}
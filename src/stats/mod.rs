@@ -0,0 +1,325 @@
+use crate::models::{CSVLine, ReadingEvent, ReadingStatus, Release};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The number of weeks and months of reading shown.
+pub const PERIODS: usize = 12;
+/// The number of days over which the activity of the series is measured.
+pub const ACTIVITY_DAYS: i64 = 90;
+/// The number of most active series shown.
+const MOST_ACTIVE: usize = 5;
+
+/// The statistics of a library.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LibraryStats {
+    pub series: usize,
+    /// The number of series of each status.
+    pub statuses: BTreeMap<String, usize>,
+    /// The chapters read in total, i.e. the sum of the last chapters read.
+    pub chapters_read: usize,
+    /// The unread chapters of the series being read, None if the chapters couldn't be fetched.
+    pub backlog: Option<usize>,
+    /// The number of series being read with unread chapters.
+    pub backlog_series: Option<usize>,
+    /// The chapters read each week, from the oldest week to the current one.
+    pub weekly: Vec<PeriodCount>,
+    /// The chapters read each month, from the oldest month to the current one.
+    pub monthly: Vec<PeriodCount>,
+    /// The series with the most releases over the last [`ACTIVITY_DAYS`] days.
+    pub most_active: Vec<SeriesActivity>,
+    /// The series without any release for `stale_months` months.
+    pub stale: Vec<StaleSeries>,
+    pub stale_months: u32,
+}
+
+/// The chapters read during a week or a month.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodCount {
+    /// The first day of the period. The weeks start on Monday.
+    pub start: NaiveDate,
+    pub chapters: usize,
+}
+
+/// The releases of a series over the last [`ACTIVITY_DAYS`] days.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeriesActivity {
+    pub url: String,
+    pub title: String,
+    pub releases: usize,
+    /// The average number of days between two releases, None with a single release.
+    pub days_between_releases: Option<f32>,
+}
+
+/// A series without any recent release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StaleSeries {
+    pub url: String,
+    pub title: String,
+    /// The last release recorded, None if none has been since the releases are recorded.
+    pub last_release: Option<DateTime<Utc>>,
+}
+
+/// Computes the statistics of a library.
+/// # Arguments:
+/// * `lines`: the lines of the library.
+/// * `history`: the reading history.
+/// * `releases`: the recorded releases.
+/// * `latest`: the URL and the number of the last chapter online of each series being read, None if they couldn't be fetched.
+/// * `stale_months`: the number of months without release after which a series is stale.
+/// * `now`: the current time.
+pub fn compute_stats(
+    lines: &[CSVLine],
+    history: &[ReadingEvent],
+    releases: &[Release],
+    latest: Option<&[(String, f32)]>,
+    stale_months: u32,
+    now: DateTime<Utc>,
+) -> LibraryStats {
+    let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+    for line in lines {
+        *statuses.entry(line.status.to_string()).or_default() += 1;
+    }
+    let unread: Option<Vec<usize>> = latest.map(|latest| {
+        latest
+            .iter()
+            .filter_map(|(url, num)| {
+                let line = lines.iter().find(|line| &line.url == url)?;
+                Some((num.floor() - line.last_chapter_num.floor()).max(0.0) as usize)
+            })
+            .filter(|unread| *unread > 0)
+            .collect()
+    });
+    LibraryStats {
+        series: lines.len(),
+        statuses,
+        chapters_read: lines
+            .iter()
+            .map(|line| line.last_chapter_num.max(0.0).floor() as usize)
+            .sum(),
+        backlog: unread.as_ref().map(|unread| unread.iter().sum()),
+        backlog_series: unread.as_ref().map(|unread| unread.len()),
+        weekly: read_per_period(history, week_starts(now.date_naive())),
+        monthly: read_per_period(history, month_starts(now.date_naive())),
+        most_active: most_active(lines, releases, now),
+        stale: stale_series(lines, releases, stale_months, now),
+        stale_months,
+    }
+}
+
+/// Gives the first day of the last [`PERIODS`] weeks, the current one last.
+fn week_starts(today: NaiveDate) -> Vec<NaiveDate> {
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    (0..PERIODS)
+        .rev()
+        .map(|weeks| monday - Duration::weeks(weeks as i64))
+        .collect()
+}
+
+/// Gives the first day of the last [`PERIODS`] months, the current one last.
+fn month_starts(today: NaiveDate) -> Vec<NaiveDate> {
+    let first = today.with_day(1).unwrap_or(today);
+    (0..PERIODS)
+        .rev()
+        .filter_map(|months| first.checked_sub_months(Months::new(months as u32)))
+        .collect()
+}
+
+/// Counts the chapters read during each period, the last one going on until now.
+fn read_per_period(history: &[ReadingEvent], starts: Vec<NaiveDate>) -> Vec<PeriodCount> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = starts.get(index + 1);
+            PeriodCount {
+                start: *start,
+                chapters: history
+                    .iter()
+                    .filter(|event| {
+                        let day = event.read_at.date_naive();
+                        day >= *start && end.is_none_or(|end| day < *end)
+                    })
+                    .map(|event| event.chapters_read())
+                    .sum(),
+            }
+        })
+        .collect()
+}
+
+/// Finds the series of the library with the most releases over the last [`ACTIVITY_DAYS`] days.
+fn most_active(lines: &[CSVLine], releases: &[Release], now: DateTime<Utc>) -> Vec<SeriesActivity> {
+    let since = now - Duration::days(ACTIVITY_DAYS);
+    let mut activity: Vec<SeriesActivity> = lines
+        .iter()
+        .filter_map(|line| {
+            let mut dates: Vec<DateTime<Utc>> = releases
+                .iter()
+                .filter(|release| release.manga_url == line.url && release.detected_at >= since)
+                .map(|release| release.detected_at)
+                .collect();
+            if dates.is_empty() {
+                return None;
+            }
+            dates.sort();
+            let span = *dates.last()? - *dates.first()?;
+            Some(SeriesActivity {
+                url: line.url.clone(),
+                title: line.title.clone(),
+                releases: dates.len(),
+                days_between_releases: (dates.len() > 1)
+                    .then(|| span.num_hours() as f32 / 24.0 / (dates.len() - 1) as f32),
+            })
+        })
+        .collect();
+    activity.sort_by_key(|series| std::cmp::Reverse(series.releases));
+    activity.truncate(MOST_ACTIVE);
+    activity
+}
+
+/// Finds the series without any release for some months, among the ones still read or planned.
+/// A series without any recorded release is stale only if the releases have been recorded for longer than that.
+fn stale_series(
+    lines: &[CSVLine],
+    releases: &[Release],
+    months: u32,
+    now: DateTime<Utc>,
+) -> Vec<StaleSeries> {
+    let Some(cutoff) = now.checked_sub_months(Months::new(months)) else {
+        return Vec::new();
+    };
+    let recorded_since = releases.iter().map(|release| release.detected_at).min();
+    lines
+        .iter()
+        .filter(|line| {
+            !matches!(
+                line.status,
+                ReadingStatus::Completed | ReadingStatus::Dropped
+            )
+        })
+        .filter_map(|line| {
+            let last_release = releases
+                .iter()
+                .filter(|release| release.manga_url == line.url)
+                .map(|release| release.detected_at)
+                .max();
+            let stale = match last_release {
+                Some(last) => last < cutoff,
+                None => recorded_since.is_some_and(|since| since < cutoff),
+            };
+            stale.then(|| StaleSeries {
+                url: line.url.clone(),
+                title: line.title.clone(),
+                last_release,
+            })
+        })
+        .collect()
+}
+
+/// Draws the values as a sparkline, one bar per value, the highest one being full.
+pub fn sparkline(values: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|value| {
+            let level = (value * (BARS.len() - 1) + max / 2)
+                .checked_div(max)
+                .unwrap_or(0);
+            BARS[level]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, release};
+    use chrono::TimeZone;
+
+    fn line(url: &str, chapter: f32, status: ReadingStatus) -> CSVLine {
+        CSVLine {
+            status,
+            ..test_utils::line(url, chapter)
+        }
+    }
+
+    fn date(month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, 12, 0, 0).unwrap()
+    }
+
+    fn read(url: &str, from: f32, to: f32, read_at: DateTime<Utc>) -> ReadingEvent {
+        ReadingEvent {
+            read_at,
+            url: url.to_owned(),
+            title: format!("title {}", url),
+            previous_chapter_num: from,
+            chapter_num: to,
+        }
+    }
+
+    #[test]
+    fn library_stats_are_computed() {
+        // A Wednesday.
+        let now = date(6, 12);
+        let lines = vec![
+            line("a", 10.0, ReadingStatus::Reading),
+            line("b", 4.5, ReadingStatus::Reading),
+            line("c", 20.0, ReadingStatus::OnHold),
+            line("d", 7.0, ReadingStatus::Dropped),
+        ];
+        let history = vec![
+            read("a", 5.0, 10.0, date(6, 10)),
+            read("b", 3.0, 4.5, date(6, 3)),
+            read("c", 19.0, 20.0, date(4, 1)),
+        ];
+        let releases = vec![
+            release("c", 20.0, date(1, 2)),
+            release("a", 9.0, date(5, 1)),
+            release("a", 10.0, date(5, 11)),
+            release("b", 5.0, date(6, 1)),
+        ];
+        let latest = vec![("a".to_owned(), 10.0), ("b".to_owned(), 7.0)];
+        let stats = compute_stats(&lines, &history, &releases, Some(&latest), 3, now);
+
+        assert_eq!(stats.series, 4);
+        assert_eq!(stats.statuses["reading"], 2);
+        assert_eq!(stats.chapters_read, 41);
+        assert_eq!(stats.backlog, Some(3));
+        assert_eq!(stats.backlog_series, Some(1));
+
+        assert_eq!(stats.weekly.len(), PERIODS);
+        assert_eq!(
+            stats.weekly[11].start,
+            NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()
+        );
+        assert_eq!(stats.weekly[11].chapters, 5);
+        assert_eq!(stats.weekly[10].chapters, 1);
+        assert_eq!(
+            stats.monthly[11].start,
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+        );
+        assert_eq!(stats.monthly[11].chapters, 6);
+        assert_eq!(stats.monthly[9].chapters, 1);
+
+        assert_eq!(stats.most_active.len(), 2);
+        assert_eq!(stats.most_active[0].url, "a");
+        assert_eq!(stats.most_active[0].days_between_releases, Some(10.0));
+        assert_eq!(stats.most_active[1].days_between_releases, None);
+
+        // d is dropped, and the releases have been recorded for less than 3 months before b's.
+        assert_eq!(stats.stale.len(), 1);
+        assert_eq!(stats.stale[0].url, "c");
+        assert_eq!(stats.stale[0].last_release, Some(date(1, 2)));
+
+        let offline = compute_stats(&lines, &history, &[], None, 3, now);
+        assert_eq!(offline.backlog, None);
+        assert!(offline.stale.is_empty());
+    }
+
+    #[test]
+    fn sparkline_is_scaled_to_the_highest_value() {
+        assert_eq!(sparkline(&[0, 1, 2, 4, 8]), "▁▂▃▅█");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+    }
+}
@@ -0,0 +1,668 @@
+use crate::file_ops::save::backup_file;
+use crate::file_ops::write_file::{create_file, write_lines};
+use crate::file_ops::{parse_csv, read_csv};
+use crate::merge::{merge_lines, resolve_conflict, Conflict, MergeResult, MergeStrategy};
+use crate::models::CSVLine;
+use crate::output::{ChangeAction, LibraryChange};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// The name of the remote the library is synced with.
+const REMOTE: &str = "origin";
+
+/// The branch created for a new repository.
+const DEFAULT_BRANCH: &str = "main";
+
+/// The git setting naming the synced library, in the config of the repository.
+const LIBRARY_KEY: &str = "manga-updater.library";
+
+/// The name of the merge driver of the library, in the git attributes and config.
+const MERGE_DRIVER: &str = "manga-updater";
+
+/// The author of the commits, used when git doesn't know the user.
+const DEFAULT_AUTHOR: (&str, &str) = ("manga_updater", "manga_updater@localhost");
+
+/// The number of times the push is tried, in case another computer pushed in the meantime.
+const PUSH_ATTEMPTS: usize = 3;
+
+/// What a sync did.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// The number of commits received from the remote.
+    pub pulled: usize,
+    /// The number of commits sent to the remote.
+    pub pushed: usize,
+    /// The entries changed on both sides, resolved by the highest chapter.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// The git repository holding a library, in the folder of the library.
+struct Repository {
+    dir: PathBuf,
+    file: String,
+    verbose: bool,
+}
+
+impl Repository {
+    /// Finds the repository of the library. It may not exist yet.
+    fn of(path: &Path, verbose: bool) -> Repository {
+        let dir = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Repository { dir, file, verbose }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(&self.file)
+    }
+
+    /// Runs a git command in the folder of the library.
+    fn run(&self, args: &[&str]) -> Result<Output, io::Error> {
+        if self.verbose {
+            println!("git {}", args.join(" "));
+        }
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(|e| Error::new(e.kind(), format!("Couldn't run git: {}", e)))
+    }
+
+    /// Runs a git command that must succeed.
+    /// # Returns:
+    /// The trimmed output of the command.
+    /// # Errors:
+    /// `io::Other` with the error printed by git if the command failed.
+    fn git(&self, args: &[&str]) -> Result<String, io::Error> {
+        let output = self.run(args)?;
+        if !output.status.success() {
+            return Err(Error::other(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+
+    /// Runs a git command answering a question, e.g. whether a revision exists.
+    fn check(&self, args: &[&str]) -> Result<bool, io::Error> {
+        Ok(self.run(args)?.status.success())
+    }
+
+    /// Checks that the folder is the root of a repository, and not a folder inside another one.
+    fn exists(&self) -> bool {
+        self.dir.join(".git").exists()
+    }
+
+    fn is_synced(&self) -> bool {
+        self.exists()
+            && self
+                .git(&["config", "--get", LIBRARY_KEY])
+                .is_ok_and(|library| library == self.file)
+    }
+
+    fn branch(&self) -> Result<String, io::Error> {
+        self.git(&["symbolic-ref", "--short", "HEAD"])
+    }
+
+    fn has_revision(&self, revision: &str) -> Result<bool, io::Error> {
+        self.check(&["rev-parse", "--verify", "--quiet", revision])
+    }
+
+    /// Reads the library as it is in a revision. A revision without the library gives an empty one.
+    fn read_version(&self, revision: &str) -> Result<Vec<CSVLine>, io::Error> {
+        let output = self.run(&["show", &format!("{}:{}", revision, self.file)])?;
+        if !output.status.success() {
+            return Ok(Vec::new());
+        }
+        parse_csv(output.stdout.as_slice())
+    }
+
+    /// Commits the library if it has changed.
+    /// # Returns:
+    /// True if a commit has been made.
+    fn commit(&self, message: &str) -> Result<bool, io::Error> {
+        self.git(&["add", "--", &self.file])?;
+        if self.check(&["diff", "--cached", "--quiet", "--", &self.file])? {
+            return Ok(false);
+        }
+        self.git(&["commit", "--quiet", "-m", message, "--", &self.file])?;
+        Ok(true)
+    }
+
+    /// Commits the changes made to the library outside of the program, e.g. by hand or before the sync was set up.
+    fn commit_pending(&self) -> Result<bool, io::Error> {
+        let message = if self.has_revision("HEAD")? {
+            let before = self.read_version("HEAD")?;
+            describe_changes(&LibraryChange::diff(&before, &read_library(&self.path())?))
+        } else {
+            "Start syncing the library".to_owned()
+        };
+        self.commit(&message)
+    }
+
+    fn count_commits(&self, range: &str) -> Result<usize, io::Error> {
+        Ok(self
+            .git(&["rev-list", "--count", range])?
+            .parse()
+            .unwrap_or(0))
+    }
+}
+
+/// Checks if the library is synced, i.e. if its folder is a repository set up by [`init`].
+/// # Argument:
+/// * `path`: the path to the library.
+pub fn is_synced(path: &Path) -> bool {
+    Repository::of(path, false).is_synced()
+}
+
+/// Commits a change made to a synced library. Nothing is done if the library isn't synced.
+/// As the change has already been written, a failed commit is only reported: the change will be committed by the next sync.
+/// # Arguments:
+/// * `path`: the path to the library.
+/// * `message`: the description of the change, e.g. from [`describe_changes`].
+pub fn commit_change(path: &Path, message: &str) {
+    let repository = Repository::of(path, false);
+    if !repository.is_synced() {
+        return;
+    }
+    if let Err(e) = repository.commit(message) {
+        eprintln!(
+            "The change couldn't be committed, it will be by the next sync: {}",
+            e
+        );
+    }
+}
+
+/// Describes the changes made to the library, as the message of a commit.
+/// A single change is described in the subject, e.g. `Update One Piece to chapter 1100`; several changes are counted in the subject and listed in the body.
+/// # Argument:
+/// * `changes`: the changes made to the library.
+/// # Returns:
+/// The message of the commit.
+pub fn describe_changes(changes: &[LibraryChange]) -> String {
+    match changes {
+        [] => "Update the library".to_owned(),
+        [change] => describe_change(change),
+        changes => {
+            let count = |action: ChangeAction| {
+                changes
+                    .iter()
+                    .filter(|change| change.action == action)
+                    .count()
+            };
+            let mut message = format!(
+                "Update the library: {} added, {} updated, {} removed\n",
+                count(ChangeAction::Added),
+                count(ChangeAction::Updated),
+                count(ChangeAction::Removed)
+            );
+            for change in changes {
+                message.push_str(&format!("\n- {}", describe_change(change)));
+            }
+            message
+        }
+    }
+}
+
+fn describe_change(change: &LibraryChange) -> String {
+    let chapter = change.chapter_num.unwrap_or_default();
+    match change.action {
+        ChangeAction::Added => format!("Add {} at chapter {}", change.title, chapter),
+        ChangeAction::Removed => format!("Remove {}", change.title),
+        ChangeAction::Updated if change.previous_chapter_num == change.chapter_num => {
+            format!("Edit {}", change.title)
+        }
+        ChangeAction::Updated => format!("Update {} to chapter {}", change.title, chapter),
+    }
+}
+
+/// Reads a library, an empty file standing for an empty library as git gives for a missing version.
+fn read_library(path: &Path) -> Result<Vec<CSVLine>, io::Error> {
+    if fs::metadata(path)?.len() == 0 {
+        return Ok(Vec::new());
+    }
+    read_csv(&Some(path.to_path_buf()), &false)
+}
+
+/// Merges two versions of the library, resolving the entries changed on both sides by the highest chapter.
+/// # Arguments:
+/// * `base`: the version both sides started from, if any.
+/// * `ours`: the local version.
+/// * `theirs`: the version of the remote.
+fn merge_versions(base: Option<&[CSVLine]>, ours: &[CSVLine], theirs: &[CSVLine]) -> MergeResult {
+    merge_lines(base, theirs, ours, |conflict| {
+        resolve_conflict(MergeStrategy::HighestChapter, conflict, false)
+    })
+}
+
+/// Sets up the sync of the library: its folder becomes a git repository, with the remote and the merge driver, then the library is synced.
+/// If the remote already has a library, e.g. from another computer, both are merged.
+/// The repository must be dedicated to the library: a folder already in a repository not set up by this function is refused.
+/// # Arguments:
+/// * `path`: the path to the library. It is created if needed.
+/// * `remote`: the URL or the path of the remote repository.
+/// * `driver`: the command git runs to merge two versions of the library, with the `%O`, `%A` and `%B` placeholders (See [`merge_files`]).
+/// * `force`: if true, the remote replaces the one already set up, if any.
+/// * `verbose`: if true, the git commands are printed.
+/// # Returns:
+/// What the first sync did.
+/// # Errors:
+/// `io::AlreadyExists` if the folder is in another repository, or if the repository has another remote and `force` is false.
+pub fn init(
+    path: &Path,
+    remote: &str,
+    driver: &str,
+    force: bool,
+    verbose: bool,
+) -> Result<SyncSummary, io::Error> {
+    if !path.exists() {
+        create_file(&Some(path.to_path_buf()))?;
+    }
+    let repository = Repository::of(path, verbose);
+    if repository.exists() {
+        if !repository.check(&["config", "--get", LIBRARY_KEY])? {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!(
+                    "{} is already a git repository, which isn't the one of a library. Move the library to a folder of its own to sync it.",
+                    repository.dir.display()
+                ),
+            ));
+        }
+    } else if repository.check(&["rev-parse", "--show-toplevel"])? {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "{} is inside another git repository. Move the library to a folder of its own to sync it.",
+                repository.dir.display()
+            ),
+        ));
+    } else {
+        repository.git(&["init", "--quiet"])?;
+        repository.git(&[
+            "symbolic-ref",
+            "HEAD",
+            &format!("refs/heads/{}", DEFAULT_BRANCH),
+        ])?;
+    }
+    if repository.check(&["remote", "get-url", REMOTE])? {
+        let current = repository.git(&["remote", "get-url", REMOTE])?;
+        if current != remote {
+            if !force {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "The library is already synced with {}. Add --force to sync it with {} instead.",
+                        current, remote
+                    ),
+                ));
+            }
+            // The branches of the previous remote are forgotten with it, so that the library is pushed to the new one.
+            repository.git(&["remote", "remove", REMOTE])?;
+        }
+    }
+    if !repository.check(&["remote", "get-url", REMOTE])? {
+        repository.git(&["remote", "add", REMOTE, remote])?;
+    }
+    for (key, name) in [
+        ("user.name", DEFAULT_AUTHOR.0),
+        ("user.email", DEFAULT_AUTHOR.1),
+    ] {
+        if !repository.check(&["config", "--get", key])? {
+            repository.git(&["config", key, name])?;
+        }
+    }
+    repository.git(&[
+        "config",
+        &format!("merge.{}.name", MERGE_DRIVER),
+        "manga_updater library merge",
+    ])?;
+    repository.git(&["config", &format!("merge.{}.driver", MERGE_DRIVER), driver])?;
+    add_attributes(&repository)?;
+    repository.git(&["config", LIBRARY_KEY, &repository.file])?;
+    sync(path, verbose)
+}
+
+/// Tells git to merge the library with the driver, in the attributes of the repository that aren't shared.
+fn add_attributes(repository: &Repository) -> Result<(), io::Error> {
+    let info = repository.dir.join(".git").join("info");
+    fs::create_dir_all(&info)?;
+    let attributes = info.join("attributes");
+    let line = format!("/{} merge={}", repository.file, MERGE_DRIVER);
+    let current = fs::read_to_string(&attributes).unwrap_or_default();
+    if !current.lines().any(|existing| existing == line) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(attributes)?;
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Syncs the library: the pending changes are committed, the commits of the remote are merged, then the result is pushed.
+/// The entries changed on both sides are resolved by the highest chapter.
+/// # Arguments:
+/// * `path`: the path to the library.
+/// * `verbose`: if true, the git commands are printed.
+/// # Returns:
+/// What the sync did.
+/// # Errors:
+/// `io::NotFound` if the library isn't synced (See [`init`]), or any error of git.
+pub fn sync(path: &Path, verbose: bool) -> Result<SyncSummary, io::Error> {
+    let repository = Repository::of(path, verbose);
+    if !repository.is_synced() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "The library {} isn't synced. Set it up with: sync init <remote>",
+                path.display()
+            ),
+        ));
+    }
+    repository.commit_pending()?;
+    let branch = repository.branch()?;
+    let upstream = format!("{}/{}", REMOTE, branch);
+    let mut summary = SyncSummary::default();
+    for attempt in 1..=PUSH_ATTEMPTS {
+        repository.git(&["fetch", "--quiet", REMOTE])?;
+        let has_upstream = repository.has_revision(&format!("refs/remotes/{}", upstream))?;
+        if has_upstream {
+            pull(&repository, &upstream, &mut summary)?;
+        }
+        let pushed = repository.count_commits(&if has_upstream {
+            format!("{}..HEAD", upstream)
+        } else {
+            "HEAD".to_owned()
+        })?;
+        if pushed == 0 {
+            return Ok(summary);
+        }
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        let output = repository.run(&["push", "--quiet", "--set-upstream", REMOTE, &refspec])?;
+        if output.status.success() {
+            summary.pushed = pushed;
+            return Ok(summary);
+        }
+        if attempt == PUSH_ATTEMPTS {
+            return Err(Error::other(format!(
+                "git push failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+    }
+    Ok(summary)
+}
+
+/// Merges the commits of the remote into the local branch.
+/// When both sides have new commits, the versions of the library are merged entry by entry, and the result is committed as the merge.
+fn pull(
+    repository: &Repository,
+    upstream: &str,
+    summary: &mut SyncSummary,
+) -> Result<(), io::Error> {
+    let pulled = repository.count_commits(&format!("HEAD..{}", upstream))?;
+    if pulled == 0 {
+        return Ok(());
+    }
+    backup_file(Some(repository.path()))?;
+    summary.pulled += pulled;
+    if repository.check(&["merge-base", "--is-ancestor", "HEAD", upstream])? {
+        repository.git(&["merge", "--quiet", "--ff-only", upstream])?;
+        return Ok(());
+    }
+    let base = match repository.git(&["merge-base", "HEAD", upstream]) {
+        Ok(commit) => Some(repository.read_version(&commit)?),
+        Err(_) => None,
+    };
+    let ours = repository.read_version("HEAD")?;
+    let theirs = repository.read_version(upstream)?;
+    let result = merge_versions(base.as_deref(), &ours, &theirs);
+    // The merge is recorded with the local version, which is then replaced by the merged one.
+    repository.git(&[
+        "merge",
+        "--quiet",
+        "--no-commit",
+        "--strategy",
+        "ours",
+        "--allow-unrelated-histories",
+        upstream,
+    ])?;
+    write_lines(&Some(repository.path()), result.lines)?;
+    repository.git(&["add", "--", &repository.file])?;
+    let mut message = format!("Merge the library from {}", upstream);
+    if !result.conflicts.is_empty() {
+        message.push_str(&format!(
+            "\n\n{} conflict(s) resolved by the highest chapter:",
+            result.conflicts.len()
+        ));
+        for conflict in &result.conflicts {
+            message.push_str(&format!("\n- {}", conflict.title));
+        }
+    }
+    repository.git(&["commit", "--quiet", "-m", &message])?;
+    summary.conflicts.extend(result.conflicts);
+    Ok(())
+}
+
+/// Merges two versions of the library for git, as the merge driver set up by [`init`].
+/// The versions are files written by git, the merged library replacing the local version.
+/// # Arguments:
+/// * `base`: the version both sides started from. It is empty if there is none.
+/// * `ours`: the local version, replaced by the merged one.
+/// * `theirs`: the version being merged.
+/// # Returns:
+/// The number of conflicts resolved by the highest chapter.
+pub fn merge_files(base: &Path, ours: &Path, theirs: &Path) -> Result<usize, io::Error> {
+    let base_lines = if fs::metadata(base)?.len() > 0 {
+        Some(read_library(base)?)
+    } else {
+        None
+    };
+    let result = merge_versions(
+        base_lines.as_deref(),
+        &read_library(ours)?,
+        &read_library(theirs)?,
+    );
+    write_lines(&Some(ours.to_path_buf()), result.lines)?;
+    Ok(result.conflicts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_ops::write_file::update_csv;
+    use crate::test_utils;
+
+    fn line(name: &str, chapter: f32) -> CSVLine {
+        CSVLine {
+            title: name.to_owned(),
+            ..test_utils::line(&format!("http://localhost/{}", name), chapter)
+        }
+    }
+
+    fn computer(dir: &Path, name: &str, lines: Vec<CSVLine>) -> Result<PathBuf, io::Error> {
+        let path = dir.join(name).join("mangas.csv");
+        write_lines(&Some(path.clone()), lines)?;
+        Ok(path)
+    }
+
+    #[test]
+    fn libraries_are_synced_through_a_bare_repository() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_sync_test");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let remote = dir.join("remote.git");
+        let output = Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&remote)
+            .output()?;
+        assert!(output.status.success());
+        let remote = remote.to_string_lossy().into_owned();
+        let first = computer(&dir, "first", vec![line("a", 1.0), line("b", 1.0)])?;
+        let second = computer(&dir, "second", vec![line("b", 3.0), line("c", 1.0)])?;
+
+        let summary = init(&first, &remote, "false", false, false)?;
+        assert_eq!((summary.pulled, summary.pushed), (0, 1));
+        // Both computers had a library: b is in both, with different chapters.
+        let summary = init(&second, &remote, "false", false, false)?;
+        assert_eq!(summary.pulled, 1);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(
+            read_csv(&Some(second.clone()), &false)?,
+            vec![line("b", 3.0), line("c", 1.0), line("a", 1.0)]
+        );
+
+        update_csv(&Some(first.clone()), vec![line("a", 5.0), line("b", 1.0)])?;
+        let repository = Repository::of(&first, false);
+        assert_eq!(
+            repository.git(&["log", "-1", "--format=%s"])?,
+            "Update a to chapter 5"
+        );
+        let summary = sync(&first, false)?;
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(
+            read_csv(&Some(first.clone()), &false)?,
+            vec![line("a", 5.0), line("b", 3.0), line("c", 1.0)]
+        );
+
+        update_csv(&Some(second.clone()), vec![line("b", 4.0), line("a", 1.0)])?;
+        sync(&second, false)?;
+        sync(&first, false)?;
+        let expected = vec![line("b", 4.0), line("a", 5.0)];
+        assert_eq!(read_csv(&Some(first), &false)?, expected);
+        assert_eq!(read_csv(&Some(second.clone()), &false)?, expected);
+        assert_eq!(sync(&second, false)?.pushed, 0);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn init_keeps_the_other_repositories_and_remotes() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_sync_init_test");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let bare = |name: &str| -> Result<String, io::Error> {
+            let remote = dir.join(name);
+            let output = Command::new("git")
+                .args(["init", "--quiet", "--bare"])
+                .arg(&remote)
+                .output()?;
+            assert!(output.status.success());
+            Ok(remote.to_string_lossy().into_owned())
+        };
+        let first = bare("first.git")?;
+        let second = bare("second.git")?;
+
+        // A folder of another repository, or inside one, isn't taken over.
+        let other = computer(&dir, "other", vec![line("a", 1.0)])?;
+        let repository = Repository::of(&other, false);
+        repository.git(&["init", "--quiet"])?;
+        let error = init(&other, &first, "false", true, false).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        let nested = computer(&dir.join("other"), "nested", vec![line("a", 1.0)])?;
+        let error = init(&nested, &first, "false", true, false).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(repository.git(&["remote"])?, "");
+        assert!(!repository.check(&["config", "--get", LIBRARY_KEY])?);
+
+        // The remote of a synced library is only replaced with --force.
+        let library = computer(&dir, "library", vec![line("a", 1.0)])?;
+        init(&library, &first, "false", false, false)?;
+        init(&library, &first, "false", false, false)?;
+        let error = init(&library, &second, "false", false, false).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        let repository = Repository::of(&library, false);
+        assert_eq!(repository.git(&["remote", "get-url", REMOTE])?, first);
+        assert_eq!(init(&library, &second, "false", true, false)?.pushed, 1);
+        assert_eq!(repository.git(&["remote", "get-url", REMOTE])?, second);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn driver_merges_by_highest_chapter() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_merge_driver_test");
+        fs::create_dir_all(&dir)?;
+        let base = dir.join("base.csv");
+        let ours = computer(&dir, "ours", vec![line("a", 2.0), line("b", 1.0)])?;
+        let theirs = computer(&dir, "theirs", vec![line("a", 3.0), line("c", 1.0)])?;
+        fs::write(&base, "")?;
+        assert_eq!(merge_files(&base, &ours, &theirs)?, 1);
+        assert_eq!(
+            read_csv(&Some(ours.clone()), &false)?,
+            vec![line("a", 3.0), line("b", 1.0), line("c", 1.0)]
+        );
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn driver_fails_on_a_malformed_library() -> Result<(), io::Error> {
+        let dir = std::env::temp_dir().join("manga_updater_merge_driver_malformed_test");
+        fs::create_dir_all(&dir)?;
+        let base = dir.join("base.csv");
+        let ours = computer(&dir, "ours", vec![line("a", 2.0)])?;
+        let theirs = dir.join("theirs.csv");
+        fs::write(&base, "")?;
+        fs::write(
+            &theirs,
+            "URL,Last chapter,Title\nhttp://localhost/a,two,a\n",
+        )?;
+        let error = merge_files(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::write(&theirs, "url,chapter\nhttp://localhost/a,2\n")?;
+        let error = merge_files(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // Our version is left as it was.
+        assert_eq!(read_csv(&Some(ours), &false)?, vec![line("a", 2.0)]);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn changes_are_described() {
+        let change = |action, previous, chapter| LibraryChange {
+            action,
+            url: "http://localhost/berserk".to_owned(),
+            title: "Berserk".to_owned(),
+            previous_chapter_num: previous,
+            chapter_num: chapter,
+        };
+        let added = change(ChangeAction::Added, None, Some(1.0));
+        let updated = change(ChangeAction::Updated, Some(1.0), Some(2.5));
+        assert_eq!(
+            describe_changes(std::slice::from_ref(&added)),
+            "Add Berserk at chapter 1"
+        );
+        assert_eq!(
+            describe_changes(std::slice::from_ref(&updated)),
+            "Update Berserk to chapter 2.5"
+        );
+        assert_eq!(
+            describe_changes(&[change(ChangeAction::Updated, Some(2.0), Some(2.0))]),
+            "Edit Berserk"
+        );
+        assert_eq!(
+            describe_changes(&[added, updated, change(ChangeAction::Removed, Some(2.0), None)]),
+            "Update the library: 1 added, 1 updated, 1 removed\n\n- Add Berserk at chapter 1\n- Update Berserk to chapter 2.5\n- Remove Berserk"
+        );
+    }
+}
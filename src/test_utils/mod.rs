@@ -0,0 +1,50 @@
+use crate::models::{CSVLine, MangaChapter, Release};
+use crate::output::NewChapterReport;
+use chrono::{DateTime, Utc};
+
+/// A manga being read, titled after its URL.
+pub fn line(url: &str, chapter: f32) -> CSVLine {
+    CSVLine {
+        url: url.to_owned(),
+        last_chapter_num: chapter,
+        title: format!("title {}", url),
+        status: Default::default(),
+        score: None,
+    }
+}
+
+/// The last chapter of a manga, as found on its page.
+pub fn manga_chapter(url: &str, num: f32) -> MangaChapter {
+    MangaChapter {
+        manga_title: "Title".to_owned(),
+        url: format!("{}/chapter-{}", url, num),
+        chapter_title: format!("Chapter {}", num),
+        num,
+        cover: None,
+    }
+}
+
+/// A chapter of the manga of [`line`], detected at the given time.
+pub fn release(url: &str, chapter: f32, detected_at: DateTime<Utc>) -> Release {
+    Release {
+        detected_at,
+        manga_url: url.to_owned(),
+        manga_title: format!("title {}", url),
+        chapter_num: chapter,
+        chapter_title: format!("Chapter {}", chapter),
+        chapter_url: format!("{}/chapter-{}", url, chapter),
+    }
+}
+
+/// The second chapter of a manga, following the first one read, as announced to the notifiers.
+pub fn new_chapter(manga_title: &str) -> NewChapterReport {
+    NewChapterReport {
+        position: 1,
+        manga_url: "http://localhost/manga-1".to_owned(),
+        manga_title: manga_title.to_owned(),
+        chapter_title: "Chapter 2".to_owned(),
+        chapter_url: "http://localhost/manga-1/chapter-2".to_owned(),
+        num: 2.0,
+        previous_num: 1.0,
+    }
+}
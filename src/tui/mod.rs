@@ -0,0 +1,496 @@
+use crate::models::{CSVLine, LineChapter, MangaChapter, ReadingStatus};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The number of rows skipped by Page Up and Page Down.
+const PAGE_SIZE: usize = 10;
+
+/// The state of the fetch of a manga's page.
+#[derive(Debug, Clone)]
+pub enum FetchState {
+    Pending,
+    Fetched(MangaChapter),
+    Failed(String),
+}
+
+/// A manga of the library, along with the last chapter found online.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The line number of the manga in the CSV, starting at 1.
+    pub position: usize,
+    pub line: CSVLine,
+    pub fetch: FetchState,
+}
+
+impl Entry {
+    /// The number of chapters released since the last one read, once the page has been fetched.
+    fn new_chapters(&self) -> f32 {
+        match &self.fetch {
+            FetchState::Fetched(chapter) => (chapter.num - self.line.last_chapter_num).max(0f32),
+            _ => 0f32,
+        }
+    }
+
+    /// The page to open: the next chapter to read if there are new ones, or the manga's page.
+    fn url_to_open(&self) -> String {
+        match &self.fetch {
+            FetchState::Fetched(chapter) => LineChapter {
+                line: self.line.clone(),
+                chapter: chapter.clone(),
+            }
+            .next_chapter_url()
+            .unwrap_or_else(|| self.line.url.clone()),
+            _ => self.line.url.clone(),
+        }
+    }
+}
+
+/// The column the entries are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Position,
+    Title,
+    NewChapters,
+    Status,
+}
+
+impl SortKey {
+    /// The key following this one, going back to the first after the last.
+    fn next(&self) -> SortKey {
+        match self {
+            SortKey::Position => SortKey::Title,
+            SortKey::Title => SortKey::NewChapters,
+            SortKey::NewChapters => SortKey::Status,
+            SortKey::Status => SortKey::Position,
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortKey::Position => write!(f, "position"),
+            SortKey::Title => write!(f, "title"),
+            SortKey::NewChapters => write!(f, "new chapters"),
+            SortKey::Status => write!(f, "status"),
+        }
+    }
+}
+
+/// An operation asked by the user, carried out by the tui command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Open(String),
+    SetChapter(usize, f32),
+    SetStatus(usize, ReadingStatus),
+    Undo,
+    Refresh,
+    Quit,
+}
+
+/// The state of the terminal UI: the entries, how they are shown, and the selected one.
+pub struct App {
+    entries: Vec<Entry>,
+    sort: SortKey,
+    reverse: bool,
+    filter: String,
+    filtering: bool,
+    table: TableState,
+    /// The message shown at the bottom, e.g. the result of the last action.
+    pub message: Option<String>,
+}
+
+impl App {
+    pub fn new(lines: Vec<CSVLine>) -> App {
+        let mut app = App {
+            entries: Vec::new(),
+            sort: SortKey::Position,
+            reverse: false,
+            filter: String::new(),
+            filtering: false,
+            table: TableState::default(),
+            message: None,
+        };
+        app.set_lines(lines);
+        app
+    }
+
+    /// Replaces the lines of the library, e.g. after they've been written.
+    /// The chapters already fetched are kept for the mangas still present.
+    pub fn set_lines(&mut self, lines: Vec<CSVLine>) {
+        let previous = std::mem::take(&mut self.entries);
+        self.entries = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let fetch = previous
+                    .iter()
+                    .find(|entry| entry.line.url == line.url)
+                    .map(|entry| entry.fetch.clone())
+                    .unwrap_or(FetchState::Pending);
+                Entry {
+                    position: i + 1,
+                    line,
+                    fetch,
+                }
+            })
+            .collect();
+        self.clamp_selection();
+    }
+
+    /// Marks every entry as being fetched again.
+    pub fn reset_fetches(&mut self) {
+        for entry in &mut self.entries {
+            entry.fetch = FetchState::Pending;
+        }
+    }
+
+    /// Stores the result of the fetch of a manga's page.
+    pub fn set_fetched(&mut self, url: &str, result: Result<MangaChapter, String>) {
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.line.url == url)
+        {
+            entry.fetch = match &result {
+                Ok(chapter) => FetchState::Fetched(chapter.clone()),
+                Err(reason) => FetchState::Failed(reason.clone()),
+            };
+        }
+    }
+
+    /// The entries matching the filter, in the sort order.
+    pub fn visible(&self) -> Vec<&Entry> {
+        let filter = self.filter.to_lowercase();
+        let mut visible: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                filter.is_empty()
+                    || entry.line.title.to_lowercase().contains(&filter)
+                    || entry.line.status.to_string().contains(&filter)
+            })
+            .collect();
+        visible.sort_by(|a, b| {
+            let ordering = match self.sort {
+                SortKey::Position => a.position.cmp(&b.position),
+                SortKey::Title => a
+                    .line
+                    .title
+                    .to_lowercase()
+                    .cmp(&b.line.title.to_lowercase()),
+                SortKey::NewChapters => b
+                    .new_chapters()
+                    .partial_cmp(&a.new_chapters())
+                    .unwrap_or(Ordering::Equal),
+                SortKey::Status => ReadingStatus::ALL
+                    .iter()
+                    .position(|status| *status == a.line.status)
+                    .cmp(
+                        &ReadingStatus::ALL
+                            .iter()
+                            .position(|status| *status == b.line.status),
+                    ),
+            };
+            // The position breaks the ties, so that the order is stable when the rows update.
+            let ordering = ordering.then(a.position.cmp(&b.position));
+            if self.reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        visible
+    }
+
+    /// The selected entry, if any.
+    pub fn selected(&self) -> Option<&Entry> {
+        self.table
+            .selected()
+            .and_then(|index| self.visible().get(index).copied())
+    }
+
+    /// Handles a key press.
+    /// # Returns:
+    /// The action to carry out, if the key asks for one.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if self.filtering {
+            match key.code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.table.select(Some(0));
+            self.clamp_selection();
+            return None;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+            KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.table.select(Some(0)),
+            KeyCode::End | KeyCode::Char('G') => self
+                .table
+                .select(Some(self.visible().len().saturating_sub(1))),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.message = Some(format!("Sorted by {}.", self.sort));
+            }
+            KeyCode::Char('S') => self.reverse = !self.reverse,
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char('R') | KeyCode::F(5) => return Some(Action::Refresh),
+            KeyCode::Char('z') => return Some(Action::Undo),
+            KeyCode::Char('o') | KeyCode::Enter => {
+                return self
+                    .selected()
+                    .map(|entry| Action::Open(entry.url_to_open()))
+            }
+            KeyCode::Char('p') => {
+                return self
+                    .selected()
+                    .map(|entry| Action::Open(entry.line.url.clone()))
+            }
+            KeyCode::Char('r') => {
+                return match self.selected() {
+                    Some(Entry {
+                        position,
+                        fetch: FetchState::Fetched(chapter),
+                        ..
+                    }) => Some(Action::SetChapter(*position, chapter.num)),
+                    Some(_) => {
+                        self.message =
+                            Some("The latest chapter of this manga isn't known yet.".to_owned());
+                        None
+                    }
+                    None => None,
+                }
+            }
+            KeyCode::Char('u') => {
+                return self.selected().map(|entry| {
+                    Action::SetChapter(entry.position, entry.line.last_chapter_num - 1f32)
+                })
+            }
+            KeyCode::Char('t') => {
+                return self
+                    .selected()
+                    .map(|entry| Action::SetStatus(entry.position, entry.line.status.next()))
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Draws the table of the entries, along with the help and the last message.
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, footer_area] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(2)]).areas(frame.area());
+        let fetched = self
+            .entries
+            .iter()
+            .filter(|entry| !matches!(entry.fetch, FetchState::Pending))
+            .count();
+        let title = format!(
+            " Manga Updater: {} mangas, {}/{} fetched, sorted by {}{} ",
+            self.entries.len(),
+            fetched,
+            self.entries.len(),
+            self.sort,
+            if self.reverse { " (reversed)" } else { "" }
+        );
+        let rows: Vec<Row> = self
+            .visible()
+            .into_iter()
+            .map(|entry| {
+                let (latest, new, style) = match &entry.fetch {
+                    FetchState::Pending => ("...".to_owned(), String::new(), Style::default()),
+                    FetchState::Failed(_) => (
+                        "error".to_owned(),
+                        String::new(),
+                        Style::default().fg(Color::Red),
+                    ),
+                    FetchState::Fetched(chapter) if entry.new_chapters() > 0f32 => (
+                        chapter.num.to_string(),
+                        format!("+{}", entry.new_chapters()),
+                        Style::default().fg(Color::Green),
+                    ),
+                    FetchState::Fetched(chapter) => {
+                        (chapter.num.to_string(), String::new(), Style::default())
+                    }
+                };
+                Row::new(vec![
+                    Cell::from(entry.position.to_string()),
+                    Cell::from(entry.line.title.clone()),
+                    Cell::from(entry.line.last_chapter_num.to_string()),
+                    Cell::from(latest),
+                    Cell::from(new),
+                    Cell::from(entry.line.status.to_string()),
+                ])
+                .style(style)
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(6),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(vec!["#", "Title", "Read", "Latest", "New", "Status"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let status_line = if self.filtering {
+            format!("Filter: {}_", self.filter)
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else if let Some(Entry {
+            fetch: FetchState::Failed(reason),
+            ..
+        }) = self.selected()
+        {
+            reason.clone()
+        } else if !self.filter.is_empty() {
+            format!("Filter: {}", self.filter)
+        } else {
+            String::new()
+        };
+        let help = "o: open next  p: open page  r: read  u: unread  t: status  s/S: sort  /: filter  z: undo  R: refresh  q: quit";
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(status_line), Line::from(help)]),
+            footer_area,
+        );
+    }
+
+    /// Moves the selection up or down, staying in the visible rows.
+    fn move_selection(&mut self, offset: isize) {
+        let count = self.visible().len();
+        if count == 0 {
+            self.table.select(None);
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let next = (current + offset).clamp(0, count as isize - 1);
+        self.table.select(Some(next as usize));
+    }
+
+    /// Keeps the selection on a visible row, e.g. after the filter has changed.
+    fn clamp_selection(&mut self) {
+        let count = self.visible().len();
+        if count == 0 {
+            self.table.select(None);
+        } else {
+            let current = self.table.selected().unwrap_or(0);
+            self.table.select(Some(current.min(count - 1)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, manga_chapter as chapter};
+
+    fn line(url: &str, chapter: f32, title: &str) -> CSVLine {
+        CSVLine {
+            title: title.to_owned(),
+            ..test_utils::line(url, chapter)
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn app() -> App {
+        App::new(vec![
+            line("url1", 3.0, "Berserk"),
+            line("url2", 10.0, "Akira"),
+            line("url3", 1.0, "Claymore"),
+        ])
+    }
+
+    #[test]
+    fn rows_are_sorted_and_filtered() {
+        let mut app = app();
+        app.set_fetched("url3", Ok(chapter("url3", 5.0)));
+        app.handle_key(key(KeyCode::Char('s')));
+        let titles: Vec<&str> = app
+            .visible()
+            .iter()
+            .map(|e| e.line.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Akira", "Berserk", "Claymore"]);
+
+        app.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(app.visible()[0].line.url, "url3");
+
+        app.handle_key(key(KeyCode::Char('/')));
+        for c in "ber".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(app.visible().len(), 1);
+        assert_eq!(app.selected().unwrap().line.url, "url1");
+    }
+
+    #[test]
+    fn keys_ask_for_actions() {
+        let mut app = app();
+        app.set_fetched("url1", Ok(chapter("url1", 6.0)));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('o'))),
+            Some(Action::Open("url1/chapter-4".to_owned()))
+        );
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('r'))),
+            Some(Action::SetChapter(1, 6.0))
+        );
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('t'))),
+            Some(Action::SetStatus(1, ReadingStatus::PlanToRead))
+        );
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.handle_key(key(KeyCode::Char('r'))), None);
+        assert!(app.message.is_some());
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('u'))),
+            Some(Action::SetChapter(2, 9.0))
+        );
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), Some(Action::Quit));
+    }
+
+    #[test]
+    fn fetched_chapters_survive_a_reload() {
+        let mut app = app();
+        app.set_fetched("url2", Ok(chapter("url2", 12.0)));
+        app.set_lines(vec![line("url2", 12.0, "Akira")]);
+        assert!(matches!(app.visible()[0].fetch, FetchState::Fetched(_)));
+        assert_eq!(app.visible()[0].new_chapters(), 0.0);
+    }
+}
@@ -0,0 +1,144 @@
+/// The hosts of the Manganato family, which share the IDs of their series.
+const MANGANATO_HOSTS: [&str; 5] = [
+    "manganato.com",
+    "readmanganato.com",
+    "chapmanganato.com",
+    "chapmanganato.to",
+    "manganelo.com",
+];
+
+/// Gives the canonical form of the URL of a manga, the same for every variant of the URL.
+/// For the Manganato family of websites, it is the ID of the series, e.g. `manganato:manga-ab123`, so that the page of the manga on any of them, or one of its chapters, gives the same form.
+/// For the other websites, it is the URL without the scheme, the `www.` prefix, the fragment and the trailing slash, with a lowercase host.
+/// # Argument:
+/// * `url`: the URL of the manga.
+/// # Returns:
+/// The canonical form, only meant to be compared with other canonical forms.
+pub fn canonical_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split('#').next().unwrap_or(url);
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let path = path.trim_end_matches('/');
+    if MANGANATO_HOSTS.contains(&host) {
+        if let Some(series) = path
+            .split('/')
+            .find(|segment| segment.starts_with("manga-"))
+        {
+            return format!("manganato:{}", series.to_lowercase());
+        }
+    }
+    format!("{}/{}", host, path)
+}
+
+/// Tells if two URLs lead to the same manga, i.e. if they have the same canonical form (See [`canonical_url`]).
+pub fn same_manga(first: &str, second: &str) -> bool {
+    first == second || canonical_url(first) == canonical_url(second)
+}
+
+/// Gives the address of the page of a manga from a supported website, i.e. the Manganato family, from any URL of the manga on it.
+/// # Argument:
+/// * `url`: the URL of the manga, or of one of its chapters.
+/// # Returns:
+/// The HTTPS URL of the page of the manga, e.g. `https://chapmanganato.to/manga-ab123`, None if the website isn't supported or the URL has no series ID.
+pub fn manga_page_url(url: &str) -> Option<String> {
+    let rest = url.trim().split_once("://")?.1;
+    let rest = rest.split(['#', '?']).next().unwrap_or(rest);
+    let (host, path) = rest.split_once('/')?;
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if !MANGANATO_HOSTS.contains(&host) {
+        return None;
+    }
+    let series = path
+        .split('/')
+        .find(|segment| segment.len() > "manga-".len() && segment.starts_with("manga-"))?;
+    Some(format!("https://{}/{}", host, series.to_lowercase()))
+}
+
+/// Finds the web addresses in a text, e.g. pasted bookmarks, a chat message or an HTML page.
+/// An address starts with `http://` or `https://`, and ends at a space, a quote or a bracket. The punctuation right after it is left out.
+/// # Argument:
+/// * `text`: the text to search.
+/// # Returns:
+/// The addresses, in the order of the text.
+pub fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        let scheme = ["https://", "http://"]
+            .into_iter()
+            .find(|scheme| candidate.starts_with(scheme));
+        let Some(scheme) = scheme else {
+            rest = &candidate["http".len()..];
+            continue;
+        };
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || "\"'<>()[]{}|\\`".contains(c))
+            .unwrap_or(candidate.len());
+        let url = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if url.len() > scheme.len() {
+            urls.push(url.to_owned());
+        }
+        rest = &candidate[end.max(scheme.len())..];
+    }
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_of_a_url_are_the_same_manga() {
+        assert!(same_manga(
+            "https://manganato.com/manga-ab123",
+            "http://www.manganato.com/manga-ab123/"
+        ));
+        assert!(same_manga(
+            "https://readmanganato.com/manga-ab123",
+            "https://chapmanganato.to/manga-ab123/chapter-5"
+        ));
+        assert!(!same_manga(
+            "https://manganato.com/manga-ab1",
+            "https://manganato.com/manga-ab12"
+        ));
+        assert_eq!(
+            canonical_url("https://www.Example.com/series/one/#chapters"),
+            "example.com/series/one"
+        );
+        assert!(!same_manga(
+            "https://example.com/series/one",
+            "https://other.com/series/one"
+        ));
+    }
+
+    #[test]
+    fn urls_are_extracted_from_pasted_text() {
+        let text = "My list:\n- https://manganato.com/manga-ab123, great!\n\
+            * [Other](http://www.readmanganato.com/manga-cd456/chapter-12)\n\
+            <a href=\"https://example.com/series/one\">one</a> httpfoo and https://\n";
+        let urls = extract_urls(text);
+        assert_eq!(
+            urls,
+            vec![
+                "https://manganato.com/manga-ab123",
+                "http://www.readmanganato.com/manga-cd456/chapter-12",
+                "https://example.com/series/one"
+            ]
+        );
+        let pages: Vec<Option<String>> = urls.iter().map(|url| manga_page_url(url)).collect();
+        assert_eq!(
+            pages,
+            vec![
+                Some("https://manganato.com/manga-ab123".to_owned()),
+                Some("https://readmanganato.com/manga-cd456".to_owned()),
+                None
+            ]
+        );
+        assert_eq!(manga_page_url("https://manganato.com/genre-all"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::CSVLine;
+use std::time::Duration;
 use std::{error, fmt};
 
 #[derive(Debug, Clone)]
@@ -54,9 +55,82 @@ pub fn update_chapters_multiple(original: Vec<CSVLine>, updated: Vec<CSVLine>) -
         .collect()
 }
 
+/// Parses a human-readable duration, such as `2h`, `30m` or `1h30m`.
+/// The supported units are `d` (days), `h` (hours), `m` (minutes) and `s` (seconds).
+/// # Argument:
+/// * `input`: the duration to parse.
+/// # Returns:
+/// The parsed duration, or an error message if the input is invalid.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let multiplier = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("Unknown unit {} in duration {}.", c, input)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("A number is missing before {} in duration {}.", c, input))?;
+        total += value * multiplier;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!(
+            "The duration {} must end with a unit (d, h, m or s).",
+            input
+        ));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Escapes the special HTML characters of a text, so that it can be put in a page or in an attribute.
+/// # Argument:
+/// * `text`: the text to escape.
+/// # Returns:
+/// The escaped text.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Waits for Ctrl-C, or SIGTERM on Unix systems.
+pub async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {},
+                    _ = tokio::signal::ctrl_c() => {},
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::ReadingStatus;
 
     #[test]
     fn update_chapter_in_vec_test() {
@@ -65,21 +139,29 @@ mod tests {
             url: "url1".to_owned(),
             last_chapter_num: 0.0,
             title: "title1".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let line2 = CSVLine {
             url: "url2".to_owned(),
             last_chapter_num: 1.0,
             title: "title2".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let line3 = CSVLine {
             url: "url3".to_owned(),
             last_chapter_num: 2.0,
             title: "title3".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         let new_line2 = CSVLine {
             url: "url2".to_owned(),
             last_chapter_num: 3.0,
             title: "title2".to_owned(),
+            status: ReadingStatus::Reading,
+            score: None,
         };
         original.push(line1);
         original.push(line2);
@@ -90,4 +172,24 @@ mod tests {
         assert_eq!(new_vec.get(1).unwrap().url, "url2");
         assert_eq!(new_vec.get(1).unwrap().last_chapter_num, 3.0);
     }
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7_200)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert!(parse_duration("12").is_err());
+        assert!(parse_duration("2w").is_err());
+        assert_eq!(parse_duration("0m"), Ok(Duration::ZERO));
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn escape_html_test() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
 }